@@ -0,0 +1,224 @@
+use clap::{ArgGroup, Parser, Subcommand};
+use std::{error::Error, path::PathBuf, process::ExitCode};
+use tracing_subscriber::EnvFilter;
+use v3_history_core::{
+    edit_script::EditScript,
+    get_state_populations::Pop,
+    project::{province_key, state_key, Project},
+};
+
+/// Edits Victoria 3 map history without the editor window, and writes the result as a mod.
+#[derive(Parser)]
+#[command(name = "v3-history-cli", version)]
+struct Cli {
+    /// Victoria 3 install folder, the one containing `game`
+    #[arg(long)]
+    game: PathBuf,
+
+    /// Mod folder to load on top of the game, repeat in load order
+    #[arg(long = "mod")]
+    mods: Vec<PathBuf>,
+
+    /// Folder the mod is written to. Whatever is already there is loaded on top of the other
+    /// mods, so edits can be chained across runs
+    #[arg(long)]
+    working_dir: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Transfer a state to another country
+    TransferState {
+        #[arg(long)]
+        state: String,
+        #[arg(long)]
+        to: String,
+        /// Needed when the state is split between several countries
+        #[arg(long)]
+        from: Option<String>,
+    },
+    /// Transfer a single province to another country
+    TransferProvince {
+        #[arg(long)]
+        province: String,
+        #[arg(long)]
+        to: String,
+    },
+    /// Transfer every state in a strategic region to another country, whoever owns them
+    TransferRegion {
+        /// e.g. `sr:region_scandinavia` or `region_scandinavia`
+        #[arg(long)]
+        region: String,
+        #[arg(long)]
+        to: String,
+    },
+    /// Create a country out of a state or a province
+    #[command(group(ArgGroup::new("source").required(true).args(["state", "province"])))]
+    CreateCountry {
+        #[arg(long)]
+        tag: String,
+        #[arg(long)]
+        state: Option<String>,
+        #[arg(long)]
+        province: Option<String>,
+        /// Needed when the state is split between several countries
+        #[arg(long, requires = "state")]
+        from: Option<String>,
+    },
+    /// Replace a country's pops in a state with the pops in a JSON file
+    SetPops {
+        #[arg(long)]
+        country: String,
+        #[arg(long)]
+        state: String,
+        /// JSON array of pops, each with `culture`, `size` and optional `religion` and `pop_type`
+        #[arg(long)]
+        pops: PathBuf,
+    },
+    /// Set a country's starting technology
+    SetTechs {
+        #[arg(long)]
+        country: String,
+        /// Starting tier, e.g. `tier_3`, the country keeps its tier without it
+        #[arg(long)]
+        base_tech: Option<String>,
+        /// Technology researched on top of the tier, repeat for more
+        #[arg(long = "tech")]
+        technologies: Vec<String>,
+    },
+    /// Move provinces into another state region, taking their pops and buildings along
+    MoveToStateRegion {
+        /// Repeat for more, e.g. `x1A2B3C`
+        #[arg(long = "province", required = true)]
+        provinces: Vec<String>,
+        #[arg(long)]
+        state: String,
+    },
+    /// Split provinces off a state region into a new one
+    SplitStateRegion {
+        #[arg(long)]
+        state: String,
+        /// Repeat for more
+        #[arg(long = "province", required = true)]
+        provinces: Vec<String>,
+        /// Key of the new state region, e.g. `STATE_GOTALAND`
+        #[arg(long)]
+        new_state: String,
+    },
+    /// Merge a state region into another
+    MergeStateRegions {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        into: String,
+    },
+    /// Apply every edit in a JSON or TOML edit script, or none of them if one fails
+    Apply { script: PathBuf },
+    /// Write the mod again without editing anything
+    Save,
+}
+
+fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .init();
+
+    match run(Cli::parse()) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let mut mods = cli.mods;
+    if cli.working_dir.exists() {
+        mods.push(cli.working_dir.clone());
+    }
+    let mut project = Project::open(cli.game, mods);
+
+    match cli.command {
+        Command::TransferState { state, to, from } => {
+            project.transfer_state(&state_key(&state), from.as_deref(), &to)?
+        }
+        Command::TransferProvince { province, to } => {
+            project.transfer_province(&province_key(&province), &to)?
+        }
+        Command::TransferRegion { region, to } => {
+            project.transfer_strategic_region(&region, &to)?;
+        }
+        Command::CreateCountry {
+            tag,
+            state,
+            province,
+            from,
+        } => match (state, province) {
+            (_, Some(province)) => {
+                project.create_country_from_province(&tag, &province_key(&province))?
+            }
+            (Some(state), None) => {
+                project.create_country(&tag, &state_key(&state), from.as_deref())?
+            }
+            (None, None) => unreachable!("clap requires a state or a province"),
+        },
+        Command::SetPops {
+            country,
+            state,
+            pops,
+        } => {
+            let pops: Vec<Pop> = serde_json::from_str(&std::fs::read_to_string(pops)?)?;
+            project.set_pops(&country, &state_key(&state), pops)?
+        }
+        Command::SetTechs {
+            country,
+            base_tech,
+            technologies,
+        } => project.set_technologies(&country, base_tech, technologies)?,
+        Command::MoveToStateRegion { provinces, state } => {
+            let provinces: Vec<String> = provinces.iter().map(|p| province_key(p)).collect();
+            let report = project.move_to_state_region(&provinces, &state_key(&state))?;
+            print_notes(&report.notes);
+        }
+        Command::SplitStateRegion {
+            state,
+            provinces,
+            new_state,
+        } => {
+            let provinces: Vec<String> = provinces.iter().map(|p| province_key(p)).collect();
+            let report = project.split_state_region(
+                &state_key(&state),
+                &provinces,
+                &state_key(&new_state),
+            )?;
+            print_notes(&report.notes);
+        }
+        Command::MergeStateRegions { from, into } => {
+            let report = project.merge_state_regions(&state_key(&from), &state_key(&into))?;
+            print_notes(&report.notes);
+        }
+        Command::Apply { script } => {
+            let report = project.apply_script(&EditScript::read(&script)?)?;
+            print!("{}", report);
+        }
+        Command::Save => {}
+    }
+
+    project.save(&cli.working_dir);
+    println!("Wrote mod to {}", cli.working_dir.display());
+    Ok(())
+}
+
+// Hubs and naval exits that had to change along with a state region's borders.
+fn print_notes(notes: &[String]) {
+    for note in notes {
+        println!("{}", note);
+    }
+}
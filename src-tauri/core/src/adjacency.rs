@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::Path,
+};
+use tracing::{instrument, warn};
+
+use crate::{
+    country::Country, labels::place_label, project::province_key, province_map_to_geojson::Coords,
+    topology::Topology,
+};
+
+/// Which provinces, or groups of them, share a border, and how long it is in pixel edges.
+/// Provinces that only touch at a corner are not neighbours, unless a crossing joins them.
+#[derive(Default)]
+pub struct Adjacency {
+    pub borders: HashMap<String, HashMap<String, u32>>,
+    pub crossings: Vec<Crossing>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Neighbour {
+    pub name: String,
+    pub border_length: u32,
+    /// How the two are joined when it is not only by their border.
+    pub crossing: Option<CrossingKind>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossingKind {
+    Sea,
+    River,
+    Lake,
+    Canal,
+    /// Separates provinces that share a border rather than joining them.
+    Impassable,
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// A special adjacency from `map_data/adjacencies.csv`, such as a strait across the sea
+/// province `through`. Start and stop are in map coordinates when the file gives them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Crossing {
+    pub from: String,
+    pub to: String,
+    pub kind: CrossingKind,
+    pub through: Option<String>,
+    pub start: Option<(f32, f32)>,
+    pub stop: Option<(f32, f32)>,
+    pub comment: String,
+}
+
+/// A crossing with the line to draw it along.
+#[derive(Serialize, Debug, Clone)]
+pub struct CrossingLine {
+    pub crossing: Crossing,
+    pub line: Vec<(f32, f32)>,
+}
+
+impl CrossingKind {
+    fn parse(kind: &str) -> CrossingKind {
+        match kind.to_lowercase().as_str() {
+            "sea" => CrossingKind::Sea,
+            "river" => CrossingKind::River,
+            "lake" => CrossingKind::Lake,
+            "canal" => CrossingKind::Canal,
+            "impassable" => CrossingKind::Impassable,
+            kind => CrossingKind::Other(kind.to_string()),
+        }
+    }
+
+    pub fn is_passable(&self) -> bool {
+        *self != CrossingKind::Impassable
+    }
+}
+
+impl Crossing {
+    /// Reads the semicolon separated rows after the header, up to the `-1` row that ends the
+    /// file. Rows whose provinces can't be read are skipped.
+    #[instrument]
+    pub fn parse_from(path: &Path) -> Vec<Crossing> {
+        let Ok(contents) = std::fs::read_to_string(path) else {
+            return vec![];
+        };
+        let mut crossings = vec![];
+        for line in contents.lines().skip(1) {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            if fields[0] == "-1" {
+                break;
+            }
+            let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+            let (Some(from), Some(to)) = (province_name(field(0)), province_name(field(1))) else {
+                warn!(line, "Skipping adjacency without two provinces");
+                continue;
+            };
+            let point = |x: &str, y: &str| match (x.parse::<f32>(), y.parse::<f32>()) {
+                (Ok(x), Ok(y)) if x >= 0.0 && y >= 0.0 => Some((x, y)),
+                _ => None,
+            };
+            crossings.push(Crossing {
+                from,
+                to,
+                kind: CrossingKind::parse(field(2)),
+                through: province_name(field(3)),
+                start: point(field(4), field(5)),
+                stop: point(field(6), field(7)),
+                comment: field(8).to_string(),
+            });
+        }
+        crossings
+    }
+}
+
+// Provinces are written as their colour, `x` or `0x` and six hex digits, in either case.
+fn province_name(field: &str) -> Option<String> {
+    let hex = field
+        .strip_prefix("0x")
+        .or_else(|| field.strip_prefix("0X"))
+        .or_else(|| field.strip_prefix(['x', 'X']))?;
+    match hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Some(province_key(hex)),
+        false => None,
+    }
+}
+
+impl Topology {
+    #[instrument(skip_all)]
+    pub fn adjacency(&self) -> Adjacency {
+        let mut adjacency = Adjacency::default();
+        for (arc, sides) in self.arcs.iter().zip(self.arc_sides()) {
+            let [Some(left), Some(right)] = sides else {
+                continue;
+            };
+            let length = arc
+                .windows(2)
+                .map(|pair| pair[0].0.abs_diff(pair[1].0) + pair[0].1.abs_diff(pair[1].1))
+                .sum();
+            adjacency.add_border(left, right, length);
+        }
+        adjacency
+    }
+}
+
+impl Adjacency {
+    pub fn with_crossings(self, crossings: Vec<Crossing>) -> Adjacency {
+        Adjacency { crossings, ..self }
+    }
+
+    /// Longest border first, then the ones only joined by a crossing. Impassable crossings
+    /// still share a border, so they are listed with it.
+    pub fn neighbours(&self, name: &str) -> Vec<Neighbour> {
+        let mut neighbours: HashMap<&str, Neighbour> = self
+            .borders
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|(neighbour, border_length)| {
+                let info = Neighbour {
+                    name: neighbour.clone(),
+                    border_length: *border_length,
+                    crossing: None,
+                };
+                (neighbour.as_str(), info)
+            })
+            .collect();
+        for crossing in self.crossings.iter() {
+            let other = match (crossing.from == name, crossing.to == name) {
+                (true, false) => &crossing.to,
+                (false, true) => &crossing.from,
+                _ => continue,
+            };
+            neighbours
+                .entry(other.as_str())
+                .or_insert_with(|| Neighbour {
+                    name: other.clone(),
+                    border_length: 0,
+                    crossing: None,
+                })
+                .crossing = Some(crossing.kind.clone());
+        }
+
+        let mut neighbours: Vec<Neighbour> = neighbours.into_values().collect();
+        neighbours.sort_by(|a, b| {
+            b.border_length
+                .cmp(&a.border_length)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        neighbours
+    }
+
+    pub fn are_neighbours(&self, a: &str, b: &str) -> bool {
+        self.borders
+            .get(a)
+            .is_some_and(|borders| borders.contains_key(b))
+    }
+
+    /// Groups of `provinces` that can be reached from each other over shared borders and
+    /// crossings without leaving them, largest first. Territory in more than one group is not
+    /// connected.
+    pub fn territories(&self, provinces: &[String]) -> Vec<Vec<String>> {
+        let provinces: HashSet<&str> = provinces.iter().map(String::as_str).collect();
+        let mut links: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut blocked: HashSet<(&str, &str)> = HashSet::new();
+        for crossing in self.crossings.iter() {
+            let (from, to) = (crossing.from.as_str(), crossing.to.as_str());
+            match crossing.kind.is_passable() {
+                true => {
+                    links.entry(from).or_default().push(to);
+                    links.entry(to).or_default().push(from);
+                }
+                false => {
+                    blocked.insert((from, to));
+                    blocked.insert((to, from));
+                }
+            }
+        }
+
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut territories = vec![];
+        for start in provinces.iter().copied().collect::<BTreeSet<_>>() {
+            if !seen.insert(start) {
+                continue;
+            }
+            let mut territory = vec![start];
+            let mut queue = vec![start];
+            while let Some(province) = queue.pop() {
+                let bordering = self
+                    .borders
+                    .get(province)
+                    .into_iter()
+                    .flat_map(|borders| borders.keys().map(String::as_str))
+                    .filter(|neighbour| !blocked.contains(&(province, *neighbour)));
+                let crossed = links.get(province).into_iter().flatten().copied();
+                for neighbour in bordering.chain(crossed) {
+                    if provinces.contains(neighbour) && seen.insert(neighbour) {
+                        territory.push(neighbour);
+                        queue.push(neighbour);
+                    }
+                }
+            }
+            let mut territory: Vec<String> = territory.into_iter().map(str::to_string).collect();
+            territory.sort();
+            territories.push(territory);
+        }
+        territories.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        territories
+    }
+
+    /// The crossings as lines between their start and stop, or between the middle of the two
+    /// provinces when the file leaves them out. Crossings between provinces without a shape
+    /// are left out.
+    pub fn crossing_lines(&self, province_coords: &HashMap<String, Coords>) -> Vec<CrossingLine> {
+        let mut anchors: HashMap<String, Option<(f32, f32)>> = HashMap::new();
+        let mut anchor = |province: &str| {
+            *anchors.entry(province.to_string()).or_insert_with(|| {
+                province_coords
+                    .get(province)
+                    .and_then(|coords| place_label(coords, ""))
+                    .map(|label| label.anchor)
+            })
+        };
+        self.crossings
+            .iter()
+            .filter_map(|crossing| {
+                let start = crossing.start.or_else(|| anchor(&crossing.from))?;
+                let stop = crossing.stop.or_else(|| anchor(&crossing.to))?;
+                Some(CrossingLine {
+                    crossing: crossing.clone(),
+                    line: vec![start, stop],
+                })
+            })
+            .collect()
+    }
+
+    /// Adjacency between groups of provinces, such as states or countries, adding up the
+    /// borders of their provinces. A province in several groups counts for the last one, and
+    /// provinces in none are left out.
+    pub fn grouped<'a>(
+        &self,
+        groups: impl IntoIterator<Item = (String, &'a [String])>,
+    ) -> Adjacency {
+        let mut group_of: HashMap<&str, String> = HashMap::new();
+        for (group, provinces) in groups {
+            for province in provinces {
+                group_of.insert(province, group.clone());
+            }
+        }
+
+        let mut adjacency = Adjacency::default();
+        for (province, borders) in self.borders.iter() {
+            let Some(group) = group_of.get(province.as_str()) else {
+                continue;
+            };
+            for (neighbour, length) in borders {
+                match group_of.get(neighbour.as_str()) {
+                    // Each border is seen from both sides, so only one adds it.
+                    Some(neighbour_group) if neighbour_group != group && province < neighbour => {
+                        adjacency.add_border(group, neighbour_group, *length)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        // Only crossings between groups join them, and an impassable one doesn't.
+        adjacency.crossings = self
+            .crossings
+            .iter()
+            .filter(|crossing| crossing.kind.is_passable())
+            .filter_map(|crossing| {
+                let from = group_of.get(crossing.from.as_str())?;
+                let to = group_of.get(crossing.to.as_str())?;
+                (from != to).then(|| Crossing {
+                    from: from.clone(),
+                    to: to.clone(),
+                    ..crossing.clone()
+                })
+            })
+            .collect();
+        adjacency
+    }
+
+    pub fn states(&self, countries: &[Country]) -> Adjacency {
+        let mut state_provinces: HashMap<String, Vec<String>> = HashMap::new();
+        for state in countries.iter().flat_map(|country| country.states.iter()) {
+            state_provinces
+                .entry(state.name.clone())
+                .or_default()
+                .extend(state.provinces.iter().cloned());
+        }
+        self.grouped(
+            state_provinces
+                .iter()
+                .map(|(state, provinces)| (state.clone(), provinces.as_slice())),
+        )
+    }
+
+    pub fn countries(&self, countries: &[Country]) -> Adjacency {
+        let country_provinces: Vec<(String, Vec<String>)> = countries
+            .iter()
+            .map(|country| {
+                let provinces = country
+                    .states
+                    .iter()
+                    .flat_map(|state| state.provinces.iter().cloned())
+                    .collect();
+                (country.name.clone(), provinces)
+            })
+            .collect();
+        self.grouped(
+            country_provinces
+                .iter()
+                .map(|(tag, provinces)| (tag.clone(), provinces.as_slice())),
+        )
+    }
+
+    fn add_border(&mut self, a: &str, b: &str, length: u32) {
+        for (from, to) in [(a, b), (b, a)] {
+            *self
+                .borders
+                .entry(from.to_string())
+                .or_default()
+                .entry(to.to_string())
+                .or_default() += length;
+        }
+    }
+}
+
+/// A province, state or country to find the neighbours of, e.g. `{ "type": "state", "name":
+/// "s:STATE_SVEALAND" }`.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", content = "name", rename_all = "snake_case")]
+pub enum Region {
+    Province(String),
+    State(String),
+    Country(String),
+}
+
+impl Adjacency {
+    /// States and countries are grouped by who owns what in `countries`.
+    pub fn neighbours_of(&self, region: &Region, countries: &[Country]) -> Vec<Neighbour> {
+        match region {
+            Region::Province(province) => self.neighbours(province),
+            Region::State(state) => self.states(countries).neighbours(state),
+            Region::Country(tag) => self.countries(countries).neighbours(tag),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn province_names_accept_x_and_0x_in_either_case() {
+        for field in ["x1a2b3c", "X1A2B3C", "0x1a2b3c", "0X1A2B3C"] {
+            assert_eq!(
+                province_name(field).as_deref(),
+                Some("x1A2B3C"),
+                "{}",
+                field
+            );
+        }
+    }
+
+    #[test]
+    fn fields_that_are_not_colours_are_not_provinces() {
+        for field in [
+            "",
+            "1A2B3C",
+            "x12345",
+            "x1234567",
+            "0x12G456",
+            "00x123456",
+            "-1",
+        ] {
+            assert_eq!(province_name(field), None, "{}", field);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use image::{imageops, imageops::FilterType, RgbaImage};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tracing::instrument;
+
+use crate::{
+    dds_to_png::{DdsError, DdsToPng},
+    tiles::TilePyramid,
+};
+
+pub const COMPOSITE_FILE_NAME: &str = "background.png";
+pub const FLATMAP_LAYER_FILE_NAME: &str = "background-flatmap.png";
+pub const LAND_LAYER_FILE_NAME: &str = "background-land.png";
+pub const OVERLAY_LAYER_FILE_NAME: &str = "background-overlay.png";
+pub const TILES_DIR_NAME: &str = "tiles";
+
+// The parchment the game tints land with on the flat map, and how strongly.
+const LAND_TINT: [u8; 3] = [224, 201, 160];
+const LAND_TINT_STRENGTH: f32 = 0.55;
+
+/// The game's flat map textures, blended into the one image the map is drawn over.
+pub struct BackgroundTextures {
+    pub flatmap: PathBuf,
+    pub land_mask: PathBuf,
+    pub overlay: PathBuf,
+}
+
+/// Cached background images. `layers` are the flatmap, the land tint and the overlay on their
+/// own, bottom first, for showing them separately. `tiles` are the pyramids of the composite and
+/// then the layers, once they have been cut, named after their images.
+#[derive(Serialize, Debug, Clone)]
+pub struct Background {
+    pub composite: PathBuf,
+    pub layers: Vec<PathBuf>,
+    pub tiles: Vec<TilePyramid>,
+}
+
+impl Background {
+    /// The background cached in `cache_dir`, if it has been composited.
+    pub fn cached(cache_dir: &Path) -> Option<Background> {
+        let mut background = Background::in_dir(cache_dir);
+        if !background.composite.exists() {
+            return None;
+        }
+        let tiles_dir = cache_dir.join(TILES_DIR_NAME);
+        background.tiles = background
+            .images()
+            .filter_map(|path| TilePyramid::cached(&tiles_dir, &pyramid_name(path)))
+            .collect();
+        Some(background)
+    }
+
+    /// Cuts the composite and each layer into tile pyramids scaled to the map's `extent`, side
+    /// by side, leaving the ones already cached at that extent as they are.
+    #[instrument(skip(self))]
+    pub fn tile(mut self, extent: (u32, u32), cache_dir: &Path) -> Background {
+        let tiles_dir = cache_dir.join(TILES_DIR_NAME);
+        self.tiles = std::thread::scope(|scope| {
+            let pyramids: Vec<_> = self
+                .images()
+                .map(|path| {
+                    let tiles_dir = &tiles_dir;
+                    scope.spawn(move || {
+                        let name = pyramid_name(path);
+                        TilePyramid::cached(tiles_dir, &name)
+                            .filter(|pyramid| (pyramid.width, pyramid.height) == extent)
+                            .unwrap_or_else(|| {
+                                let image = image::open(path).unwrap().to_rgba8();
+                                TilePyramid::build(&image, extent, tiles_dir, &name)
+                            })
+                    })
+                })
+                .collect();
+            pyramids
+                .into_iter()
+                .map(|pyramid| pyramid.join().unwrap())
+                .collect()
+        });
+        self
+    }
+
+    /// The file names of the composite and the layers, which their tile pyramids are also named
+    /// after without the extension.
+    pub fn file_names() -> [&'static str; 4] {
+        [
+            COMPOSITE_FILE_NAME,
+            FLATMAP_LAYER_FILE_NAME,
+            LAND_LAYER_FILE_NAME,
+            OVERLAY_LAYER_FILE_NAME,
+        ]
+    }
+
+    fn images(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.composite).chain(self.layers.iter())
+    }
+
+    fn in_dir(cache_dir: &Path) -> Background {
+        Background {
+            composite: cache_dir.join(COMPOSITE_FILE_NAME),
+            layers: [
+                FLATMAP_LAYER_FILE_NAME,
+                LAND_LAYER_FILE_NAME,
+                OVERLAY_LAYER_FILE_NAME,
+            ]
+            .iter()
+            .map(|file_name| cache_dir.join(file_name))
+            .collect(),
+            tiles: vec![],
+        }
+    }
+}
+
+impl BackgroundTextures {
+    /// Decodes the textures and writes the composite and each layer to `cache_dir`, side by
+    /// side. The composite is written last, so a background is only cached once it is whole.
+    ///
+    /// Land is tinted by the land mask, which is white over land and black over water, and the
+    /// overlay multiplies in the paper texture and map details, as the game draws them. The
+    /// mask and overlay are scaled to the flatmap if their sizes differ.
+    #[instrument(skip(self))]
+    pub fn composite(&self, cache_dir: &Path) -> Result<Background, DdsError> {
+        let (flatmap, land_mask, overlay) = std::thread::scope(|scope| {
+            let flatmap = scope.spawn(|| decode(&self.flatmap));
+            let land_mask = scope.spawn(|| decode(&self.land_mask));
+            let overlay = scope.spawn(|| decode(&self.overlay));
+            (
+                flatmap.join().unwrap(),
+                land_mask.join().unwrap(),
+                overlay.join().unwrap(),
+            )
+        });
+        let (flatmap, land_mask, overlay) = (flatmap?, land_mask?, overlay?);
+        let (width, height) = flatmap.dimensions();
+        let land_mask = fit(land_mask, width, height);
+        let overlay = fit(overlay, width, height);
+
+        let land = land_layer(&land_mask);
+        let mut composite = flatmap.clone();
+        let rows = height.div_ceil(available_threads()) as usize;
+        let row_bytes = width as usize * 4;
+        std::thread::scope(|scope| {
+            for (index, chunk) in composite.chunks_mut(rows * row_bytes).enumerate() {
+                let offset = index * rows * row_bytes;
+                let land = &land.as_raw()[offset..offset + chunk.len()];
+                let overlay = &overlay.as_raw()[offset..offset + chunk.len()];
+                scope.spawn(move || blend(chunk, land, overlay));
+            }
+        });
+
+        let background = Background::in_dir(cache_dir);
+        std::thread::scope(|scope| {
+            let saves: Vec<_> = [&flatmap, &land, &overlay]
+                .into_iter()
+                .zip(&background.layers)
+                .map(|(image, path)| scope.spawn(move || save(image, path)))
+                .collect();
+            saves.into_iter().try_for_each(|save| save.join().unwrap())
+        })?;
+        save(&composite, &background.composite)?;
+        Ok(background)
+    }
+}
+
+fn pyramid_name(image: &Path) -> String {
+    image.file_stem().unwrap().to_string_lossy().to_string()
+}
+
+fn save(image: &RgbaImage, path: &Path) -> Result<(), DdsError> {
+    image
+        .save(path)
+        .map_err(|e| DdsError::Encode(path.to_path_buf(), e.to_string()))
+}
+
+fn decode(dds_file_path: &Path) -> Result<RgbaImage, DdsError> {
+    DdsToPng {
+        dds_file_path: dds_file_path.to_path_buf(),
+    }
+    .dds_to_buffer()
+}
+
+fn fit(image: RgbaImage, width: u32, height: u32) -> RgbaImage {
+    if image.dimensions() == (width, height) {
+        return image;
+    }
+    imageops::resize(&image, width, height, FilterType::Triangle)
+}
+
+// The tint on its own, transparent over water.
+fn land_layer(land_mask: &RgbaImage) -> RgbaImage {
+    let mut land = RgbaImage::new(land_mask.width(), land_mask.height());
+    for (pixel, mask) in land.pixels_mut().zip(land_mask.pixels()) {
+        let alpha = mask[0] as f32 * LAND_TINT_STRENGTH;
+        pixel.0 = [
+            LAND_TINT[0],
+            LAND_TINT[1],
+            LAND_TINT[2],
+            alpha.round() as u8,
+        ];
+    }
+    land
+}
+
+// Lays the land tint over the flatmap, then multiplies the overlay in by its alpha.
+fn blend(flatmap: &mut [u8], land: &[u8], overlay: &[u8]) {
+    for ((base, land), overlay) in flatmap
+        .chunks_exact_mut(4)
+        .zip(land.chunks_exact(4))
+        .zip(overlay.chunks_exact(4))
+    {
+        let land_alpha = land[3] as f32 / 255.0;
+        let overlay_alpha = overlay[3] as f32 / 255.0;
+        for channel in 0..3 {
+            let tinted =
+                base[channel] as f32 * (1.0 - land_alpha) + land[channel] as f32 * land_alpha;
+            let multiplier = 1.0 - overlay_alpha + overlay_alpha * overlay[channel] as f32 / 255.0;
+            base[channel] = (tinted * multiplier).round() as u8;
+        }
+        base[3] = 255;
+    }
+}
+
+pub(crate) fn available_threads() -> u32 {
+    std::thread::available_parallelism()
+        .map(|threads| threads.get() as u32)
+        .unwrap_or(1)
+}
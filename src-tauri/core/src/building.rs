@@ -1,9 +1,8 @@
 use jomini::text::de::from_utf8_reader;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::PathBuf};
-use tauri::{Manager, Window};
 
-use crate::cache_config::CacheConfig;
+use crate::mod_stack::ModStack;
 
 // const PRODUCTION_METHODS_PATH: &str = "common/production_methods";
 const PRODUCTION_METHOD_GROUPS_PATH: &str = "common/production_method_groups";
@@ -26,20 +25,17 @@ pub struct Building {
     pub production_method_groups: Vec<ProductionMethodGroup>,
     pub buildable: bool,
     pub unique: bool,
+    pub building_group: Option<String>,
 }
 
 impl Building {
-    pub fn parse_from_game_folder(window: Window) -> Vec<Building> {
-        let cache_dir = window.app_handle().path().app_cache_dir().unwrap();
-        let cache_config: CacheConfig = CacheConfig::get_config(cache_dir.join("config.json"));
-
-        Self::parse_from(cache_config.game_folder.unwrap().join("game"))
-    }
-
-    fn parse_from(path: PathBuf) -> Vec<Building> {
+    pub fn parse_from(mod_stack: &ModStack) -> Vec<Building> {
         let production_method_groups_map =
-            parse_production_method_groups(path.join(PRODUCTION_METHOD_GROUPS_PATH));
-        parse_buildings(path.join(BUILDINGS_PATH), production_method_groups_map)
+            parse_production_method_groups(mod_stack.files_in(PRODUCTION_METHOD_GROUPS_PATH));
+        parse_buildings(
+            mod_stack.files_in(BUILDINGS_PATH),
+            production_method_groups_map,
+        )
     }
 }
 
@@ -53,16 +49,17 @@ struct RawBuilding {
     buildable: bool,
     #[serde(default)]
     unique: bool,
+    #[serde(default)]
+    building_group: Option<String>,
 }
 
 pub fn parse_buildings(
-    buildings_path: PathBuf,
+    building_files: Vec<PathBuf>,
     pmg_map: HashMap<String, ProductionMethodGroup>,
 ) -> Vec<Building> {
     let mut buildings: Vec<Building> = Vec::new();
 
-    for entry in std::fs::read_dir(buildings_path).unwrap() {
-        let entry = entry.unwrap().path();
+    for entry in building_files {
         if entry.extension().unwrap() != "txt" {
             continue;
         };
@@ -74,6 +71,7 @@ pub fn parse_buildings(
                 production_method_groups,
                 buildable,
                 unique,
+                building_group,
             } = raw_building;
             let production_method_groups: Vec<ProductionMethodGroup> = production_method_groups
                 .iter()
@@ -84,6 +82,7 @@ pub fn parse_buildings(
                 production_method_groups,
                 buildable,
                 unique,
+                building_group,
             };
 
             buildings.push(building);
@@ -98,11 +97,10 @@ struct RawProductionMethodGroup {
     production_methods: Vec<String>,
 }
 
-fn parse_production_method_groups(path: PathBuf) -> HashMap<String, ProductionMethodGroup> {
+fn parse_production_method_groups(files: Vec<PathBuf>) -> HashMap<String, ProductionMethodGroup> {
     let mut pmg_map: HashMap<String, ProductionMethodGroup> = HashMap::new();
 
-    for entry in std::fs::read_dir(path).unwrap() {
-        let entry = entry.unwrap().path();
+    for entry in files {
         if entry.extension().unwrap() != "txt" {
             continue;
         };
@@ -0,0 +1,552 @@
+use image::{imageops, imageops::FilterType, Rgba, RgbaImage};
+use jomini::{text::ValueReader, TextTape, Utf8Encoding};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::{instrument, warn};
+
+use crate::{
+    color_converter::ColorConverter,
+    dds_to_png::{DdsError, DdsToPng},
+    mod_stack::ModStack,
+};
+
+pub const COAT_OF_ARMS_PATH: &str = "common/coat_of_arms/coat_of_arms";
+pub const NAMED_COLORS_PATH: &str = "common/named_colors";
+const PATTERNS_PATH: &str = "gfx/coat_of_arms/patterns";
+const COLORED_EMBLEMS_PATH: &str = "gfx/coat_of_arms/colored_emblems";
+const TEXTURED_EMBLEMS_PATH: &str = "gfx/coat_of_arms/textured_emblems";
+pub const FLAGS_DIR_NAME: &str = "flags";
+
+// Sub arms can nest, but never this deep in anything the game ships.
+const MAX_SUB_DEPTH: usize = 4;
+
+/// A colour as the coat of arms writes it: a name from `common/named_colors`, a colour of its
+/// own, or `color1` to `color3` of the coat it is in.
+#[derive(Debug, Clone)]
+pub enum CoatColor {
+    Named(String),
+    Rgb((u8, u8, u8)),
+    Reference(usize),
+}
+
+/// A flag from `common/coat_of_arms/coat_of_arms`: a pattern in up to three colours, with
+/// emblems and other coats of arms drawn over it.
+#[derive(Debug, Clone)]
+pub struct CoatOfArms {
+    pub name: String,
+    pub pattern: Option<String>,
+    pub colors: [Option<CoatColor>; 3],
+    pub colored_emblems: Vec<Emblem>,
+    pub textured_emblems: Vec<Emblem>,
+    pub subs: Vec<SubArms>,
+}
+
+/// Colored emblems are masks recoloured like the pattern, textured emblems are drawn as they
+/// are and ignore `colors`.
+#[derive(Debug, Clone)]
+pub struct Emblem {
+    pub texture: String,
+    pub colors: [Option<CoatColor>; 3],
+    pub instances: Vec<EmblemInstance>,
+}
+
+/// Where an emblem is drawn, as fractions of the flag. `position` is its centre and
+/// `rotation` is clockwise, in degrees. Emblems with a greater depth are drawn further back.
+#[derive(Debug, Clone)]
+pub struct EmblemInstance {
+    pub position: (f32, f32),
+    pub scale: (f32, f32),
+    pub rotation: f32,
+    pub depth: f32,
+}
+
+/// Another coat of arms drawn into part of this one, `offset` being its top left corner.
+#[derive(Debug, Clone)]
+pub struct SubArms {
+    pub parent: String,
+    pub instances: Vec<SubInstance>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubInstance {
+    pub offset: (f32, f32),
+    pub scale: (f32, f32),
+}
+
+// Centred and covering the whole flag, unless the instance says otherwise.
+impl Default for EmblemInstance {
+    fn default() -> Self {
+        Self {
+            position: (0.5, 0.5),
+            scale: (1.0, 1.0),
+            rotation: 0.0,
+            depth: 0.0,
+        }
+    }
+}
+
+impl Default for SubInstance {
+    fn default() -> Self {
+        Self {
+            offset: (0.0, 0.0),
+            scale: (1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FlagError {
+    UnknownCoatOfArms(String),
+    Texture(DdsError),
+    Write(PathBuf, String),
+}
+
+impl fmt::Display for FlagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlagError::UnknownCoatOfArms(name) => write!(f, "no coat of arms named {}", name),
+            FlagError::Texture(e) => write!(f, "{}", e),
+            FlagError::Write(path, e) => write!(f, "could not write {:?}: {}", path, e),
+        }
+    }
+}
+
+impl std::error::Error for FlagError {}
+
+impl From<DdsError> for FlagError {
+    fn from(e: DdsError) -> Self {
+        FlagError::Texture(e)
+    }
+}
+
+impl CoatOfArms {
+    pub fn parse_map_from(mod_stack: &ModStack) -> HashMap<String, CoatOfArms> {
+        let mut coats = HashMap::new();
+        for file in mod_stack.files_in(COAT_OF_ARMS_PATH) {
+            if file.extension().unwrap() != "txt" {
+                continue;
+            }
+            let data = std::fs::read(&file).unwrap();
+            let Ok(tape) = TextTape::from_slice(&data) else {
+                warn!(?file, "Failed to parse coats of arms");
+                continue;
+            };
+            for (key, _op, value) in tape.utf8_reader().fields() {
+                let Ok(fields) = value.read_object() else {
+                    continue;
+                };
+                let mut coat = CoatOfArms {
+                    name: key.read_str().to_string(),
+                    pattern: None,
+                    colors: [None, None, None],
+                    colored_emblems: vec![],
+                    textured_emblems: vec![],
+                    subs: vec![],
+                };
+                for (field, _op, value) in fields.fields() {
+                    match field.read_str().as_ref() {
+                        "pattern" => coat.pattern = value.read_string().ok(),
+                        "colored_emblem" => coat.colored_emblems.extend(read_emblem(&value)),
+                        "textured_emblem" => coat.textured_emblems.extend(read_emblem(&value)),
+                        "sub" => coat.subs.extend(read_sub(&value)),
+                        field => {
+                            if let Some(index) = color_index(field) {
+                                coat.colors[index] = read_coat_color(&value);
+                            }
+                        }
+                    }
+                }
+                coats.insert(coat.name.clone(), coat);
+            }
+        }
+        coats
+    }
+}
+
+/// Colours by name from `common/named_colors`.
+pub fn parse_named_colors(mod_stack: &ModStack) -> HashMap<String, (u8, u8, u8)> {
+    let mut named_colors = HashMap::new();
+    for file in mod_stack.files_in(NAMED_COLORS_PATH) {
+        if file.extension().unwrap() != "txt" {
+            continue;
+        }
+        let data = std::fs::read(&file).unwrap();
+        let Ok(tape) = TextTape::from_slice(&data) else {
+            warn!(?file, "Failed to parse named colors");
+            continue;
+        };
+        for (key, _op, value) in tape.utf8_reader().fields() {
+            if key.read_str() != "colors" {
+                continue;
+            }
+            let Ok(colors) = value.read_object() else {
+                continue;
+            };
+            for (name, _op, color) in colors.fields() {
+                if let Some(color) = ColorConverter::read_color(&color) {
+                    named_colors.insert(name.read_str().to_string(), color);
+                }
+            }
+        }
+    }
+    named_colors
+}
+
+// `color1` to `color3`, as indices.
+fn color_index(field: &str) -> Option<usize> {
+    match field {
+        "color1" => Some(0),
+        "color2" => Some(1),
+        "color3" => Some(2),
+        _ => None,
+    }
+}
+
+fn read_coat_color(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<CoatColor> {
+    if let Ok(name) = value.read_string() {
+        return Some(match color_index(&name) {
+            Some(index) => CoatColor::Reference(index),
+            None => CoatColor::Named(name),
+        });
+    }
+    ColorConverter::read_color(value).map(CoatColor::Rgb)
+}
+
+fn read_emblem(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<Emblem> {
+    let mut emblem = Emblem {
+        texture: String::new(),
+        colors: [None, None, None],
+        instances: vec![],
+    };
+    for (field, _op, value) in value.read_object().ok()?.fields() {
+        match field.read_str().as_ref() {
+            "texture" => emblem.texture = value.read_string().ok()?,
+            "instance" => {
+                let mut instance = EmblemInstance::default();
+                for (field, _op, value) in value.read_object().ok()?.fields() {
+                    match field.read_str().as_ref() {
+                        "position" => instance.position = read_pair(&value)?,
+                        "scale" => instance.scale = read_pair(&value)?,
+                        "rotation" => instance.rotation = read_number(&value)?,
+                        "depth" => instance.depth = read_number(&value)?,
+                        _ => {}
+                    }
+                }
+                emblem.instances.push(instance);
+            }
+            field => {
+                if let Some(index) = color_index(field) {
+                    emblem.colors[index] = read_coat_color(&value);
+                }
+            }
+        }
+    }
+    if emblem.instances.is_empty() {
+        emblem.instances.push(EmblemInstance::default());
+    }
+    match emblem.texture.is_empty() {
+        true => None,
+        false => Some(emblem),
+    }
+}
+
+fn read_sub(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<SubArms> {
+    let mut sub = SubArms {
+        parent: String::new(),
+        instances: vec![],
+    };
+    for (field, _op, value) in value.read_object().ok()?.fields() {
+        match field.read_str().as_ref() {
+            "parent" => sub.parent = value.read_string().ok()?,
+            "instance" => {
+                let mut instance = SubInstance::default();
+                for (field, _op, value) in value.read_object().ok()?.fields() {
+                    match field.read_str().as_ref() {
+                        "offset" => instance.offset = read_pair(&value)?,
+                        "scale" => instance.scale = read_pair(&value)?,
+                        _ => {}
+                    }
+                }
+                sub.instances.push(instance);
+            }
+            _ => {}
+        }
+    }
+    if sub.instances.is_empty() {
+        sub.instances.push(SubInstance::default());
+    }
+    match sub.parent.is_empty() {
+        true => None,
+        false => Some(sub),
+    }
+}
+
+fn read_pair(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<(f32, f32)> {
+    let numbers: Vec<f32> = value
+        .read_array()
+        .ok()?
+        .values()
+        .map(|number| read_number(&number))
+        .collect::<Option<_>>()?;
+    match numbers.as_slice() {
+        [x, y] => Some((*x, *y)),
+        _ => None,
+    }
+}
+
+fn read_number(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<f32> {
+    value.read_string().ok()?.parse().ok()
+}
+
+/// Renders flags from coats of arms, keeping the textures it has decoded for the next one.
+/// Flags can be rendered side by side, the textures are only locked to look them up and to
+/// keep new ones.
+pub struct FlagRenderer {
+    pub mod_stack: ModStack,
+    pub coats: HashMap<String, CoatOfArms>,
+    pub named_colors: HashMap<String, (u8, u8, u8)>,
+    textures: Mutex<HashMap<(PathBuf, u32), RgbaImage>>,
+}
+
+impl FlagRenderer {
+    pub fn new(mod_stack: ModStack) -> Self {
+        Self {
+            coats: CoatOfArms::parse_map_from(&mod_stack),
+            named_colors: parse_named_colors(&mod_stack),
+            mod_stack,
+            textures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The flag of the coat of arms named `name`, usually a country's tag, `width` pixels wide
+    /// and two thirds as tall, as the game shows them.
+    #[instrument(skip(self))]
+    pub fn render(&self, name: &str, width: u32) -> Result<RgbaImage, FlagError> {
+        let height = (width * 2 / 3).max(1);
+        self.render_coat(name, width, height, 0)
+    }
+
+    /// The flag rendered into `cache_dir`, unless it already is, as `flags/{name}-{width}.png`.
+    pub fn cache(&self, name: &str, width: u32, cache_dir: &Path) -> Result<PathBuf, FlagError> {
+        let path = flag_path(cache_dir, name, width);
+        if path.exists() {
+            return Ok(path);
+        }
+        let flag = self.render(name, width)?;
+        std::fs::create_dir_all(path.parent().unwrap())
+            .map_err(|e| FlagError::Write(path.clone(), e.to_string()))?;
+        flag.save(&path)
+            .map_err(|e| FlagError::Write(path.clone(), e.to_string()))?;
+        Ok(path)
+    }
+
+    fn render_coat(
+        &self,
+        name: &str,
+        width: u32,
+        height: u32,
+        depth: usize,
+    ) -> Result<RgbaImage, FlagError> {
+        let coat = self
+            .coats
+            .get(name)
+            .cloned()
+            .ok_or_else(|| FlagError::UnknownCoatOfArms(name.to_string()))?;
+        let colors = self.resolve_colors(&coat.colors, &DEFAULT_COLORS);
+
+        let mut flag = match &coat.pattern {
+            Some(pattern) => {
+                let pattern = self.texture(PATTERNS_PATH, pattern, width.max(height))?;
+                let pattern = imageops::resize(&pattern, width, height, FilterType::Triangle);
+                let mut flag = RgbaImage::new(width, height);
+                for (pixel, mask) in flag.pixels_mut().zip(pattern.pixels()) {
+                    *pixel = recolor(mask, &colors, 255);
+                }
+                flag
+            }
+            None => RgbaImage::from_pixel(
+                width,
+                height,
+                Rgba([colors[0].0, colors[0].1, colors[0].2, 255]),
+            ),
+        };
+
+        // Deepest first, and otherwise in the order they are written.
+        let mut emblems: Vec<(bool, &Emblem, &EmblemInstance)> = coat
+            .colored_emblems
+            .iter()
+            .map(|emblem| (true, emblem))
+            .chain(coat.textured_emblems.iter().map(|emblem| (false, emblem)))
+            .flat_map(|(colored, emblem)| {
+                emblem
+                    .instances
+                    .iter()
+                    .map(move |instance| (colored, emblem, instance))
+            })
+            .collect();
+        emblems.sort_by(|a, b| b.2.depth.total_cmp(&a.2.depth));
+
+        for (colored, emblem, instance) in emblems {
+            let size = (instance.scale.0.abs() * width as f32)
+                .max(instance.scale.1.abs() * height as f32)
+                .ceil()
+                .max(1.0) as u32;
+            let texture = match colored {
+                true => {
+                    let emblem_colors = self.resolve_colors(&emblem.colors, &colors);
+                    let mask = self.texture(COLORED_EMBLEMS_PATH, &emblem.texture, size)?;
+                    let mut texture = mask.clone();
+                    for (pixel, mask) in texture.pixels_mut().zip(mask.pixels()) {
+                        *pixel = recolor(mask, &emblem_colors, mask[3]);
+                    }
+                    texture
+                }
+                false => self.texture(TEXTURED_EMBLEMS_PATH, &emblem.texture, size)?,
+            };
+            draw_emblem(&mut flag, &texture, instance);
+        }
+
+        if depth < MAX_SUB_DEPTH {
+            for sub in coat.subs.iter() {
+                for instance in sub.instances.iter() {
+                    let sub_width = (instance.scale.0 * width as f32).round().max(1.0) as u32;
+                    let sub_height = (instance.scale.1 * height as f32).round().max(1.0) as u32;
+                    let sub_flag =
+                        self.render_coat(&sub.parent, sub_width, sub_height, depth + 1)?;
+                    imageops::overlay(
+                        &mut flag,
+                        &sub_flag,
+                        (instance.offset.0 * width as f32).round() as i64,
+                        (instance.offset.1 * height as f32).round() as i64,
+                    );
+                }
+            }
+        }
+
+        Ok(flag)
+    }
+
+    // Unset colours fall back to `defaults`, and references to the coat's own colours to those.
+    fn resolve_colors(
+        &self,
+        colors: &[Option<CoatColor>; 3],
+        defaults: &[(u8, u8, u8); 3],
+    ) -> [(u8, u8, u8); 3] {
+        let mut resolved = *defaults;
+        for (index, color) in colors.iter().enumerate() {
+            resolved[index] = match color {
+                Some(CoatColor::Rgb(color)) => *color,
+                Some(CoatColor::Named(name)) => match self.named_colors.get(name) {
+                    Some(color) => *color,
+                    None => {
+                        warn!(name, "Unknown named color");
+                        defaults[index]
+                    }
+                },
+                Some(CoatColor::Reference(reference)) => defaults[*reference],
+                None => defaults[index],
+            };
+        }
+        resolved
+    }
+
+    // Decoded from the smallest mip that is still `size` pixels across. Scripts name some
+    // textures `.tga`, but only the DDS versions are shipped.
+    fn texture(&self, dir: &str, file_name: &str, size: u32) -> Result<RgbaImage, FlagError> {
+        let file_name = Path::new(file_name).with_extension("dds");
+        let path = self
+            .mod_stack
+            .file(&format!("{}/{}", dir, file_name.to_string_lossy()));
+        if let Some(texture) = self.textures.lock().unwrap().get(&(path.clone(), size)) {
+            return Ok(texture.clone());
+        }
+        let texture = DdsToPng {
+            dds_file_path: path.clone(),
+        }
+        .preview(size)?;
+        self.textures
+            .lock()
+            .unwrap()
+            .insert((path, size), texture.clone());
+        Ok(texture)
+    }
+}
+
+pub fn flag_path(cache_dir: &Path, name: &str, width: u32) -> PathBuf {
+    cache_dir
+        .join(FLAGS_DIR_NAME)
+        .join(format!("{}-{}.png", name, width))
+}
+
+const DEFAULT_COLORS: [(u8, u8, u8); 3] = [(255, 255, 255), (0, 0, 0), (128, 128, 128)];
+
+// Patterns and colored emblems are masks, their red, green and blue channels standing for how
+// much of `color1`, `color2` and `color3` each pixel is.
+fn recolor(mask: &Rgba<u8>, colors: &[(u8, u8, u8); 3], alpha: u8) -> Rgba<u8> {
+    let weights = [mask[0] as f32, mask[1] as f32, mask[2] as f32];
+    let total: f32 = weights.iter().sum();
+    if total == 0.0 {
+        return Rgba([colors[0].0, colors[0].1, colors[0].2, alpha]);
+    }
+    let channel = |pick: fn(&(u8, u8, u8)) -> u8| {
+        let mixed: f32 = weights
+            .iter()
+            .zip(colors.iter())
+            .map(|(weight, color)| weight * pick(color) as f32)
+            .sum();
+        (mixed / total).round() as u8
+    };
+    Rgba([
+        channel(|color| color.0),
+        channel(|color| color.1),
+        channel(|color| color.2),
+        alpha,
+    ])
+}
+
+// Maps each pixel of the flag back into the emblem, so rotated and mirrored instances are drawn
+// without gaps.
+fn draw_emblem(flag: &mut RgbaImage, texture: &RgbaImage, instance: &EmblemInstance) {
+    let (width, height) = (flag.width() as f32, flag.height() as f32);
+    let emblem_width = instance.scale.0 * width;
+    let emblem_height = instance.scale.1 * height;
+    if emblem_width == 0.0 || emblem_height == 0.0 {
+        return;
+    }
+    let center = (instance.position.0 * width, instance.position.1 * height);
+    let (sin, cos) = instance.rotation.to_radians().sin_cos();
+
+    // Half the diagonal bounds the emblem however it is rotated.
+    let reach = (emblem_width.powi(2) + emblem_height.powi(2)).sqrt() / 2.0;
+    let min_x = (center.0 - reach).floor().max(0.0) as u32;
+    let max_x = (center.0 + reach).ceil().min(width) as u32;
+    let min_y = (center.1 - reach).floor().max(0.0) as u32;
+    let max_y = (center.1 + reach).ceil().min(height) as u32;
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let dx = x as f32 + 0.5 - center.0;
+            let dy = y as f32 + 0.5 - center.1;
+            let u = (dx * cos + dy * sin) / emblem_width + 0.5;
+            let v = (-dx * sin + dy * cos) / emblem_height + 0.5;
+            if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+                continue;
+            }
+            let source = texture.get_pixel(
+                (u * texture.width() as f32) as u32,
+                (v * texture.height() as f32) as u32,
+            );
+            let alpha = source[3] as f32 / 255.0;
+            let target = flag.get_pixel_mut(x, y);
+            for channel in 0..3 {
+                target[channel] = (source[channel] as f32 * alpha
+                    + target[channel] as f32 * (1.0 - alpha))
+                    .round() as u8;
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use jomini::{text::ValueReader, Utf8Encoding};
+use palette::{convert::FromColorUnclamped, rgb as PaletteRgb, Hsv, Srgb};
+
+pub struct ColorConverter;
+
+impl ColorConverter {
+    pub fn rgb_from_hsv_degrees(hue: i64, saturation: i64, brightness_value: i64) -> (u8, u8, u8) {
+        let hsv: Hsv<Srgb, f64> = Hsv::new(
+            hue as f64,
+            saturation as f64 / 100.0,
+            brightness_value as f64 / 100.0,
+        );
+        let rgb: PaletteRgb::Rgb<_, u8> = PaletteRgb::Rgb::from_color_unclamped(hsv).into();
+
+        (rgb.red, rgb.green, rgb.blue)
+    }
+
+    pub fn rgb_from_hsv_float(hue: f64, saturation: f64, brightness_value: f64) -> (u8, u8, u8) {
+        let hsv: Hsv<Srgb, f64> = Hsv::new(hue, saturation, brightness_value);
+        let rgb: PaletteRgb::Rgb<_, u8> = PaletteRgb::Rgb::from_color_unclamped(hsv).into();
+
+        (rgb.red, rgb.green, rgb.blue)
+    }
+
+    /// Reads a colour written as `{ r g b }`, with components from 0 to 255 or, if any has a
+    /// decimal point, from 0 to 1, or as `rgb`, `hsv` or `hsv360` followed by its components.
+    pub fn read_color(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<(u8, u8, u8)> {
+        let values: Vec<ValueReader<'_, '_, Utf8Encoding>> =
+            value.read_array().ok()?.values().collect();
+        match values.as_slice() {
+            [kind, components] => {
+                let [a, b, c] = components_of(components)?;
+                match kind.read_string().ok()?.as_str() {
+                    "rgb" => Some(rgb_from_components([a, b, c])),
+                    "hsv" => Some(Self::rgb_from_hsv_float(a.0, b.0, c.0)),
+                    "hsv360" => Some(Self::rgb_from_hsv_degrees(
+                        a.0 as i64, b.0 as i64, c.0 as i64,
+                    )),
+                    _ => None,
+                }
+            }
+            [_, _, _] => Some(rgb_from_components(components_of(value)?)),
+            _ => None,
+        }
+    }
+}
+
+// Each component's value and whether it was written with a decimal point.
+fn components_of(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<[(f64, bool); 3]> {
+    let components: Vec<(f64, bool)> = value
+        .read_array()
+        .ok()?
+        .values()
+        .map(|component| {
+            let component = component.read_string().ok()?;
+            Some((component.parse().ok()?, component.contains('.')))
+        })
+        .collect::<Option<_>>()?;
+    components.try_into().ok()
+}
+
+fn rgb_from_components(components: [(f64, bool); 3]) -> (u8, u8, u8) {
+    let scale = match components.iter().any(|(_, is_fraction)| *is_fraction) {
+        true => 255.0,
+        false => 1.0,
+    };
+    let [r, g, b] =
+        components.map(|(component, _)| (component * scale).round().clamp(0.0, 255.0) as u8);
+    (r, g, b)
+}
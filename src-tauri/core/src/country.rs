@@ -1,14 +1,9 @@
-use geo::{BooleanOps, MultiPolygon};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    country_definition::CountryDefinition,
-    country_setup::CountrySetup,
-    geo_converters::{multi_poly_to_vec, vec_to_multi_poly},
-    get_state_buildings::StateBuilding,
-    get_state_populations::Pop,
-    merge_buildings::merge_state_buildings,
-    merge_pops::merge_pops,
+    country_definition::CountryDefinition, country_setup::CountrySetup,
+    get_state_buildings::StateBuilding, get_state_populations::Pop, labels::Label,
+    merge_buildings::merge_state_buildings, merge_pops::merge_pops,
 };
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -17,6 +12,8 @@ pub struct Country {
     pub color: (u8, u8, u8),
     pub states: Vec<State>,
     pub coordinates: Vec<Vec<(f32, f32)>>,
+    #[serde(default)]
+    pub label: Option<Label>,
     pub setup: CountrySetup,
 }
 
@@ -29,10 +26,8 @@ pub struct State {
 }
 
 impl Country {
-    pub fn remove_state(mut self, state: &str, coords: &MultiPolygon<f32>) -> Country {
+    pub fn remove_state(mut self, state: &str) -> Country {
         self.states.retain(|from_state| from_state.name != state);
-        self.coordinates =
-            multi_poly_to_vec(vec_to_multi_poly(self.coordinates).difference(coords));
 
         self
     }
@@ -43,17 +38,12 @@ impl Country {
             color: country_definition.color,
             states: vec![],
             coordinates: vec![],
+            label: None,
             setup: CountrySetup::new(),
         }
     }
 
-    pub fn add_state(
-        mut self,
-        from_country: &Country,
-        state: &str,
-        to_coords: &MultiPolygon<f32>,
-    ) -> Country {
-        self.coordinates = multi_poly_to_vec(vec_to_multi_poly(self.coordinates).union(to_coords));
+    pub fn add_state(mut self, from_country: &Country, state: &str) -> Country {
         let existing_state = self.states.iter().find(|to_state| to_state.name == state);
         match existing_state {
             Some(to_state) => {
@@ -96,7 +86,6 @@ impl Country {
         mut self,
         state: &str,
         province: &str,
-        province_coords: &MultiPolygon<f32>,
         pops: Vec<Pop>,
         state_buildings: Vec<StateBuilding>,
     ) -> Country {
@@ -127,9 +116,6 @@ impl Country {
             }
         };
 
-        self.coordinates =
-            multi_poly_to_vec(vec_to_multi_poly(self.coordinates).union(province_coords));
-
         self
     }
 
@@ -137,7 +123,6 @@ impl Country {
         mut self,
         state: &str,
         province: &str,
-        province_coords: &MultiPolygon<f32>,
     ) -> (Country, Vec<Pop>, Vec<StateBuilding>) {
         let existing_state = self
             .states
@@ -168,9 +153,6 @@ impl Country {
             false => (new_pops, new_state_buildings),
         };
 
-        self.coordinates =
-            multi_poly_to_vec(vec_to_multi_poly(self.coordinates).difference(province_coords));
-
         (self, pops_given, state_buildings_given)
     }
 }
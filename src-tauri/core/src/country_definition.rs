@@ -11,15 +11,14 @@ pub struct CountryDefinition {
 }
 
 impl CountryDefinition {
-    pub fn parse_from(path: PathBuf) -> Vec<CountryDefinition> {
-        Self::parse_map_from(path).values().cloned().collect()
+    pub fn parse_from(files: Vec<PathBuf>) -> Vec<CountryDefinition> {
+        Self::parse_map_from(files).values().cloned().collect()
     }
 
-    pub fn parse_map_from(path: PathBuf) -> HashMap<String, CountryDefinition> {
+    pub fn parse_map_from(files: Vec<PathBuf>) -> HashMap<String, CountryDefinition> {
         let mut country_definitions: HashMap<String, CountryDefinition> = HashMap::new();
 
-        for entry in std::fs::read_dir(path).unwrap() {
-            let entry = entry.unwrap().path();
+        for entry in files {
             if entry.extension().unwrap() != "txt" || entry.file_name().unwrap() == "99_dynamic.txt"
             {
                 continue;
@@ -64,18 +64,24 @@ struct CountrySetupFile {
     countries: HashMap<String, RawCountrySetup>,
 }
 
-impl CountrySetup {
-    pub fn new() -> CountrySetup {
+// New countries start at the lowest tech tier with nothing else researched.
+impl Default for CountrySetup {
+    fn default() -> Self {
         CountrySetup {
             base_tech: Some("tier_7".to_string()),
             technologies_researched: vec![],
         }
     }
-    pub fn parse_map_from(path: PathBuf) -> HashMap<String, CountrySetup> {
+}
+
+impl CountrySetup {
+    pub fn new() -> CountrySetup {
+        CountrySetup::default()
+    }
+    pub fn parse_map_from(files: Vec<PathBuf>) -> HashMap<String, CountrySetup> {
         let mut country_setups: HashMap<String, CountrySetup> = HashMap::new();
 
-        for entry in std::fs::read_dir(path).unwrap() {
-            let entry = entry.unwrap().path();
+        for entry in files {
             if entry.extension().unwrap() != "txt" {
                 continue;
             };
@@ -120,11 +126,10 @@ impl CountrySetup {
         country_setups
     }
 
-    pub fn parse_map_unprocessed_values(path: PathBuf) -> HashMap<String, String> {
+    pub fn parse_map_unprocessed_values(files: Vec<PathBuf>) -> HashMap<String, String> {
         let mut country_setups: HashMap<String, String> = HashMap::new();
 
-        for entry in std::fs::read_dir(path).unwrap() {
-            let entry = entry.unwrap().path();
+        for entry in files {
             if entry.extension().unwrap() != "txt" {
                 continue;
             };
@@ -0,0 +1,374 @@
+use ddsfile::Dds;
+use image::{
+    codecs::png::PngEncoder, imageops, imageops::FilterType, ExtendedColorType, ImageEncoder,
+    RgbaImage,
+};
+use serde::Serialize;
+use std::{
+    fmt,
+    fs::File,
+    path::{Path, PathBuf},
+};
+use tracing::{instrument, warn};
+
+use crate::mod_stack::ModStack;
+
+/// Converts a DDS texture to PNG, decoding any mip level of the block compressed formats the
+/// game ships, BC1 to BC7, and of uncompressed ones.
+pub struct DdsToPng {
+    pub dds_file_path: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum DdsError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, String),
+    UnsupportedFormat(PathBuf, String),
+    MissingMip { path: PathBuf, mip: u32, mips: u32 },
+    Encode(PathBuf, String),
+    Write(PathBuf, std::io::Error),
+}
+
+impl fmt::Display for DdsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DdsError::Read(path, e) => write!(f, "could not read {:?}: {}", path, e),
+            DdsError::Parse(path, e) => write!(f, "{:?} is not a valid DDS file: {}", path, e),
+            DdsError::UnsupportedFormat(path, e) => {
+                write!(f, "can't decode the format of {:?}: {}", path, e)
+            }
+            DdsError::MissingMip { path, mip, mips } => {
+                write!(f, "{:?} has no mip level {}, only {}", path, mip, mips)
+            }
+            DdsError::Encode(path, e) => write!(f, "could not encode {:?} as PNG: {}", path, e),
+            DdsError::Write(path, e) => write!(f, "could not write {:?}: {}", path, e),
+        }
+    }
+}
+
+impl std::error::Error for DdsError {}
+
+impl DdsToPng {
+    pub fn dds_to_buffer(&self) -> Result<RgbaImage, DdsError> {
+        self.mip_to_buffer(0)
+    }
+
+    /// Mip 0 is the full size texture, and each level after it is half the size of the last.
+    pub fn mip_to_buffer(&self, mip: u32) -> Result<RgbaImage, DdsError> {
+        let dds = self.read()?;
+        let mips = dds.get_num_mipmap_levels();
+        if mip >= mips {
+            return Err(DdsError::MissingMip {
+                path: self.dds_file_path.clone(),
+                mip,
+                mips,
+            });
+        }
+
+        match image_dds::image_from_dds(&dds, mip) {
+            Ok(image) => {
+                Ok(RgbaImage::from_raw(image.width(), image.height(), image.into_raw()).unwrap())
+            }
+            Err(e) => decode_uncompressed(&dds, mip).ok_or_else(|| {
+                DdsError::UnsupportedFormat(self.dds_file_path.clone(), e.to_string())
+            }),
+        }
+    }
+
+    /// The texture scaled to fit in `max_size` pixels, decoded from the smallest mip that is at
+    /// least that large so big textures are never decoded whole for a preview.
+    pub fn preview(&self, max_size: u32) -> Result<RgbaImage, DdsError> {
+        let dds = self.read()?;
+        let largest_side = dds.get_width().max(dds.get_height());
+        let mut mip = 0;
+        while mip + 1 < dds.get_num_mipmap_levels() && (largest_side >> (mip + 1)) >= max_size {
+            mip += 1;
+        }
+
+        let image = self.mip_to_buffer(mip)?;
+        if image.width().max(image.height()) <= max_size {
+            return Ok(image);
+        }
+        let scale = max_size as f32 / image.width().max(image.height()) as f32;
+        Ok(imageops::resize(
+            &image,
+            ((image.width() as f32 * scale).round() as u32).max(1),
+            ((image.height() as f32 * scale).round() as u32).max(1),
+            FilterType::Triangle,
+        ))
+    }
+
+    /// Converts the texture into `cache_dir` unless it already is, returning the PNG's path.
+    pub fn cache(&self, cache_dir: &Path) -> Result<PathBuf, DdsError> {
+        let png_file_path = self.png_file_path(cache_dir);
+        if !self.exists_in_cache(cache_dir) {
+            self.write_image(&self.dds_to_buffer()?, &png_file_path)?;
+        }
+        Ok(png_file_path)
+    }
+
+    /// Encodes the whole image before writing it next to `path` and moving it into place, so a
+    /// failed conversion never leaves a PNG behind to be taken for a cached one.
+    pub fn write_image(&self, image: &RgbaImage, path: &Path) -> Result<(), DdsError> {
+        let mut png = vec![];
+        PngEncoder::new(&mut png)
+            .write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                ExtendedColorType::Rgba8,
+            )
+            .map_err(|e| DdsError::Encode(self.dds_file_path.clone(), e.to_string()))?;
+
+        let partial_path = path.with_extension("png.partial");
+        std::fs::write(&partial_path, png)
+            .and_then(|_| std::fs::rename(&partial_path, path))
+            .map_err(|e| DdsError::Write(path.to_path_buf(), e))
+    }
+
+    /// Empty files left by earlier versions, which wrote the PNG before encoding it, don't count.
+    pub fn exists_in_cache(&self, cache_dir: &Path) -> bool {
+        std::fs::metadata(self.png_file_path(cache_dir)).is_ok_and(|metadata| metadata.len() > 0)
+    }
+
+    pub fn png_file_path(&self, cache_dir: &Path) -> PathBuf {
+        let mut png_file_path = cache_dir.join(self.dds_file_path.file_name().unwrap());
+        png_file_path.set_extension("png");
+        png_file_path
+    }
+
+    fn read(&self) -> Result<Dds, DdsError> {
+        let file = File::open(&self.dds_file_path)
+            .map_err(|e| DdsError::Read(self.dds_file_path.clone(), e))?;
+        Dds::read(file).map_err(|e| DdsError::Parse(self.dds_file_path.clone(), e.to_string()))
+    }
+}
+
+// Older tools write uncompressed textures as bit masks, such as 24 bit BGR or 8 bit luminance,
+// rather than as a DXGI format.
+fn decode_uncompressed(dds: &Dds, mip: u32) -> Option<RgbaImage> {
+    let format = &dds.header.spf;
+    let bits = format.rgb_bit_count?;
+    if bits % 8 != 0 || bits == 0 || bits > 32 {
+        return None;
+    }
+    let bytes = (bits / 8) as usize;
+    let masks = [
+        format.r_bit_mask.unwrap_or(0),
+        format.g_bit_mask.unwrap_or(0),
+        format.b_bit_mask.unwrap_or(0),
+        format.a_bit_mask.unwrap_or(0),
+    ];
+    // Luminance only has the red mask, and is grey.
+    let luminance = masks[1] == 0 && masks[2] == 0;
+
+    let mip_size = |level: u32| {
+        let width = (dds.get_width() >> level).max(1) as usize;
+        let height = (dds.get_height() >> level).max(1) as usize;
+        (width, height)
+    };
+    let offset: usize = (0..mip)
+        .map(|level| {
+            let (width, height) = mip_size(level);
+            width * height * bytes
+        })
+        .sum();
+    let (width, height) = mip_size(mip);
+    let data = dds.get_data(0).ok()?;
+    let data = data.get(offset..offset + width * height * bytes)?;
+
+    let mut image = RgbaImage::new(width as u32, height as u32);
+    for (pixel, texel) in image.pixels_mut().zip(data.chunks_exact(bytes)) {
+        let mut value = 0u64;
+        for (index, byte) in texel.iter().enumerate() {
+            value |= (*byte as u64) << (8 * index);
+        }
+        let channel = |mask: u32| match mask as u64 {
+            0 => None,
+            mask => {
+                let max = mask >> mask.trailing_zeros();
+                Some((((value & mask) >> mask.trailing_zeros()) * 255 / max) as u8)
+            }
+        };
+        let red = channel(masks[0]).unwrap_or(0);
+        pixel.0 = match luminance {
+            true => [red, red, red, channel(masks[3]).unwrap_or(255)],
+            false => [
+                red,
+                channel(masks[1]).unwrap_or(0),
+                channel(masks[2]).unwrap_or(0),
+                channel(masks[3]).unwrap_or(255),
+            ],
+        };
+    }
+    Some(image)
+}
+
+/// What converting a folder of textures did.
+#[derive(Debug, Default, Serialize)]
+pub struct GfxConversion {
+    /// PNGs by the DDS path relative to the folder, such as `goods_icons/grain.dds`.
+    pub converted: Vec<(String, PathBuf)>,
+    pub failed: Vec<(String, String)>,
+}
+
+/// Converts every DDS texture under `relative_dir` in the mod stack, such as
+/// `gfx/interface/icons/goods_icons`, into the same layout under `out_dir/<max_size>`, or
+/// `out_dir/full` when they are kept at full size. Textures are scaled to fit in `max_size`
+/// when it is given, and ones already converted at that size are kept.
+#[instrument(skip(mod_stack))]
+pub fn convert_gfx(
+    mod_stack: &ModStack,
+    relative_dir: &str,
+    out_dir: &Path,
+    max_size: Option<u32>,
+) -> GfxConversion {
+    let textures: Vec<(String, PathBuf)> = mod_stack
+        .files_under(relative_dir)
+        .into_iter()
+        .filter(|(_, path)| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("dds"))
+        })
+        .collect();
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = textures.len().div_ceil(threads).max(1);
+    let size_dir = out_dir.join(max_size.map_or("full".to_string(), |size| size.to_string()));
+    let size_dir = &size_dir;
+
+    let results: Vec<(String, Result<PathBuf, DdsError>)> = std::thread::scope(|scope| {
+        let chunks: Vec<_> = textures
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(relative_path, dds_file_path)| {
+                            let png_file_path = size_dir
+                                .join(relative_dir)
+                                .join(relative_path)
+                                .with_extension("png");
+                            let result = convert_texture(dds_file_path, &png_file_path, max_size);
+                            (relative_path.clone(), result.map(|_| png_file_path))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        chunks
+            .into_iter()
+            .flat_map(|chunk| chunk.join().unwrap())
+            .collect()
+    });
+
+    let mut conversion = GfxConversion::default();
+    for (relative_path, result) in results {
+        match result {
+            Ok(png_file_path) => conversion.converted.push((relative_path, png_file_path)),
+            Err(e) => {
+                warn!(relative_path, error = %e, "Failed to convert texture");
+                conversion.failed.push((relative_path, e.to_string()));
+            }
+        }
+    }
+    conversion
+}
+
+fn convert_texture(
+    dds_file_path: &Path,
+    png_file_path: &Path,
+    max_size: Option<u32>,
+) -> Result<(), DdsError> {
+    if std::fs::metadata(png_file_path).is_ok_and(|metadata| metadata.len() > 0) {
+        return Ok(());
+    }
+    let dds_to_png = DdsToPng {
+        dds_file_path: dds_file_path.to_path_buf(),
+    };
+    let image = match max_size {
+        Some(max_size) => dds_to_png.preview(max_size)?,
+        None => dds_to_png.dds_to_buffer()?,
+    };
+    let png_dir = png_file_path.parent().unwrap();
+    std::fs::create_dir_all(png_dir).map_err(|e| DdsError::Write(png_dir.to_path_buf(), e))?;
+    dds_to_png.write_image(&image, png_file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ddsfile::{D3DFormat, NewD3dParams};
+
+    fn texture(format: D3DFormat, width: u32, height: u32, mips: u32, data: Vec<u8>) -> Dds {
+        let mut dds = Dds::new_d3d(NewD3dParams {
+            height,
+            width,
+            depth: None,
+            format,
+            mipmap_levels: Some(mips),
+            caps2: None,
+        })
+        .unwrap();
+        dds.data = data;
+        dds
+    }
+
+    #[test]
+    fn bgr_texels_are_read_by_their_masks() {
+        let dds = texture(
+            D3DFormat::R8G8B8,
+            2,
+            1,
+            1,
+            vec![0x10, 0x20, 0x30, 0xFF, 0x00, 0x80],
+        );
+        let image = decode_uncompressed(&dds, 0).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [0x30, 0x20, 0x10, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0x80, 0x00, 0xFF, 255]);
+    }
+
+    #[test]
+    fn alpha_comes_from_its_mask() {
+        let dds = texture(D3DFormat::A8R8G8B8, 1, 1, 1, vec![0x01, 0x02, 0x03, 0x40]);
+        let image = decode_uncompressed(&dds, 0).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [0x03, 0x02, 0x01, 0x40]);
+    }
+
+    #[test]
+    fn narrow_channels_are_scaled_to_a_byte() {
+        // Full red, half green and no blue in 5, 6 and 5 bits.
+        let texel: u16 = (0x1F << 11) | (0x20 << 5);
+        let dds = texture(D3DFormat::R5G6B5, 1, 1, 1, texel.to_le_bytes().to_vec());
+        let image = decode_uncompressed(&dds, 0).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [255, 129, 0, 255]);
+    }
+
+    #[test]
+    fn luminance_is_grey() {
+        let dds = texture(D3DFormat::L8, 1, 1, 1, vec![0x80]);
+        assert_eq!(
+            decode_uncompressed(&dds, 0).unwrap().get_pixel(0, 0).0,
+            [0x80, 0x80, 0x80, 255]
+        );
+
+        let dds = texture(D3DFormat::A8L8, 1, 1, 1, vec![0x80, 0x20]);
+        assert_eq!(
+            decode_uncompressed(&dds, 0).unwrap().get_pixel(0, 0).0,
+            [0x80, 0x80, 0x80, 0x20]
+        );
+    }
+
+    #[test]
+    fn mips_are_read_after_the_larger_ones() {
+        let dds = texture(D3DFormat::L8, 2, 2, 2, vec![1, 2, 3, 4, 9]);
+        let mip = decode_uncompressed(&dds, 1).unwrap();
+        assert_eq!(mip.dimensions(), (1, 1));
+        assert_eq!(mip.get_pixel(0, 0).0, [9, 9, 9, 255]);
+    }
+
+    #[test]
+    fn short_data_is_not_decoded() {
+        let dds = texture(D3DFormat::R8G8B8, 2, 2, 1, vec![0; 5]);
+        assert!(decode_uncompressed(&dds, 0).is_none());
+    }
+}
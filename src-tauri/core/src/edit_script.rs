@@ -0,0 +1,472 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt, path::Path};
+use tracing::instrument;
+
+use crate::{
+    get_state_populations::Pop,
+    project::{province_key, state_key, EditError, Project},
+};
+
+/// A list of edits applied to a project in one go, written as JSON or TOML.
+///
+/// ```toml
+/// [[operations]]
+/// op = "transfer_states"
+/// states = ["STATE_SVEALAND", "STATE_NORRLAND"]
+/// to = "NOR"
+///
+/// [[operations]]
+/// op = "set_base_tech"
+/// country = "NOR"
+/// base_tech = "tier_3"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditScript {
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    TransferStates {
+        states: Vec<String>,
+        to: String,
+        #[serde(default)]
+        from: Option<String>,
+    },
+    TransferProvinces {
+        provinces: Vec<String>,
+        to: String,
+    },
+    /// The country is created out of the first state or province, the rest are transferred to
+    /// it.
+    CreateCountry {
+        tag: String,
+        #[serde(default)]
+        states: Vec<String>,
+        #[serde(default)]
+        provinces: Vec<String>,
+        #[serde(default)]
+        from: Option<String>,
+    },
+    SetPops {
+        country: String,
+        state: String,
+        pops: Vec<Pop>,
+    },
+    AddBuilding {
+        country: String,
+        state: String,
+        building: String,
+        #[serde(default = "default_level")]
+        level: i64,
+    },
+    SetBaseTech {
+        country: String,
+        base_tech: String,
+    },
+    /// Every state of the region, whoever owns it.
+    TransferStrategicRegion {
+        region: String,
+        to: String,
+    },
+    /// Moves provinces into another state region, whoever owns them.
+    MoveToStateRegion {
+        provinces: Vec<String>,
+        state: String,
+    },
+    SplitStateRegion {
+        state: String,
+        provinces: Vec<String>,
+        new_state: String,
+    },
+    MergeStateRegions {
+        from: String,
+        into: String,
+    },
+}
+
+fn default_level() -> i64 {
+    1
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::TransferStates { states, to, .. } => {
+                write!(f, "transfer states {} to {}", states.join(", "), to)
+            }
+            Operation::TransferProvinces { provinces, to } => {
+                write!(f, "transfer provinces {} to {}", provinces.join(", "), to)
+            }
+            Operation::CreateCountry {
+                tag,
+                states,
+                provinces,
+                ..
+            } => {
+                let land: Vec<String> = states.iter().chain(provinces).cloned().collect();
+                write!(f, "create country {} from {}", tag, land.join(", "))
+            }
+            Operation::SetPops { country, state, .. } => {
+                write!(f, "set pops of {} in {}", country, state)
+            }
+            Operation::AddBuilding {
+                country,
+                state,
+                building,
+                level,
+            } => write!(
+                f,
+                "add level {} {} to {} in {}",
+                level, building, country, state
+            ),
+            Operation::SetBaseTech { country, base_tech } => {
+                write!(f, "set base tech of {} to {}", country, base_tech)
+            }
+            Operation::TransferStrategicRegion { region, to } => {
+                write!(f, "transfer strategic region {} to {}", region, to)
+            }
+            Operation::MoveToStateRegion { provinces, state } => {
+                write!(f, "move provinces {} to {}", provinces.join(", "), state)
+            }
+            Operation::SplitStateRegion {
+                state,
+                provinces,
+                new_state,
+            } => write!(
+                f,
+                "split {} off {} from {}",
+                provinces.join(", "),
+                new_state,
+                state
+            ),
+            Operation::MergeStateRegions { from, into } => {
+                write!(f, "merge {} into {}", from, into)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Read(std::io::Error),
+    Parse(String),
+    Edit {
+        index: usize,
+        operation: String,
+        error: EditError,
+    },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Read(error) => write!(f, "could not read edit script: {}", error),
+            ScriptError::Parse(error) => write!(f, "could not parse edit script: {}", error),
+            ScriptError::Edit {
+                index,
+                operation,
+                error,
+            } => write!(
+                f,
+                "operation {} ({}) failed, nothing was applied: {}",
+                index + 1,
+                operation,
+                error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// What an applied script did, one entry per operation.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptReport {
+    pub operations: Vec<OperationReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationReport {
+    pub operation: String,
+    pub countries: Vec<String>,
+    /// What else had to change for the edit, such as hubs moved with a state region's borders.
+    pub notes: Vec<String>,
+}
+
+impl ScriptReport {
+    /// Tags of every country an operation touched, including ones left without land.
+    pub fn changed_countries(&self) -> BTreeSet<String> {
+        self.operations
+            .iter()
+            .flat_map(|operation| operation.countries.iter().cloned())
+            .collect()
+    }
+}
+
+impl fmt::Display for ScriptReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, operation) in self.operations.iter().enumerate() {
+            writeln!(
+                f,
+                "{}. {} ({})",
+                index + 1,
+                operation.operation,
+                operation.countries.join(", ")
+            )?;
+            for note in operation.notes.iter() {
+                writeln!(f, "   {}", note)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EditScript {
+    /// Reads a script, as TOML if the file ends in `.toml` and as JSON otherwise.
+    pub fn read(path: &Path) -> Result<EditScript, ScriptError> {
+        let contents = std::fs::read_to_string(path).map_err(ScriptError::Read)?;
+        match path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            true => toml::from_str(&contents).map_err(|e| ScriptError::Parse(e.to_string())),
+            false => serde_json::from_str(&contents).map_err(|e| ScriptError::Parse(e.to_string())),
+        }
+    }
+}
+
+impl Project {
+    /// Applies every operation of the script, or none of them if one fails.
+    #[instrument(skip_all, fields(operations = script.operations.len()))]
+    pub fn apply_script(&mut self, script: &EditScript) -> Result<ScriptReport, ScriptError> {
+        let countries = self.countries.clone();
+        let state_region_edits = self.state_region_edits.clone();
+        let mut operations = vec![];
+
+        for (index, operation) in script.operations.iter().enumerate() {
+            let mut notes = vec![];
+            match self.apply_operation(operation, &mut notes) {
+                Ok(touched) => operations.push(OperationReport {
+                    operation: operation.to_string(),
+                    countries: touched.into_iter().collect(),
+                    notes,
+                }),
+                Err(error) => {
+                    self.countries = countries;
+                    self.state_region_edits = state_region_edits;
+                    return Err(ScriptError::Edit {
+                        index,
+                        operation: operation.to_string(),
+                        error,
+                    });
+                }
+            }
+        }
+
+        Ok(ScriptReport { operations })
+    }
+
+    fn apply_operation(
+        &mut self,
+        operation: &Operation,
+        notes: &mut Vec<String>,
+    ) -> Result<BTreeSet<String>, EditError> {
+        let mut touched = BTreeSet::new();
+        match operation {
+            Operation::TransferStates { states, to, from } => {
+                if states.is_empty() {
+                    return Err(EditError::NothingToTransfer);
+                }
+                for state in states.iter().map(|state| state_key(state)) {
+                    touched.extend(self.state_owners(&state));
+                    self.transfer_state(&state, from.as_deref(), to)?;
+                }
+                touched.insert(to.clone());
+            }
+            Operation::TransferProvinces { provinces, to } => {
+                if provinces.is_empty() {
+                    return Err(EditError::NothingToTransfer);
+                }
+                for province in provinces.iter().map(|province| province_key(province)) {
+                    touched.extend(self.province_owner(&province).map(|(owner, _)| owner));
+                    self.transfer_province(&province, to)?;
+                }
+                touched.insert(to.clone());
+            }
+            Operation::CreateCountry {
+                tag,
+                states,
+                provinces,
+                from,
+            } => {
+                let states: Vec<String> = states.iter().map(|state| state_key(state)).collect();
+                let provinces: Vec<String> = provinces
+                    .iter()
+                    .map(|province| province_key(province))
+                    .collect();
+                match (states.first(), provinces.first()) {
+                    (Some(state), _) => {
+                        touched.extend(self.state_owners(state));
+                        self.create_country(tag, state, from.as_deref())?;
+                    }
+                    (None, Some(province)) => {
+                        touched.extend(self.province_owner(province).map(|(owner, _)| owner));
+                        self.create_country_from_province(tag, province)?;
+                    }
+                    (None, None) => return Err(EditError::NothingToTransfer),
+                }
+                for state in states.iter().skip(1) {
+                    touched.extend(self.state_owners(state));
+                    self.transfer_state(state, from.as_deref(), tag)?;
+                }
+                let skip_provinces = usize::from(states.is_empty());
+                for province in provinces.iter().skip(skip_provinces) {
+                    touched.extend(self.province_owner(province).map(|(owner, _)| owner));
+                    self.transfer_province(province, tag)?;
+                }
+                touched.insert(tag.clone());
+            }
+            Operation::SetPops {
+                country,
+                state,
+                pops,
+            } => {
+                self.set_pops(country, &state_key(state), pops.clone())?;
+                touched.insert(country.clone());
+            }
+            Operation::AddBuilding {
+                country,
+                state,
+                building,
+                level,
+            } => {
+                self.add_building(country, &state_key(state), building, *level)?;
+                touched.insert(country.clone());
+            }
+            Operation::SetBaseTech { country, base_tech } => {
+                self.set_base_tech(country, base_tech)?;
+                touched.insert(country.clone());
+            }
+            Operation::TransferStrategicRegion { region, to } => {
+                touched.extend(self.transfer_strategic_region(region, to)?);
+                touched.insert(to.clone());
+            }
+            Operation::MoveToStateRegion { provinces, state } => {
+                let provinces: Vec<String> = provinces
+                    .iter()
+                    .map(|province| province_key(province))
+                    .collect();
+                let report = self.move_to_state_region(&provinces, &state_key(state))?;
+                touched.extend(report.countries);
+                notes.extend(report.notes);
+            }
+            Operation::SplitStateRegion {
+                state,
+                provinces,
+                new_state,
+            } => {
+                let provinces: Vec<String> = provinces
+                    .iter()
+                    .map(|province| province_key(province))
+                    .collect();
+                let report =
+                    self.split_state_region(&state_key(state), &provinces, &state_key(new_state))?;
+                touched.extend(report.countries);
+                notes.extend(report.notes);
+            }
+            Operation::MergeStateRegions { from, into } => {
+                let report = self.merge_state_regions(&state_key(from), &state_key(into))?;
+                touched.extend(report.countries);
+                notes.extend(report.notes);
+            }
+        }
+        Ok(touched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(file_name: &str, contents: &str) -> Result<EditScript, ScriptError> {
+        let dir = std::env::temp_dir().join(format!("edit-script-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(file_name);
+        std::fs::write(&path, contents).unwrap();
+        let script = EditScript::read(&path);
+        std::fs::remove_file(path).unwrap();
+        script
+    }
+
+    #[test]
+    fn toml_scripts_are_read_by_their_extension() {
+        let script = read(
+            "script.toml",
+            r#"
+[[operations]]
+op = "transfer_states"
+states = ["STATE_SVEALAND", "STATE_NORRLAND"]
+to = "NOR"
+
+[[operations]]
+op = "add_building"
+country = "NOR"
+state = "STATE_SVEALAND"
+building = "building_port"
+"#,
+        )
+        .unwrap();
+        assert_eq!(script.operations.len(), 2);
+        assert!(matches!(
+            &script.operations[0],
+            Operation::TransferStates { states, to, from: None } if states.len() == 2 && to == "NOR"
+        ));
+        assert!(matches!(
+            &script.operations[1],
+            Operation::AddBuilding { level: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn other_scripts_are_read_as_json() {
+        let script = read(
+            "script.json",
+            r#"{ "operations": [
+                { "op": "create_country", "tag": "GOT", "provinces": ["x1A2B3C"], "from": "SWE" },
+                { "op": "merge_state_regions", "from": "STATE_A", "into": "STATE_B" }
+            ] }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            &script.operations[0],
+            Operation::CreateCountry { tag, states, provinces, from: Some(from) }
+                if tag == "GOT" && states.is_empty() && provinces.len() == 1 && from == "SWE"
+        ));
+        assert!(matches!(
+            &script.operations[1],
+            Operation::MergeStateRegions { .. }
+        ));
+    }
+
+    #[test]
+    fn unknown_operations_and_missing_fields_are_parse_errors() {
+        let unknown = read(
+            "unknown.json",
+            r#"{ "operations": [{ "op": "annex", "to": "SWE" }] }"#,
+        );
+        assert!(matches!(unknown, Err(ScriptError::Parse(_))));
+        let missing = read(
+            "missing.toml",
+            "[[operations]]\nop = \"set_base_tech\"\ncountry = \"SWE\"\n",
+        );
+        assert!(matches!(missing, Err(ScriptError::Parse(_))));
+    }
+
+    #[test]
+    fn missing_scripts_are_read_errors() {
+        let path = std::env::temp_dir().join("no-such-edit-script.json");
+        assert!(matches!(EditScript::read(&path), Err(ScriptError::Read(_))));
+    }
+}
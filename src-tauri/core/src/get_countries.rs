@@ -58,6 +58,7 @@ pub fn get_countries(
                             state_buildings,
                         }],
                         coordinates: vec![],
+                        label: None,
                         setup: country_setups.get(&state.owner).unwrap().clone(),
                     });
                 }
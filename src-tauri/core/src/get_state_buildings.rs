@@ -81,11 +81,12 @@ fn parse_state_building(
         .collect()
 }
 
-pub fn get_state_buildings(state_buildings_path: PathBuf) -> HashMap<String, Vec<StateBuilding>> {
+pub fn get_state_buildings(
+    state_buildings_files: Vec<PathBuf>,
+) -> HashMap<String, Vec<StateBuilding>> {
     let mut state_buildings_map: HashMap<String, Vec<StateBuilding>> = HashMap::new();
 
-    for entry in std::fs::read_dir(state_buildings_path).unwrap() {
-        let entry = entry.unwrap().path();
+    for entry in state_buildings_files {
         let string_entry = read(entry).unwrap();
         let tape = TextTape::from_slice(&string_entry).unwrap();
         let reader = tape.windows1252_reader();
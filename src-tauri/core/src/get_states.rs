@@ -19,15 +19,23 @@ pub struct State {
     pub claims: Vec<String>,
 }
 
-pub fn get_states(state_definition_path: PathBuf) -> Vec<State> {
-    let parsed_states = parse_script(&std::fs::read_to_string(state_definition_path).unwrap());
+pub fn get_states(state_files: Vec<PathBuf>) -> Vec<State> {
+    let mut states: Vec<State> = vec![];
 
-    parsed_states[0][1]
-        .as_array()
-        .unwrap()
-        .iter()
-        .map(|state| get_sub_states_from_state(state.as_array().unwrap()))
-        .collect::<Vec<_>>()
+    for state_file in state_files {
+        let parsed_states = parse_script(&std::fs::read_to_string(state_file).unwrap());
+        let Some(parsed_states) = parsed_states[0][1].as_array() else {
+            continue;
+        };
+
+        for state in parsed_states {
+            let state = get_sub_states_from_state(state.as_array().unwrap());
+            states.retain(|existing_state| existing_state.name != state.name);
+            states.push(state);
+        }
+    }
+
+    states
 }
 
 fn get_sub_states_from_state(state: &[JsonValue]) -> State {
@@ -0,0 +1,428 @@
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use crate::province_map_to_geojson::Coords;
+
+// Where along the shape's main axis the width of the shape is measured for the baseline.
+const BASELINE_STATIONS: usize = 12;
+// Shapes less elongated than this get a horizontal label instead of one along their axis.
+const MIN_ELONGATION: f32 = 1.5;
+// Width of a character relative to the font size, for an average name.
+const CHARACTER_WIDTH: f32 = 0.65;
+
+/// Where to draw a name inside a shape, in map coordinates. The text is centred on the
+/// baseline, which runs left to right along the shape's main axis and bends with it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Label {
+    pub anchor: (f32, f32),
+    pub baseline: Vec<(f32, f32)>,
+    pub font_size: f32,
+}
+
+/// Labels the largest part of the shape, at its pole of inaccessibility: the point furthest
+/// from any border. `text` is only used to size the font so it fits.
+pub fn place_label(coords: &Coords, text: &str) -> Option<Label> {
+    let polygon = largest_polygon(coords)?;
+    let (anchor, distance) = pole_of_inaccessibility(&polygon);
+    let characters = text.chars().count().max(1) as f32;
+
+    let baseline = curved_baseline(&polygon, anchor).unwrap_or_else(|| {
+        vec![
+            (anchor.0 - distance, anchor.1),
+            (anchor.0 + distance, anchor.1),
+        ]
+    });
+    let length: f32 = baseline
+        .windows(2)
+        .map(|pair| ((pair[1].0 - pair[0].0).powi(2) + (pair[1].1 - pair[0].1).powi(2)).sqrt())
+        .sum();
+    let font_size = (distance * 1.2).min(length / (characters * CHARACTER_WIDTH));
+
+    Some(Label {
+        anchor,
+        baseline,
+        font_size,
+    })
+}
+
+// A polygon's area and its rings, the outer ring first.
+type AreaPolygon<'a> = (f32, Vec<&'a [(f32, f32)]>);
+
+// Each outer ring is followed by its holes, which wind the other way.
+fn largest_polygon(coords: &Coords) -> Option<Vec<&[(f32, f32)]>> {
+    let mut polygons: Vec<AreaPolygon> = vec![];
+    for ring in coords.iter().filter(|ring| ring.len() > 3) {
+        let area = signed_area(ring);
+        match (area < 0.0, polygons.last_mut()) {
+            (true, Some((_, polygon))) => polygon.push(ring),
+            _ => polygons.push((area, vec![ring])),
+        }
+    }
+    polygons
+        .into_iter()
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, polygon)| polygon)
+}
+
+fn signed_area(ring: &[(f32, f32)]) -> f32 {
+    ring.windows(2)
+        .map(|pair| pair[0].0 * pair[1].1 - pair[1].0 * pair[0].1)
+        .sum::<f32>()
+        / 2.0
+}
+
+// Distance to the nearest border, negative outside the polygon.
+fn signed_distance(polygon: &[&[(f32, f32)]], point: (f32, f32)) -> f32 {
+    let mut inside = false;
+    let mut nearest = f32::MAX;
+    for ring in polygon {
+        for pair in ring.windows(2) {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            if (y1 > point.1) != (y2 > point.1)
+                && point.0 < x1 + (point.1 - y1) / (y2 - y1) * (x2 - x1)
+            {
+                inside = !inside;
+            }
+            nearest = nearest.min(segment_distance(point, pair[0], pair[1]));
+        }
+    }
+    match inside {
+        true => nearest,
+        false => -nearest,
+    }
+}
+
+fn segment_distance(point: (f32, f32), from: (f32, f32), to: (f32, f32)) -> f32 {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx * dx + dy * dy;
+    let along = match length > 0.0 {
+        true => (((point.0 - from.0) * dx + (point.1 - from.1) * dy) / length).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    ((point.0 - from.0 - along * dx).powi(2) + (point.1 - from.1 - along * dy).powi(2)).sqrt()
+}
+
+struct Cell {
+    centre: (f32, f32),
+    half_size: f32,
+    distance: f32,
+}
+
+impl Cell {
+    fn new(polygon: &[&[(f32, f32)]], centre: (f32, f32), half_size: f32) -> Cell {
+        Cell {
+            centre,
+            half_size,
+            distance: signed_distance(polygon, centre),
+        }
+    }
+
+    // The furthest from a border any point in the cell could be.
+    fn potential(&self) -> f32 {
+        self.distance + self.half_size * std::f32::consts::SQRT_2
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.potential() == other.potential()
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.potential().total_cmp(&other.potential())
+    }
+}
+
+// Splits the bounding box into cells, keeps splitting the ones that could hold a point
+// further from the border than the best found, and stops once none can by more than a pixel,
+// or a hundredth of the shape for big ones.
+fn pole_of_inaccessibility(polygon: &[&[(f32, f32)]]) -> ((f32, f32), f32) {
+    let outer = polygon[0];
+    let (mut min, mut max) = (outer[0], outer[0]);
+    for point in outer {
+        min = (min.0.min(point.0), min.1.min(point.1));
+        max = (max.0.max(point.0), max.1.max(point.1));
+    }
+    let cell_size = (max.0 - min.0).min(max.1 - min.1);
+    if cell_size <= 0.0 {
+        return (min, 0.0);
+    }
+
+    let precision = (cell_size / 100.0).max(1.0);
+    let half_size = cell_size / 2.0;
+    let mut cells = BinaryHeap::new();
+    let mut x = min.0;
+    while x < max.0 {
+        let mut y = min.1;
+        while y < max.1 {
+            cells.push(Cell::new(
+                polygon,
+                (x + half_size, y + half_size),
+                half_size,
+            ));
+            y += cell_size;
+        }
+        x += cell_size;
+    }
+
+    let centre = ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+    let mut best = Cell::new(polygon, centre, 0.0);
+    while let Some(cell) = cells.pop() {
+        if cell.distance > best.distance {
+            best = Cell::new(polygon, cell.centre, 0.0);
+        }
+        if cell.potential() - best.distance <= precision {
+            continue;
+        }
+        let half_size = cell.half_size / 2.0;
+        for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let centre = (
+                cell.centre.0 + dx * half_size,
+                cell.centre.1 + dy * half_size,
+            );
+            cells.push(Cell::new(polygon, centre, half_size));
+        }
+    }
+
+    (best.centre, best.distance.max(0.0))
+}
+
+// The shape's main axis from its second moments of area, pointing right, or horizontal when
+// the shape is too round to have one.
+fn main_axis(polygon: &[&[(f32, f32)]]) -> (f32, f32) {
+    let (mut area, mut cx, mut cy) = (0.0f64, 0.0f64, 0.0f64);
+    let (mut xx, mut yy, mut xy) = (0.0f64, 0.0f64, 0.0f64);
+    for ring in polygon {
+        for pair in ring.windows(2) {
+            let ((x1, y1), (x2, y2)) = (
+                (pair[0].0 as f64, pair[0].1 as f64),
+                (pair[1].0 as f64, pair[1].1 as f64),
+            );
+            let cross = x1 * y2 - x2 * y1;
+            area += cross / 2.0;
+            cx += (x1 + x2) * cross / 6.0;
+            cy += (y1 + y2) * cross / 6.0;
+            xx += (x1 * x1 + x1 * x2 + x2 * x2) * cross / 12.0;
+            yy += (y1 * y1 + y1 * y2 + y2 * y2) * cross / 12.0;
+            xy += (x1 * y2 + 2.0 * x1 * y1 + 2.0 * x2 * y2 + x2 * y1) * cross / 24.0;
+        }
+    }
+    if area <= 0.0 {
+        return (1.0, 0.0);
+    }
+    let (cx, cy) = (cx / area, cy / area);
+    let (xx, yy, xy) = (
+        xx - area * cx * cx,
+        yy - area * cy * cy,
+        xy - area * cx * cy,
+    );
+
+    let spread = ((xx - yy).powi(2) + 4.0 * xy * xy).sqrt();
+    let (major, minor) = ((xx + yy + spread) / 2.0, (xx + yy - spread) / 2.0);
+    if minor <= 0.0 || ((major / minor).sqrt() as f32) < MIN_ELONGATION {
+        return (1.0, 0.0);
+    }
+    let angle = 0.5 * (2.0 * xy).atan2(xx - yy);
+    let axis = (angle.cos() as f32, angle.sin() as f32);
+    match axis.0 < 0.0 {
+        true => (-axis.0, -axis.1),
+        false => axis,
+    }
+}
+
+// Follows the middle of the shape along its main axis: at each station the widest stretch of
+// the shape across the axis is measured, and a parabola through their midpoints keeps the
+// baseline smooth.
+fn curved_baseline(polygon: &[&[(f32, f32)]], anchor: (f32, f32)) -> Option<Vec<(f32, f32)>> {
+    let axis = main_axis(polygon);
+    let across = (-axis.1, axis.0);
+    let to_local = |point: &(f32, f32)| {
+        let (dx, dy) = (point.0 - anchor.0, point.1 - anchor.1);
+        (dx * axis.0 + dy * axis.1, dx * across.0 + dy * across.1)
+    };
+    let local: Vec<Vec<(f32, f32)>> = polygon
+        .iter()
+        .map(|ring| ring.iter().map(to_local).collect())
+        .collect();
+    let start = local[0]
+        .iter()
+        .map(|point| point.0)
+        .fold(f32::MAX, f32::min);
+    let end = local[0]
+        .iter()
+        .map(|point| point.0)
+        .fold(f32::MIN, f32::max);
+
+    let mut middles: Vec<(f32, f32, f32)> = vec![];
+    for station in 0..BASELINE_STATIONS {
+        let along = start + (station as f32 + 0.5) / BASELINE_STATIONS as f32 * (end - start);
+        let mut crossings: Vec<f32> = local
+            .iter()
+            .flat_map(|ring| ring.windows(2))
+            .filter(|pair| (pair[0].0 > along) != (pair[1].0 > along))
+            .map(|pair| {
+                let ((u1, v1), (u2, v2)) = (pair[0], pair[1]);
+                v1 + (along - u1) / (u2 - u1) * (v2 - v1)
+            })
+            .collect();
+        crossings.sort_by(f32::total_cmp);
+        let widest = crossings
+            .chunks(2)
+            .filter_map(|span| match span {
+                [from, to] => Some((*from, *to)),
+                _ => None,
+            })
+            .max_by(|a, b| (a.1 - a.0).total_cmp(&(b.1 - b.0)));
+        if let Some((from, to)) = widest {
+            middles.push((along, (from + to) / 2.0, to - from));
+        }
+    }
+
+    // Narrow ends, like peninsulas, would pull the baseline out of the body of the shape.
+    let widest = middles.iter().map(|middle| middle.2).fold(0.0, f32::max);
+    middles.retain(|middle| middle.2 >= widest * 0.4);
+    if middles.len() < 3 {
+        return None;
+    }
+    let (first, last) = (middles[0].0, middles[middles.len() - 1].0);
+    let (a, b, c) = fit_parabola(&middles)?;
+
+    // Keeps the bend gentle enough to read, no deeper than a sixth of the baseline's length.
+    let half_length = (last - first) / 2.0;
+    let max_bend = (last - first) / 6.0 / half_length.powi(2).max(1.0);
+    let (a, b, c) = clamp_bend((a, b, c), max_bend, (first + last) / 2.0);
+
+    Some(
+        (0..=8)
+            .map(|step| {
+                let u = first + step as f32 / 8.0 * (last - first);
+                let v = a + b * u + c * u * u;
+                (
+                    anchor.0 + u * axis.0 + v * across.0,
+                    anchor.1 + u * axis.1 + v * across.1,
+                )
+            })
+            .collect(),
+    )
+}
+
+// Flattens the parabola v = a + bu + cu² to at most `max_bend` around `mid`, keeping its
+// height and slope there so the baseline stays where it was in the middle.
+fn clamp_bend((a, b, c): (f32, f32, f32), max_bend: f32, mid: f32) -> (f32, f32, f32) {
+    let clamped = c.clamp(-max_bend, max_bend);
+    (
+        a - (c - clamped) * mid * mid,
+        b + 2.0 * (c - clamped) * mid,
+        clamped,
+    )
+}
+
+// Least squares fit of v = a + bu + cu², weighted by how wide the shape is at each point.
+fn fit_parabola(points: &[(f32, f32, f32)]) -> Option<(f32, f32, f32)> {
+    let mut sums = [[0.0f64; 4]; 3];
+    for (u, v, weight) in points {
+        let (u, v, weight) = (*u as f64, *v as f64, *weight as f64);
+        let powers = [1.0, u, u * u];
+        for (sum, row_power) in sums.iter_mut().zip(powers) {
+            for (cell, column_power) in sum.iter_mut().zip(powers) {
+                *cell += weight * row_power * column_power;
+            }
+            sum[3] += weight * row_power * v;
+        }
+    }
+
+    // Gaussian elimination with partial pivoting.
+    for column in 0..3 {
+        let pivot =
+            (column..3).max_by(|a, b| sums[*a][column].abs().total_cmp(&sums[*b][column].abs()))?;
+        sums.swap(column, pivot);
+        if sums[column][column].abs() < 1e-9 {
+            return None;
+        }
+        let pivot_row = sums[column];
+        for (row, sum) in sums.iter_mut().enumerate() {
+            if row != column {
+                let factor = sum[column] / pivot_row[column];
+                for (cell, pivot) in sum[column..].iter_mut().zip(&pivot_row[column..]) {
+                    *cell -= factor * pivot;
+                }
+            }
+        }
+    }
+    Some((
+        (sums[0][3] / sums[0][0]) as f32,
+        (sums[1][3] / sums[1][1]) as f32,
+        (sums[2][3] / sums[2][2]) as f32,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parabola((a, b, c): (f32, f32, f32), u: f32) -> f32 {
+        a + b * u + c * u * u
+    }
+
+    #[test]
+    fn clamped_bend_keeps_height_and_slope_at_the_middle() {
+        let fitted = (0.0, 0.0, 1.0);
+        let clamped = clamp_bend(fitted, 0.0, 1.0);
+        assert_eq!(clamped, (-1.0, 2.0, 0.0));
+        assert_eq!(parabola(clamped, 1.0), parabola(fitted, 1.0));
+
+        let fitted = (3.0, -2.0, 0.5);
+        let clamped = clamp_bend(fitted, 0.1, 4.0);
+        assert_eq!(clamped.2, 0.1);
+        assert!((parabola(clamped, 4.0) - parabola(fitted, 4.0)).abs() < 1e-4);
+        let slope = |(_, b, c): (f32, f32, f32)| b + 2.0 * c * 4.0;
+        assert!((slope(clamped) - slope(fitted)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn parabola_through_its_points_is_found() {
+        let points: Vec<(f32, f32, f32)> = [-2.0f32, -1.0, 0.0, 1.0, 3.0]
+            .iter()
+            .map(|u| (*u, 1.5 - 0.5 * u + 0.25 * u * u, 1.0))
+            .collect();
+        let (a, b, c) = fit_parabola(&points).unwrap();
+        assert!((a - 1.5).abs() < 1e-4);
+        assert!((b + 0.5).abs() < 1e-4);
+        assert!((c - 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn wider_points_pull_the_fit_harder() {
+        // A straight line with one point off it, which counts for little when it is narrow.
+        let points = [
+            (0.0, 0.0, 10.0),
+            (1.0, 0.0, 10.0),
+            (2.0, 0.0, 10.0),
+            (3.0, 0.0, 10.0),
+            (4.0, 4.0, 0.01),
+        ];
+        let (a, b, c) = fit_parabola(&points).unwrap();
+        assert!(parabola((a, b, c), 4.0) < 0.5);
+    }
+
+    #[test]
+    fn points_in_a_column_have_no_parabola() {
+        let points = [(1.0, 0.0, 1.0), (1.0, 2.0, 1.0), (1.0, 5.0, 1.0)];
+        assert_eq!(fit_parabola(&points), None);
+    }
+
+    #[test]
+    fn gentle_bend_is_left_alone() {
+        assert_eq!(clamp_bend((1.0, 2.0, 0.05), 0.1, 7.0), (1.0, 2.0, 0.05));
+    }
+}
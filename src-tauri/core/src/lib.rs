@@ -0,0 +1,43 @@
+//! Loading, editing and saving Victoria 3 map history.
+//!
+//! [`project::Project`] is the entry point: it loads a game folder and mods, applies edits
+//! such as state and province transfers, and writes the result as a mod. The other modules
+//! are the parsers, models and map tracing it is built from, for tools that need them
+//! directly.
+
+pub mod adjacency;
+pub mod background;
+pub mod building;
+pub mod coat_of_arms;
+pub mod color_converter;
+pub mod country;
+pub mod country_definition;
+pub mod country_setup;
+pub mod dds_to_png;
+pub mod edit_script;
+pub mod get_countries;
+pub mod get_state_buildings;
+pub mod get_state_populations;
+pub mod get_states;
+pub mod labels;
+pub mod map_export;
+pub mod map_modes;
+pub mod merge_buildings;
+pub mod merge_pops;
+pub mod mod_stack;
+pub mod ownership_layers;
+pub mod pdx_script_parser;
+pub mod project;
+pub mod province_index;
+pub mod province_map_to_geojson;
+pub mod province_types;
+pub mod save_as_pdx_script;
+pub mod simplify;
+pub mod state_regions;
+pub mod strategic_regions;
+pub mod technology;
+pub mod terrain;
+pub mod tiles;
+pub mod topology;
+pub mod transfer_provinces;
+pub mod transfer_state;
@@ -0,0 +1,365 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use resvg::{tiny_skia, usvg};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+    path::{Path, PathBuf},
+};
+use tracing::instrument;
+
+use crate::{
+    country::Country,
+    country_definition::CountryDefinition,
+    labels::Label,
+    province_map_to_geojson::{country_map_to_geojson, simplified_outlines, Coords},
+    simplify::SIMPLIFICATION_TOLERANCES,
+    topology::Topology,
+};
+
+// Generic families are left to the renderer, which may not map them to an installed font.
+const FONT_FAMILY: &str = "Arial, 'DejaVu Sans', 'Liberation Sans', sans-serif";
+// Sizes in pixels of the exported image, whatever its resolution.
+const COUNTRY_BORDER_WIDTH: f32 = 1.5;
+const STATE_BORDER_WIDTH: f32 = 1.0;
+const PROVINCE_BORDER_WIDTH: f32 = 0.5;
+const LEGEND_ROW_HEIGHT: u32 = 20;
+const LEGEND_COLUMN_WIDTH: u32 = 110;
+const LEGEND_PADDING: u32 = 10;
+/// The narrowest map that still fits a column of the legend.
+pub const MIN_WIDTH: u32 = LEGEND_COLUMN_WIDTH + 2 * LEGEND_PADDING;
+// The most a simplified border may be off by in the exported image, in pixels.
+const MAX_SIMPLIFICATION_ERROR: f32 = 0.5;
+
+/// What to draw on top of the ownership colours.
+#[derive(Deserialize, Debug, Clone)]
+pub struct MapExportOptions {
+    /// Width of the image in pixels, the height follows from the map's proportions.
+    pub width: u32,
+    pub state_borders: bool,
+    pub province_borders: bool,
+    pub labels: bool,
+    pub legend: bool,
+}
+
+impl MapExportOptions {
+    /// The coarsest level of detail whose simplification can't be seen at this width.
+    pub fn simplification_level(&self, topology: &Topology) -> usize {
+        let scale = self.width as f32 / topology.extent().0 as f32;
+        SIMPLIFICATION_TOLERANCES
+            .iter()
+            .rposition(|tolerance| tolerance * scale <= MAX_SIMPLIFICATION_ERROR)
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    UnknownFormat(PathBuf),
+    Io(std::io::Error),
+    Render(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::UnknownFormat(path) => {
+                write!(f, "can't export to {:?}, pick a .png or .svg file", path)
+            }
+            ExportError::Io(e) => write!(f, "failed to write the map: {}", e),
+            ExportError::Render(e) => write!(f, "failed to render the map: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+/// The political map as an image: country colours over the game's own map textures, with
+/// optional borders, labels and a legend below the map.
+pub struct MapExport<'a> {
+    /// The full topology, which countries and states are dissolved on.
+    pub topology: &'a Topology,
+    /// A simplified level of it to draw the borders with, see
+    /// [`MapExportOptions::simplification_level`].
+    pub simplified: &'a Topology,
+    pub countries: Vec<Country>,
+    pub country_definitions: &'a HashMap<String, CountryDefinition>,
+    /// PNG images drawn under the countries in order, stretched over the whole map.
+    pub backgrounds: Vec<PathBuf>,
+    pub options: MapExportOptions,
+}
+
+impl MapExport<'_> {
+    /// Writes a PNG or SVG, depending on the file's extension.
+    #[instrument(skip(self), fields(width = self.options.width))]
+    pub fn write(&self, path: &Path) -> Result<(), ExportError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("svg") => Ok(std::fs::write(path, self.to_svg()?)?),
+            Some("png") => self.write_png(path),
+            _ => Err(ExportError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+
+    fn write_png(&self, path: &Path) -> Result<(), ExportError> {
+        let mut options = usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        let tree = usvg::Tree::from_str(&self.to_svg()?, &options)
+            .map_err(|e| ExportError::Render(e.to_string()))?;
+        let size = tree.size().to_int_size();
+        let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or_else(|| {
+            ExportError::Render(format!("{}x{} is too large", size.width(), size.height()))
+        })?;
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        pixmap
+            .save_png(path)
+            .map_err(|e| ExportError::Render(e.to_string()))
+    }
+
+    pub fn to_svg(&self) -> Result<String, ExportError> {
+        let (map_width, map_height) = self.topology.extent();
+        let scale = self.options.width as f32 / map_width as f32;
+        let height = (map_height as f32 * scale).round() as u32;
+        let canvas = Canvas { scale, map_height };
+
+        // Dissolved again rather than trusting the countries' coordinates, which the map may
+        // have swapped for a coarser level of detail.
+        let countries = country_map_to_geojson(self.topology, self.countries.clone());
+        let mut countries: Vec<&Country> = countries
+            .iter()
+            .filter(|country| !country.coordinates.is_empty())
+            .collect();
+        countries.sort_by(|a, b| a.name.cmp(&b.name));
+        let legend_height = match self.options.legend {
+            true => self.legend_height(countries.len()),
+            false => 0,
+        };
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{width}" height="{total}" viewBox="0 0 {width} {total}">"#,
+            width = self.options.width,
+            total = height + legend_height,
+        )
+        .unwrap();
+
+        for background in self.backgrounds.iter() {
+            writeln!(
+                svg,
+                r#"<image x="0" y="0" width="{}" height="{}" preserveAspectRatio="none" xlink:href="data:image/png;base64,{}"/>"#,
+                self.options.width,
+                height,
+                STANDARD.encode(std::fs::read(background)?),
+            )
+            .unwrap();
+        }
+
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for country in countries.iter() {
+            groups.insert(country.name.clone(), provinces_of(country));
+            if self.options.state_borders {
+                for state in country.states.iter() {
+                    groups.insert(
+                        format!("{}:{}", country.name, state.name),
+                        state.provinces.clone(),
+                    );
+                }
+            }
+        }
+        let outlines = simplified_outlines(self.topology, self.simplified, &groups);
+
+        svg.push_str(r#"<g fill-rule="evenodd" stroke-linejoin="round">"#);
+        for country in countries.iter() {
+            let (r, g, b) = self.color_of(country);
+            writeln!(
+                svg,
+                r#"<path d="{}" fill="rgb({},{},{})" fill-opacity="0.5" stroke="none"/>"#,
+                canvas.path(&outlines[&country.name]),
+                r,
+                g,
+                b
+            )
+            .unwrap();
+        }
+        if self.options.province_borders {
+            let province_coords = self.simplified.province_coords();
+            for country in countries.iter() {
+                for province in provinces_of(country) {
+                    if let Some(coords) = province_coords.get(&province) {
+                        writeln!(
+                            svg,
+                            r#"<path d="{}" fill="none" stroke="black" stroke-opacity="0.3" stroke-width="{}"/>"#,
+                            canvas.path(coords),
+                            PROVINCE_BORDER_WIDTH
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+        if self.options.state_borders {
+            for country in countries.iter() {
+                for state in country.states.iter() {
+                    writeln!(
+                        svg,
+                        r#"<path d="{}" fill="none" stroke="black" stroke-opacity="0.6" stroke-width="{}" stroke-dasharray="3 2"/>"#,
+                        canvas.path(&outlines[&format!("{}:{}", country.name, state.name)]),
+                        STATE_BORDER_WIDTH
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        for country in countries.iter() {
+            writeln!(
+                svg,
+                r#"<path d="{}" fill="none" stroke="black" stroke-width="{}"/>"#,
+                canvas.path(&outlines[&country.name]),
+                COUNTRY_BORDER_WIDTH
+            )
+            .unwrap();
+        }
+        svg.push_str("</g>\n");
+
+        if self.options.labels {
+            for country in countries.iter() {
+                if let Some(label) = &country.label {
+                    canvas.write_label(&mut svg, &country.name, label);
+                }
+            }
+        }
+        if self.options.legend {
+            self.write_legend(&mut svg, &countries, height);
+        }
+
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+
+    // Colours come from the country definitions, countries created since are still drawn.
+    fn color_of(&self, country: &Country) -> (u8, u8, u8) {
+        self.country_definitions
+            .get(&country.name)
+            .map(|definition| definition.color)
+            .unwrap_or(country.color)
+    }
+
+    fn legend_columns(&self) -> u32 {
+        (self.options.width.saturating_sub(2 * LEGEND_PADDING) / LEGEND_COLUMN_WIDTH).max(1)
+    }
+
+    fn legend_height(&self, entries: usize) -> u32 {
+        let rows = (entries as u32).div_ceil(self.legend_columns());
+        rows * LEGEND_ROW_HEIGHT + 2 * LEGEND_PADDING
+    }
+
+    // Countries fill the legend column by column, in the order of their tags.
+    fn write_legend(&self, svg: &mut String, countries: &[&Country], top: u32) {
+        writeln!(
+            svg,
+            r#"<rect x="0" y="{}" width="{}" height="{}" fill="white"/>"#,
+            top,
+            self.options.width,
+            self.legend_height(countries.len())
+        )
+        .unwrap();
+        let rows = (countries.len() as u32)
+            .div_ceil(self.legend_columns())
+            .max(1);
+        writeln!(svg, r#"<g font-family="{}" font-size="14">"#, FONT_FAMILY).unwrap();
+        for (index, country) in countries.iter().enumerate() {
+            let (column, row) = (index as u32 / rows, index as u32 % rows);
+            let (x, y) = (
+                LEGEND_PADDING + column * LEGEND_COLUMN_WIDTH,
+                top + LEGEND_PADDING + row * LEGEND_ROW_HEIGHT,
+            );
+            let (r, g, b) = self.color_of(country);
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="14" height="14" fill="rgb({},{},{})" stroke="black"/><text x="{}" y="{}">{}</text>"#,
+                x,
+                y + 3,
+                r,
+                g,
+                b,
+                x + 20,
+                y + 15,
+                escape(&country.name)
+            )
+            .unwrap();
+        }
+        svg.push_str("</g>\n");
+    }
+}
+
+fn provinces_of(country: &Country) -> Vec<String> {
+    country
+        .states
+        .iter()
+        .flat_map(|state| state.provinces.iter().cloned())
+        .collect()
+}
+
+// Map coordinates have y running up from the bottom of the map, SVG has it running down.
+struct Canvas {
+    scale: f32,
+    map_height: i32,
+}
+
+impl Canvas {
+    fn point(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (x * self.scale, (self.map_height as f32 - y) * self.scale)
+    }
+
+    fn path(&self, coords: &Coords) -> String {
+        let mut path = String::new();
+        for ring in coords {
+            for (index, point) in ring.iter().enumerate() {
+                let (x, y) = self.point(*point);
+                let command = if index == 0 { 'M' } else { 'L' };
+                write!(path, "{}{:.1} {:.1}", command, x, y).unwrap();
+            }
+            path.push('Z');
+        }
+        path
+    }
+
+    fn write_label(&self, svg: &mut String, text: &str, label: &Label) {
+        let baseline: String = label
+            .baseline
+            .iter()
+            .enumerate()
+            .map(|(index, point)| {
+                let (x, y) = self.point(*point);
+                format!("{}{:.1} {:.1}", if index == 0 { 'M' } else { 'L' }, x, y)
+            })
+            .collect();
+        let font_size = label.font_size * self.scale;
+        writeln!(
+            svg,
+            r#"<path id="label-{id}" d="{baseline}" fill="none"/><text font-family="{FONT_FAMILY}" font-size="{font_size:.1}" fill="white" stroke="black" stroke-width="{stroke:.1}" paint-order="stroke" dominant-baseline="middle"><textPath xlink:href="{href}" startOffset="50%" text-anchor="middle">{text}</textPath></text>"#,
+            id = escape(text),
+            href = format_args!("#label-{}", escape(text)),
+            stroke = font_size / 12.0,
+            text = escape(text),
+        )
+        .unwrap();
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
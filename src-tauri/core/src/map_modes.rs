@@ -0,0 +1,431 @@
+use jomini::{text::de::from_utf8_reader, TextTape};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+use tracing::instrument;
+
+use crate::{
+    building::Building,
+    color_converter::ColorConverter,
+    country::{Country, State},
+    get_states::get_states,
+    mod_stack::{ModStack, STATES_PATH},
+    topology::Topology,
+};
+
+const CULTURES_PATH: &str = "common/cultures";
+const RELIGIONS_PATH: &str = "common/religions";
+const BUILDING_GROUPS_PATH: &str = "common/building_groups";
+
+pub type Color = (u8, u8, u8);
+
+// For anything without a colour of its own, such as a culture missing from the definitions.
+const NO_DATA: Color = (128, 128, 128);
+const CHANGED: Color = (220, 60, 40);
+const UNCHANGED: Color = (210, 210, 200);
+// Stops of the scale continuous values are coloured along, from low to high.
+const GRADIENT: [Color; 5] = [
+    (68, 1, 84),
+    (59, 82, 139),
+    (33, 145, 140),
+    (94, 201, 98),
+    (253, 231, 37),
+];
+const GRADIENT_LEGEND_STEPS: usize = 5;
+
+/// Ways of colouring the map other than by owner.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum MapMode {
+    Culture,
+    Religion,
+    /// Pops per pixel of the state's provinces.
+    PopDensity,
+    /// Levels of the buildings in a building group, or in any of the groups under it.
+    BuildingLevels {
+        building_group: String,
+    },
+    TechTier,
+    /// Provinces whose owner differs from the game's own history, without any mods.
+    ChangedFromVanilla,
+}
+
+/// A colour per state, keyed `TAG:s:STATE` like the map's states, or per province, with a
+/// legend of what the colours mean.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MapModeColors {
+    pub states: HashMap<String, Color>,
+    pub provinces: HashMap<String, Color>,
+    pub legend: Vec<LegendEntry>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LegendEntry {
+    pub label: String,
+    pub color: Color,
+}
+
+struct Culture {
+    color: Option<Color>,
+    religion: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawBuildingGroup {
+    #[serde(default)]
+    parent_group: Option<String>,
+}
+
+/// Computes map modes for the countries being edited, reading the definitions they need from
+/// the mod stack.
+pub struct MapModes<'a> {
+    pub mod_stack: &'a ModStack,
+    pub topology: &'a Topology,
+    pub countries: &'a [Country],
+}
+
+impl MapModes<'_> {
+    #[instrument(skip(self))]
+    pub fn colors(&self, mode: &MapMode) -> MapModeColors {
+        match mode {
+            MapMode::Culture => {
+                let cultures = parse_cultures(self.mod_stack.files_in(CULTURES_PATH));
+                self.majority_colors(
+                    |pop_culture, _| Some(pop_culture.to_string()),
+                    |culture| cultures.get(culture).and_then(|culture| culture.color),
+                )
+            }
+            MapMode::Religion => {
+                let cultures = parse_cultures(self.mod_stack.files_in(CULTURES_PATH));
+                let religions = parse_colors(self.mod_stack.files_in(RELIGIONS_PATH));
+                // Pops without a religion of their own follow their culture's.
+                self.majority_colors(
+                    |pop_culture, pop_religion| {
+                        pop_religion.map(str::to_string).or_else(|| {
+                            cultures
+                                .get(pop_culture)
+                                .and_then(|culture| culture.religion.clone())
+                        })
+                    },
+                    |religion| religions.get(religion).copied(),
+                )
+            }
+            MapMode::PopDensity => self.pop_density(),
+            MapMode::BuildingLevels { building_group } => self.building_levels(building_group),
+            MapMode::TechTier => self.tech_tiers(),
+            MapMode::ChangedFromVanilla => self.changed_from_vanilla(),
+        }
+    }
+
+    fn sub_states(&self) -> impl Iterator<Item = (String, &Country, &State)> {
+        self.countries.iter().flat_map(|country| {
+            country
+                .states
+                .iter()
+                .map(move |state| (format!("{}:{}", country.name, state.name), country, state))
+        })
+    }
+
+    // Colours each state by whatever most of its pops have, weighing pops by their size.
+    fn majority_colors(
+        &self,
+        group_of: impl Fn(&str, Option<&str>) -> Option<String>,
+        color_of: impl Fn(&str) -> Option<Color>,
+    ) -> MapModeColors {
+        let mut colors = MapModeColors::default();
+        let mut totals: HashMap<String, i64> = HashMap::new();
+        for (key, _, state) in self.sub_states() {
+            let mut sizes: HashMap<String, i64> = HashMap::new();
+            for pop in state.pops.iter() {
+                if let Some(group) = group_of(&pop.culture, pop.religion.as_deref()) {
+                    *sizes.entry(group).or_default() += pop.size;
+                }
+            }
+            let Some((majority, _)) = sizes.iter().max_by_key(|(group, size)| (**size, *group))
+            else {
+                continue;
+            };
+            colors
+                .states
+                .insert(key, color_of(majority).unwrap_or(NO_DATA));
+            for (group, size) in sizes {
+                *totals.entry(group).or_default() += size;
+            }
+        }
+
+        let mut groups: Vec<(String, i64)> = totals.into_iter().collect();
+        groups.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        colors.legend = groups
+            .into_iter()
+            .map(|(group, _)| LegendEntry {
+                color: color_of(&group).unwrap_or(NO_DATA),
+                label: group,
+            })
+            .collect();
+        colors
+    }
+
+    // Densities span orders of magnitude, so they are coloured on a log scale.
+    fn pop_density(&self) -> MapModeColors {
+        let areas = self.province_areas();
+        let densities: Vec<(String, f64)> = self
+            .sub_states()
+            .filter_map(|(key, _, state)| {
+                let area: f64 = state
+                    .provinces
+                    .iter()
+                    .filter_map(|province| areas.get(province))
+                    .sum();
+                let pops: i64 = state.pops.iter().map(|pop| pop.size).sum();
+                (area > 0.0).then(|| (key, (pops as f64 / area).max(0.1).log10()))
+            })
+            .collect();
+        gradient_colors(densities, |value| {
+            format!("{:.1} pops per pixel", 10f64.powf(value))
+        })
+    }
+
+    fn building_levels(&self, building_group: &str) -> MapModeColors {
+        let groups = self.groups_within(building_group);
+        let building_groups: HashMap<String, String> = Building::parse_from(self.mod_stack)
+            .into_iter()
+            .filter_map(|building| Some((building.name, building.building_group?)))
+            .collect();
+        let levels: Vec<(String, f64)> = self
+            .sub_states()
+            .map(|(key, _, state)| {
+                let levels: i64 = state
+                    .state_buildings
+                    .iter()
+                    .filter(|building| {
+                        building_groups
+                            .get(&building.name)
+                            .is_some_and(|group| groups.contains(group))
+                    })
+                    .filter_map(|building| building.level)
+                    .sum();
+                (key, levels as f64)
+            })
+            .collect();
+        gradient_colors(levels, |value| format!("{:.0} levels", value))
+    }
+
+    // The group and every group whose parents lead up to it.
+    fn groups_within(&self, building_group: &str) -> HashSet<String> {
+        let parents = parse_building_group_parents(self.mod_stack.files_in(BUILDING_GROUPS_PATH));
+        let is_within = |group: &str| {
+            let mut group = Some(group);
+            let mut seen = HashSet::new();
+            while let Some(current) = group {
+                if current == building_group {
+                    return true;
+                }
+                if !seen.insert(current) {
+                    return false;
+                }
+                group = parents.get(current).map(String::as_str);
+            }
+            false
+        };
+        let mut groups: HashSet<String> = parents
+            .keys()
+            .filter(|group| is_within(group))
+            .cloned()
+            .collect();
+        groups.insert(building_group.to_string());
+        groups
+    }
+
+    fn tech_tiers(&self) -> MapModeColors {
+        let tiers: Vec<(String, f64)> = self
+            .sub_states()
+            .filter_map(|(key, country, _)| {
+                let tier = country.setup.base_tech.as_deref()?;
+                let tier: f64 = tier.trim_start_matches("tier_").parse().ok()?;
+                Some((key, tier))
+            })
+            .collect();
+        gradient_colors(tiers, |value| format!("Tier {:.0}", value))
+    }
+
+    fn changed_from_vanilla(&self) -> MapModeColors {
+        let vanilla = ModStack::new(self.mod_stack.game_folder.clone(), vec![]);
+        let vanilla_owners: HashMap<String, String> = get_states(vanilla.files_in(STATES_PATH))
+            .into_iter()
+            .flat_map(|state| state.sub_states)
+            .flat_map(|sub_state| {
+                sub_state
+                    .provinces
+                    .into_iter()
+                    .map(move |province| (province, sub_state.owner.clone()))
+            })
+            .collect();
+
+        let mut colors = MapModeColors::default();
+        for (_, country, state) in self.sub_states() {
+            for province in state.provinces.iter() {
+                let changed = vanilla_owners.get(province) != Some(&country.name);
+                colors
+                    .provinces
+                    .insert(province.clone(), if changed { CHANGED } else { UNCHANGED });
+            }
+        }
+        colors.legend = vec![
+            LegendEntry {
+                label: "Changed owner".to_string(),
+                color: CHANGED,
+            },
+            LegendEntry {
+                label: "Same owner".to_string(),
+                color: UNCHANGED,
+            },
+        ];
+        colors
+    }
+
+    // In square pixels, holes taken out.
+    fn province_areas(&self) -> HashMap<String, f64> {
+        self.topology
+            .province_coords()
+            .into_iter()
+            .map(|(province, coords)| {
+                let area: f64 = coords.iter().map(|ring| signed_area(ring)).sum();
+                (province, area.abs())
+            })
+            .collect()
+    }
+}
+
+fn signed_area(ring: &[(f32, f32)]) -> f64 {
+    ring.windows(2)
+        .map(|pair| {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            x1 as f64 * y2 as f64 - x2 as f64 * y1 as f64
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+// Spreads the values over the gradient from the lowest to the highest.
+fn gradient_colors(values: Vec<(String, f64)>, label: impl Fn(f64) -> String) -> MapModeColors {
+    let min = values
+        .iter()
+        .map(|(_, value)| *value)
+        .fold(f64::MAX, f64::min);
+    let max = values
+        .iter()
+        .map(|(_, value)| *value)
+        .fold(f64::MIN, f64::max);
+    if values.is_empty() {
+        return MapModeColors::default();
+    }
+    let position = |value: f64| match max > min {
+        true => (value - min) / (max - min),
+        false => 0.0,
+    };
+
+    let states = values
+        .into_iter()
+        .map(|(key, value)| (key, gradient(position(value))))
+        .collect();
+    let steps = match max > min {
+        true => GRADIENT_LEGEND_STEPS,
+        false => 1,
+    };
+    let legend = (0..steps)
+        .map(|step| {
+            let value = min + (max - min) * step as f64 / (steps - 1).max(1) as f64;
+            LegendEntry {
+                label: label(value),
+                color: gradient(position(value)),
+            }
+        })
+        .collect();
+
+    MapModeColors {
+        states,
+        provinces: HashMap::new(),
+        legend,
+    }
+}
+
+fn gradient(position: f64) -> Color {
+    let scaled = position.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f64;
+    let index = (scaled.floor() as usize).min(GRADIENT.len() - 2);
+    let (from, to, t) = (GRADIENT[index], GRADIENT[index + 1], scaled - index as f64);
+    let mix = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * t).round() as u8;
+    (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+fn parse_cultures(files: Vec<PathBuf>) -> HashMap<String, Culture> {
+    let mut cultures = HashMap::new();
+    for file in files {
+        if file.extension().unwrap() != "txt" {
+            continue;
+        }
+        let data = std::fs::read(&file).unwrap();
+        let tape = TextTape::from_slice(&data).unwrap();
+        for (key, _op, value) in tape.utf8_reader().fields() {
+            let Ok(fields) = value.read_object() else {
+                continue;
+            };
+            let mut culture = Culture {
+                color: None,
+                religion: None,
+            };
+            for (field, _op, value) in fields.fields() {
+                match field.read_str().as_ref() {
+                    "color" => culture.color = ColorConverter::read_color(&value),
+                    "religion" => culture.religion = value.read_string().ok(),
+                    _ => {}
+                }
+            }
+            cultures.insert(key.read_str().to_string(), culture);
+        }
+    }
+    cultures
+}
+
+// The `color` of each top level definition, such as religions.
+fn parse_colors(files: Vec<PathBuf>) -> HashMap<String, Color> {
+    let mut colors = HashMap::new();
+    for file in files {
+        if file.extension().unwrap() != "txt" {
+            continue;
+        }
+        let data = std::fs::read(&file).unwrap();
+        let tape = TextTape::from_slice(&data).unwrap();
+        for (key, _op, value) in tape.utf8_reader().fields() {
+            let Ok(fields) = value.read_object() else {
+                continue;
+            };
+            let color = fields
+                .fields()
+                .find(|(field, _, _)| field.read_str() == "color")
+                .and_then(|(_, _, value)| ColorConverter::read_color(&value));
+            if let Some(color) = color {
+                colors.insert(key.read_str().to_string(), color);
+            }
+        }
+    }
+    colors
+}
+
+fn parse_building_group_parents(files: Vec<PathBuf>) -> HashMap<String, String> {
+    let mut parents = HashMap::new();
+    for file in files {
+        if file.extension().unwrap() != "txt" {
+            continue;
+        }
+        let groups: HashMap<String, RawBuildingGroup> =
+            from_utf8_reader(&*std::fs::read(file).unwrap()).unwrap();
+        for (group, raw_group) in groups {
+            if let Some(parent) = raw_group.parent_group {
+                parents.insert(group, parent);
+            }
+        }
+    }
+    parents
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+pub const STATES_PATH: &str = "common/history/states";
+pub const STATE_POPS_PATH: &str = "common/history/pops";
+pub const STATE_BUILDINGS_PATH: &str = "common/history/buildings";
+pub const COUNTRY_DEFINITIONS_PATH: &str = "common/country_definitions";
+pub const COUNTRY_SETUP_PATH: &str = "common/history/countries";
+pub const PROVINCES_PATH: &str = "map_data/provinces.png";
+pub const DEFAULT_MAP_PATH: &str = "map_data/default.map";
+pub const PROVINCE_TERRAINS_PATH: &str = "map_data/province_terrains.txt";
+pub const HEIGHTMAP_PATH: &str = "map_data/heightmap.png";
+pub const ADJACENCIES_PATH: &str = "map_data/adjacencies.csv";
+
+/// The game folder followed by mods in load order. A file in a later layer replaces the file
+/// with the same relative path in earlier layers, the same way the game resolves mods.
+#[derive(Debug, Clone)]
+pub struct ModStack {
+    pub game_folder: PathBuf,
+    pub mods: Vec<PathBuf>,
+}
+
+impl ModStack {
+    pub fn new(game_folder: PathBuf, mods: Vec<PathBuf>) -> Self {
+        Self { game_folder, mods }
+    }
+
+    pub fn with_mod(&self, mod_path: PathBuf) -> Self {
+        let mut mods = self.mods.clone();
+        mods.push(mod_path);
+        Self {
+            game_folder: self.game_folder.clone(),
+            mods,
+        }
+    }
+
+    pub fn game_path(&self) -> PathBuf {
+        self.game_folder.join("game")
+    }
+
+    fn layers(&self) -> Vec<PathBuf> {
+        let mut layers = vec![self.game_path()];
+        layers.extend(self.mods.iter().cloned());
+        layers
+    }
+
+    /// Files in `relative_dir` across all layers. Files are ordered by layer, so anything parsed
+    /// into a map by inserting in order ends up with the topmost definition.
+    pub fn files_in(&self, relative_dir: &str) -> Vec<PathBuf> {
+        let mut files: HashMap<OsString, (usize, PathBuf)> = HashMap::new();
+
+        for (layer_index, layer) in self.layers().iter().enumerate() {
+            for file in files_in_dir(&layer.join(relative_dir)) {
+                files.insert(file.file_name().unwrap().to_owned(), (layer_index, file));
+            }
+        }
+
+        let mut files: Vec<(usize, PathBuf)> = files.into_values().collect();
+        files.sort();
+        files.into_iter().map(|(_, file)| file).collect()
+    }
+
+    /// Files anywhere under `relative_dir` across all layers, by their path relative to it with
+    /// `/` separators, each the topmost layer's copy.
+    pub fn files_under(&self, relative_dir: &str) -> BTreeMap<String, PathBuf> {
+        let mut files = BTreeMap::new();
+        for layer in self.layers() {
+            let dir = layer.join(relative_dir);
+            let mut pending = vec![dir.clone()];
+            while let Some(current) = pending.pop() {
+                let Ok(entries) = std::fs::read_dir(&current) else {
+                    continue;
+                };
+                for entry in entries {
+                    let path = entry.unwrap().path();
+                    if path.is_dir() {
+                        pending.push(path);
+                    } else {
+                        let relative_path = path.strip_prefix(&dir).unwrap();
+                        let relative_path = relative_path
+                            .components()
+                            .map(|component| component.as_os_str().to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join("/");
+                        files.insert(relative_path, path);
+                    }
+                }
+            }
+        }
+        files
+    }
+
+    /// The topmost layer's copy of a file, falling back to the game's path if none has it.
+    pub fn file(&self, relative_path: &str) -> PathBuf {
+        self.layers()
+            .iter()
+            .rev()
+            .map(|layer| layer.join(relative_path))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| self.game_path().join(relative_path))
+    }
+}
+
+pub fn files_in_dir(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files
+}
@@ -0,0 +1,493 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+};
+use tracing::instrument;
+
+use crate::{
+    country::{Country, State},
+    project::{province_key, Project},
+    topology::{ArcPolygon, ArcRef, Topology},
+};
+
+/// Who owns what, as layers of countries, states and provinces for GIS tools.
+///
+/// Coordinates are those of the province map's pixels, x to the right and y down from its top
+/// left corner, unlike the map's own which have y running up. Rings wind counterclockwise
+/// around what they enclose, as RFC 7946 asks.
+pub struct OwnershipLayers<'a> {
+    pub topology: &'a Topology,
+    pub countries: &'a [Country],
+}
+
+#[derive(Debug)]
+pub enum ExchangeError {
+    UnknownFormat(PathBuf),
+    Read(std::io::Error),
+    Write(std::io::Error),
+    Parse(String),
+}
+
+impl fmt::Display for ExchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExchangeError::UnknownFormat(path) => write!(
+                f,
+                "can't tell the format of {:?}, use .geojson, .topojson or .csv",
+                path
+            ),
+            ExchangeError::Read(e) => write!(f, "could not read ownership: {}", e),
+            ExchangeError::Write(e) => write!(f, "could not write ownership: {}", e),
+            ExchangeError::Parse(e) => write!(f, "could not parse ownership: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExchangeError {}
+
+// A feature's outline and its TopoJSON properties.
+type Feature = (Vec<ArcPolygon>, Map<String, Value>);
+
+impl OwnershipLayers<'_> {
+    /// Writes GeoJSON for `.geojson` and `.json` files and TopoJSON for `.topojson` ones.
+    #[instrument(skip(self))]
+    pub fn write(&self, path: &Path) -> Result<(), ExchangeError> {
+        let layers = match extension_of(path).as_deref() {
+            Some("geojson" | "json") => self.to_geojson(),
+            Some("topojson") => self.to_topojson(),
+            _ => return Err(ExchangeError::UnknownFormat(path.to_path_buf())),
+        };
+        let layers = serde_json::to_string(&layers).unwrap();
+        std::fs::write(path, layers).map_err(ExchangeError::Write)
+    }
+
+    /// A single feature collection, with a `layer` property telling countries, states and
+    /// provinces apart.
+    pub fn to_geojson(&self) -> Value {
+        let height = self.topology.extent().1;
+        let features: Vec<Value> = self
+            .layers()
+            .into_iter()
+            .flat_map(|(layer, features)| {
+                features.into_iter().map(move |(polygons, mut properties)| {
+                    properties.insert("layer".to_string(), json!(layer));
+                    let coordinates: Vec<Vec<Vec<[i32; 2]>>> = polygons
+                        .iter()
+                        .map(|polygon| {
+                            polygon
+                                .iter()
+                                .map(|ring| self.flipped_ring(ring, height))
+                                .collect()
+                        })
+                        .collect();
+                    json!({
+                        "type": "Feature",
+                        "geometry": { "type": "MultiPolygon", "coordinates": coordinates },
+                        "properties": properties,
+                    })
+                })
+            })
+            .collect();
+
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+
+    /// An object per layer, sharing the topology's arcs. The transform flips y, so the arcs are
+    /// written as they are and only the rings are reversed.
+    pub fn to_topojson(&self) -> Value {
+        let height = self.topology.extent().1;
+        let objects: Map<String, Value> = self
+            .layers()
+            .into_iter()
+            .map(|(layer, features)| {
+                let geometries: Vec<Value> = features
+                    .into_iter()
+                    .map(|(polygons, properties)| {
+                        let arcs: Vec<ArcPolygon> = polygons
+                            .iter()
+                            .map(|polygon| polygon.iter().map(|ring| reversed(ring)).collect())
+                            .collect();
+                        json!({ "type": "MultiPolygon", "arcs": arcs, "properties": properties })
+                    })
+                    .collect();
+                (
+                    format!("{}s", layer),
+                    json!({ "type": "GeometryCollection", "geometries": geometries }),
+                )
+            })
+            .collect();
+
+        json!({
+            "type": "Topology",
+            "transform": { "scale": [1, -1], "translate": [0, height] },
+            "arcs": self.topology.to_topojson(HashMap::new()).arcs,
+            "objects": objects,
+        })
+    }
+
+    // Each layer's features, as the polygons of their outline and their properties.
+    fn layers(&self) -> Vec<(&'static str, Vec<Feature>)> {
+        let country_provinces: Vec<(String, Vec<String>)> = self
+            .countries
+            .iter()
+            .map(|country| {
+                let provinces = country
+                    .states
+                    .iter()
+                    .flat_map(|state| state.provinces.iter().cloned())
+                    .collect();
+                (country.name.clone(), provinces)
+            })
+            .collect();
+        let state_provinces: Vec<(String, &[String])> = self
+            .countries
+            .iter()
+            .flat_map(|country| {
+                country.states.iter().map(|state| {
+                    (
+                        format!("{}:{}", country.name, state.name),
+                        state.provinces.as_slice(),
+                    )
+                })
+            })
+            .collect();
+        let mut country_polygons = self.topology.dissolve(
+            country_provinces
+                .iter()
+                .map(|(tag, provinces)| (tag.clone(), provinces.as_slice())),
+        );
+        let mut state_polygons = self.topology.dissolve(state_provinces);
+
+        let mut countries = vec![];
+        let mut states = vec![];
+        let mut provinces = vec![];
+        for country in self.countries {
+            let pops: i64 = country.states.iter().map(state_pops).sum();
+            countries.push((
+                country_polygons.remove(&country.name).unwrap_or_default(),
+                properties(json!({ "tag": country.name, "pops": pops })),
+            ));
+            for state in country.states.iter() {
+                states.push((
+                    state_polygons
+                        .remove(&format!("{}:{}", country.name, state.name))
+                        .unwrap_or_default(),
+                    properties(json!({
+                        "tag": country.name,
+                        "state": state.name,
+                        "pops": state_pops(state),
+                    })),
+                ));
+                for province in state.provinces.iter() {
+                    let Some(polygons) = self.topology.provinces.get(province) else {
+                        continue;
+                    };
+                    provinces.push((
+                        polygons.clone(),
+                        properties(json!({
+                            "province": province,
+                            "tag": country.name,
+                            "state": state.name,
+                        })),
+                    ));
+                }
+            }
+        }
+
+        vec![
+            ("country", countries),
+            ("state", states),
+            ("province", provinces),
+        ]
+    }
+
+    fn flipped_ring(&self, ring: &[i32], height: i32) -> Vec<[i32; 2]> {
+        let polygon: ArcPolygon = vec![ring.to_vec()];
+        let coords = self.topology.polygons_to_coords(&[polygon]);
+        coords[0]
+            .iter()
+            .rev()
+            .map(|(x, y)| [*x as i32, height - *y as i32])
+            .collect()
+    }
+}
+
+// The same ring walked the other way round, each arc backwards.
+fn reversed(ring: &[ArcRef]) -> Vec<ArcRef> {
+    ring.iter().rev().map(|arc_ref| !arc_ref).collect()
+}
+
+fn state_pops(state: &State) -> i64 {
+    state.pops.iter().map(|pop| pop.size).sum()
+}
+
+fn properties(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(properties) => properties,
+        _ => unreachable!(),
+    }
+}
+
+fn extension_of(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+}
+
+/// A province and the country it should belong to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvinceAssignment {
+    pub province: String,
+    pub tag: String,
+}
+
+/// The assignments read from a file, and the rows of it that aren't one.
+#[derive(Debug, Clone, Default)]
+pub struct Assignments {
+    pub assignments: Vec<ProvinceAssignment>,
+    pub rejected: Vec<RejectedAssignment>,
+}
+
+/// Reads assignments from a CSV of provinces and tags, or from GeoJSON features with
+/// `province` and `tag` properties, such as the provinces of an edited export. Features
+/// without both, like the country and state ones, are left out. CSV rows without both are
+/// rejected by their line in the file.
+pub fn read_assignments(path: &Path) -> Result<Assignments, ExchangeError> {
+    let contents = std::fs::read_to_string(path).map_err(ExchangeError::Read)?;
+    match extension_of(path).as_deref() {
+        Some("csv") => assignments_from_csv(&contents),
+        Some("geojson" | "json") => assignments_from_geojson(&contents),
+        _ => Err(ExchangeError::UnknownFormat(path.to_path_buf())),
+    }
+}
+
+// The `province` and `tag` columns if there is a header naming them, the first two otherwise.
+fn assignments_from_csv(contents: &str) -> Result<Assignments, ExchangeError> {
+    let mut rows = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let cells = line
+                .split([',', ';'])
+                .map(|cell| cell.trim().trim_matches('"').to_string())
+                .collect::<Vec<String>>();
+            (index + 1, cells)
+        })
+        .peekable();
+    let header = rows
+        .peek()
+        .map(|(_, cells)| cells.clone())
+        .unwrap_or_default();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|cell| cell.eq_ignore_ascii_case(name))
+    };
+    let (province_column, tag_column) = match (column("province"), column("tag")) {
+        (Some(province), Some(tag)) => {
+            rows.next();
+            (province, tag)
+        }
+        _ => (0, 1),
+    };
+
+    let mut assignments = Assignments::default();
+    for (line, row) in rows {
+        match (row.get(province_column), row.get(tag_column)) {
+            (Some(province), Some(tag)) if !province.is_empty() && !tag.is_empty() => {
+                assignments.assignments.push(ProvinceAssignment {
+                    province: province_key(province),
+                    tag: tag.to_uppercase(),
+                })
+            }
+            (province, tag) => assignments.rejected.push(RejectedAssignment {
+                province: province.cloned().unwrap_or_default(),
+                tag: tag.cloned().unwrap_or_default(),
+                reason: "no province or tag".to_string(),
+                line: Some(line),
+            }),
+        }
+    }
+    Ok(assignments)
+}
+
+fn assignments_from_geojson(contents: &str) -> Result<Assignments, ExchangeError> {
+    let geojson: Value =
+        serde_json::from_str(contents).map_err(|e| ExchangeError::Parse(e.to_string()))?;
+    let features = geojson["features"]
+        .as_array()
+        .ok_or_else(|| ExchangeError::Parse("not a GeoJSON feature collection".to_string()))?;
+
+    Ok(Assignments {
+        assignments: features
+            .iter()
+            .filter_map(|feature| {
+                let properties = &feature["properties"];
+                Some(ProvinceAssignment {
+                    province: province_key(properties["province"].as_str()?),
+                    tag: properties["tag"].as_str()?.to_uppercase(),
+                })
+            })
+            .collect(),
+        rejected: vec![],
+    })
+}
+
+/// What importing assignments did. Provinces already owned by their country are left alone.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub transferred: usize,
+    pub unchanged: usize,
+    pub rejected: Vec<RejectedAssignment>,
+    /// Tags of every country that gained or lost a province.
+    pub countries: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedAssignment {
+    pub province: String,
+    pub tag: String,
+    pub reason: String,
+    /// The line of the file, for rows that couldn't be read as an assignment.
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Transferred {} provinces, {} were already owned by their country.",
+            self.transferred, self.unchanged
+        )?;
+        if !self.rejected.is_empty() {
+            writeln!(f, "Rejected {} provinces:", self.rejected.len())?;
+            for rejected in self.rejected.iter() {
+                match rejected.line {
+                    Some(line) => writeln!(f, "line {}: {}", line, rejected.reason)?,
+                    None => writeln!(
+                        f,
+                        "{} to {}: {}",
+                        rejected.province, rejected.tag, rejected.reason
+                    )?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Project {
+    /// Transfers each province to its country, creating countries from their definitions as
+    /// needed. Unlike an edit script, provinces that can't be transferred are reported and the
+    /// rest still are, along with the rows of the file that couldn't be read.
+    #[instrument(skip_all, fields(assignments = assignments.assignments.len()))]
+    pub fn apply_assignments(&mut self, assignments: &Assignments) -> ImportReport {
+        let mut report = ImportReport {
+            rejected: assignments.rejected.clone(),
+            ..Default::default()
+        };
+        for assignment in assignments.assignments.iter() {
+            let owner = self.province_owner(&assignment.province);
+            if owner
+                .as_ref()
+                .is_some_and(|(owner, _)| *owner == assignment.tag)
+            {
+                report.unchanged += 1;
+                continue;
+            }
+            match self.transfer_province(&assignment.province, &assignment.tag) {
+                Ok(()) => {
+                    report.transferred += 1;
+                    report.countries.extend(owner.map(|(owner, _)| owner));
+                    report.countries.insert(assignment.tag.clone());
+                }
+                Err(e) => report.rejected.push(RejectedAssignment {
+                    province: assignment.province.clone(),
+                    tag: assignment.tag.clone(),
+                    reason: e.to_string(),
+                    line: None,
+                }),
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(province: &str, tag: &str) -> ProvinceAssignment {
+        ProvinceAssignment {
+            province: province.to_string(),
+            tag: tag.to_string(),
+        }
+    }
+
+    #[test]
+    fn csv_columns_are_found_by_their_header() {
+        let assignments =
+            assignments_from_csv("tag;province\nswe;x1a2b3c\n\"NOR\",0XFF0000\n").unwrap();
+        assert_eq!(
+            assignments.assignments,
+            vec![assignment("x1A2B3C", "SWE"), assignment("xFF0000", "NOR")]
+        );
+        assert!(assignments.rejected.is_empty());
+    }
+
+    #[test]
+    fn csv_without_a_header_is_province_then_tag() {
+        let assignments = assignments_from_csv("x1A2B3C,SWE\n").unwrap();
+        assert_eq!(assignments.assignments, vec![assignment("x1A2B3C", "SWE")]);
+    }
+
+    #[test]
+    fn bad_csv_rows_are_rejected_by_their_line() {
+        let assignments =
+            assignments_from_csv("province,tag\nx000001,SWE\n\nx000002\n,NOR\nx000003,DEN\n")
+                .unwrap();
+        assert_eq!(
+            assignments.assignments,
+            vec![assignment("x000001", "SWE"), assignment("x000003", "DEN")]
+        );
+        let lines: Vec<Option<usize>> = assignments
+            .rejected
+            .iter()
+            .map(|rejected| rejected.line)
+            .collect();
+        assert_eq!(lines, vec![Some(4), Some(5)]);
+        assert_eq!(assignments.rejected[0].province, "x000002");
+        assert_eq!(assignments.rejected[1].tag, "NOR");
+    }
+
+    #[test]
+    fn geojson_features_without_a_province_and_tag_are_left_out() {
+        let geojson = json!({
+            "type": "FeatureCollection",
+            "features": [
+                { "type": "Feature", "properties": { "province": "0x1a2b3c", "tag": "swe" } },
+                { "type": "Feature", "properties": { "tag": "SWE" } },
+                { "type": "Feature", "properties": { "province": "x000001", "state": "s:STATE_SVEALAND" } },
+            ]
+        });
+        let assignments = assignments_from_geojson(&geojson.to_string()).unwrap();
+        assert_eq!(assignments.assignments, vec![assignment("x1A2B3C", "SWE")]);
+        assert!(assignments.rejected.is_empty());
+    }
+
+    #[test]
+    fn geojson_must_be_a_feature_collection() {
+        assert!(matches!(
+            assignments_from_geojson("{\"type\": \"Feature\"}"),
+            Err(ExchangeError::Parse(_))
+        ));
+        assert!(matches!(
+            assignments_from_geojson("not json"),
+            Err(ExchangeError::Parse(_))
+        ));
+    }
+}
@@ -9,6 +9,7 @@ use nom::{
     IResult, Parser,
 };
 use serde_json::Value as JsonValue;
+use std::ops::Range;
 
 pub fn parse_script(script: &str) -> JsonValue {
     convert_vector_of_tuples_to_vector_of_vectors(preceded(bom, keys_and_values)(script).unwrap().1)
@@ -124,3 +125,83 @@ fn convert_tuple_to_array(tuple: (&str, JsonValue)) -> JsonValue {
 fn convert_vector_of_tuples_to_vector_of_vectors(array: Vec<(&str, JsonValue)>) -> JsonValue {
     JsonValue::Array(array.into_iter().map(convert_tuple_to_array).collect())
 }
+
+/// The `key = { ... }` blocks at the top level of `script`, each with the byte range from its
+/// key to its closing brace, so a block can be replaced without touching the rest of the file.
+/// Comments and quoted strings are skipped.
+pub fn block_ranges(script: &str) -> Vec<(String, Range<usize>)> {
+    let bytes = script.as_bytes();
+    let mut blocks = vec![];
+    let mut depth: usize = 0;
+    let mut key: Option<Range<usize>> = None;
+    let mut after_operator = false;
+    let mut open: Option<(String, usize)> = None;
+    let mut index = if script.starts_with('\u{feff}') { 3 } else { 0 };
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'#' => {
+                while index < bytes.len() && bytes[index] != b'\n' {
+                    index += 1;
+                }
+                continue;
+            }
+            b'"' => {
+                index += 1;
+                while index < bytes.len() && bytes[index] != b'"' {
+                    index += if bytes[index] == b'\\' { 2 } else { 1 };
+                }
+                key = None;
+            }
+            b'{' => {
+                if depth == 0 && after_operator {
+                    open = key
+                        .take()
+                        .map(|key| (script[key.clone()].to_string(), key.start));
+                }
+                depth += 1;
+                after_operator = false;
+            }
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                key = None;
+                if depth == 0 {
+                    if let Some((key, start)) = open.take() {
+                        blocks.push((key, start..index + 1));
+                    }
+                }
+            }
+            b'=' => after_operator = key.is_some(),
+            byte if byte.is_ascii_whitespace() => {}
+            _ => {
+                let start = index;
+                while index < bytes.len()
+                    && !bytes[index].is_ascii_whitespace()
+                    && !b"={}#\"".contains(&bytes[index])
+                {
+                    index += 1;
+                }
+                key = Some(start..index);
+                after_operator = false;
+                continue;
+            }
+        }
+        index += 1;
+    }
+
+    blocks
+}
+
+/// `script` with each range replaced by its text. Ranges must not overlap.
+pub fn replace_ranges(script: &str, mut replacements: Vec<(Range<usize>, String)>) -> String {
+    replacements.sort_by_key(|(range, _)| range.start);
+    let mut replaced = String::new();
+    let mut end = 0;
+    for (range, text) in replacements {
+        replaced.push_str(&script[end..range.start]);
+        replaced.push_str(&text);
+        end = range.end;
+    }
+    replaced.push_str(&script[end..]);
+    replaced
+}
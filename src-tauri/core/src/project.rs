@@ -0,0 +1,618 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use tracing::instrument;
+
+use crate::{
+    building::Building,
+    country::Country,
+    country_definition::CountryDefinition,
+    country_setup::CountrySetup,
+    get_countries::get_countries,
+    get_state_buildings::{get_state_buildings, StateBuilding},
+    get_state_populations::{get_state_populations, Pop},
+    get_states::get_states,
+    mod_stack::{
+        ModStack, COUNTRY_DEFINITIONS_PATH, COUNTRY_SETUP_PATH, STATES_PATH, STATE_BUILDINGS_PATH,
+        STATE_POPS_PATH,
+    },
+    province_types::{ProvinceTypes, ProvinceTypesError},
+    save_as_pdx_script::write_mod,
+    state_regions::{StateRegion, StateRegionEdits},
+    strategic_regions::StrategicRegion,
+    transfer_provinces::transfer_province_ownership,
+    transfer_state::transfer_state_ownership,
+};
+
+/// Why an edit was refused. The project is left unchanged when an edit fails.
+#[derive(Debug)]
+pub enum EditError {
+    UnknownCountry(String),
+    UnknownCountryDefinition(String),
+    CountryAlreadyExists(String),
+    UnknownState(String),
+    UnknownProvince(String),
+    StateNotOwned { state: String, country: String },
+    AmbiguousStateOwner { state: String, owners: Vec<String> },
+    SameCountry(String),
+    UnknownBuilding(String),
+    BuildingAlreadyExists { building: String, state: String },
+    NothingToTransfer,
+    NotOwnable(String),
+    UnknownStrategicRegion(String),
+    UnknownStateRegion(String),
+    StateRegionAlreadyExists(String),
+    NotInAnyStateRegion(String),
+    NotInStateRegion { province: String, state: String },
+    AlreadyInStateRegion { province: String, state: String },
+    SameStateRegion(String),
+    EmptyStateRegion(String),
+    ProvinceTypes(ProvinceTypesError),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::UnknownCountry(tag) => {
+                write!(f, "no country with tag {} owns any land", tag)
+            }
+            EditError::UnknownCountryDefinition(tag) => {
+                write!(f, "no country definition for tag {}", tag)
+            }
+            EditError::CountryAlreadyExists(tag) => write!(f, "country {} already exists", tag),
+            EditError::UnknownState(state) => write!(f, "no country owns state {}", state),
+            EditError::UnknownProvince(province) => {
+                write!(f, "no country owns province {}", province)
+            }
+            EditError::StateNotOwned { state, country } => {
+                write!(f, "{} does not own any of {}", country, state)
+            }
+            EditError::AmbiguousStateOwner { state, owners } => write!(
+                f,
+                "{} is split between {}, pick the country to transfer from",
+                state,
+                owners.join(", ")
+            ),
+            EditError::SameCountry(tag) => write!(f, "{} already owns it", tag),
+            EditError::UnknownBuilding(building) => write!(f, "no building named {}", building),
+            EditError::BuildingAlreadyExists { building, state } => {
+                write!(f, "{} already has a {}", state, building)
+            }
+            EditError::NothingToTransfer => write!(f, "no states or provinces given"),
+            EditError::NotOwnable(province) => {
+                write!(f, "{} is sea or a lake, which no country can own", province)
+            }
+            EditError::UnknownStrategicRegion(region) => {
+                write!(f, "no strategic region named {}", region)
+            }
+            EditError::UnknownStateRegion(state) => write!(f, "no state region named {}", state),
+            EditError::StateRegionAlreadyExists(state) => {
+                write!(f, "state region {} already exists", state)
+            }
+            EditError::NotInAnyStateRegion(province) => {
+                write!(f, "{} is not in any state region", province)
+            }
+            EditError::NotInStateRegion { province, state } => {
+                write!(f, "{} is not in {}", province, state)
+            }
+            EditError::AlreadyInStateRegion { province, state } => {
+                write!(f, "{} is already in {}", province, state)
+            }
+            EditError::SameStateRegion(state) => write!(f, "can't merge {} into itself", state),
+            EditError::EmptyStateRegion(state) => write!(
+                f,
+                "splitting off every province of {} would leave it empty, merge it instead",
+                state
+            ),
+            EditError::ProvinceTypes(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// The countries of a game folder and its mods, edited in memory and written out as a mod.
+///
+/// Most edits only change which country owns what, the rest move provinces between state
+/// regions. Geometry is derived from ownership when it is needed, see
+/// `province_map_to_geojson::country_coords`.
+///
+/// ```no_run
+/// use std::path::{Path, PathBuf};
+/// use v3_history_core::project::Project;
+///
+/// let mut project = Project::open(PathBuf::from("Victoria 3"), vec![]);
+/// project.transfer_state("s:STATE_SVEALAND", None, "NOR")?;
+/// project.save(Path::new("my_mod"));
+/// # Ok::<(), v3_history_core::project::EditError>(())
+/// ```
+pub struct Project {
+    pub mod_stack: ModStack,
+    pub countries: Vec<Country>,
+    country_definitions: HashMap<String, CountryDefinition>,
+    buildings: Option<Vec<Building>>,
+    province_types: Option<ProvinceTypes>,
+    strategic_regions: Option<Vec<StrategicRegion>>,
+    pub(crate) state_region_edits: Option<StateRegionEdits>,
+}
+
+impl Project {
+    /// Loads the game folder, the one containing `game`, with `mods` loaded on top of it in
+    /// order.
+    pub fn open(game_folder: PathBuf, mods: Vec<PathBuf>) -> Project {
+        Self::open_mod_stack(ModStack::new(game_folder, mods))
+    }
+
+    #[instrument(skip_all, fields(game_folder = ?mod_stack.game_folder, mods = ?mod_stack.mods))]
+    pub fn open_mod_stack(mod_stack: ModStack) -> Project {
+        let countries = get_countries(
+            get_states(mod_stack.files_in(STATES_PATH)),
+            get_state_populations(mod_stack.files_in(STATE_POPS_PATH)),
+            get_state_buildings(mod_stack.files_in(STATE_BUILDINGS_PATH)),
+            CountryDefinition::parse_map_from(mod_stack.files_in(COUNTRY_DEFINITIONS_PATH)),
+            CountrySetup::parse_map_from(mod_stack.files_in(COUNTRY_SETUP_PATH)),
+        );
+        Self::with_countries(mod_stack, countries)
+    }
+
+    /// Edits countries that were already loaded, such as the editor's unsaved ones, on top of
+    /// the mod stack they came from.
+    pub fn with_countries(mod_stack: ModStack, countries: Vec<Country>) -> Project {
+        let country_definitions =
+            CountryDefinition::parse_map_from(mod_stack.files_in(COUNTRY_DEFINITIONS_PATH));
+
+        Project {
+            mod_stack,
+            countries,
+            country_definitions,
+            buildings: None,
+            province_types: None,
+            strategic_regions: None,
+            state_region_edits: None,
+        }
+    }
+
+    /// Keeps editing state regions that were edited before, such as the editor's unsaved ones.
+    pub fn with_state_region_edits(self, edits: StateRegionEdits) -> Project {
+        Project {
+            state_region_edits: Some(edits),
+            ..self
+        }
+    }
+
+    /// The edits to the state regions, if there were any.
+    pub fn state_region_edits(&self) -> Option<&StateRegionEdits> {
+        self.state_region_edits
+            .as_ref()
+            .filter(|edits| edits.is_edited())
+    }
+
+    /// The country with `tag`, if it owns any land.
+    pub fn country(&self, tag: &str) -> Option<&Country> {
+        self.countries.iter().find(|country| country.name == tag)
+    }
+
+    /// Tags of the countries owning part of `state`.
+    pub fn state_owners(&self, state: &str) -> Vec<String> {
+        self.countries
+            .iter()
+            .filter(|country| country.states.iter().any(|s| s.name == state))
+            .map(|country| country.name.clone())
+            .collect()
+    }
+
+    /// The tag of the country owning `province`, and the state it is in.
+    pub fn province_owner(&self, province: &str) -> Option<(String, String)> {
+        self.countries.iter().find_map(|country| {
+            country
+                .states
+                .iter()
+                .find(|state| state.provinces.iter().any(|p| p == province))
+                .map(|state| (country.name.clone(), state.name.clone()))
+        })
+    }
+
+    /// Transfers a country's part of `state` to `to`, creating `to` from its country
+    /// definition if it owns no land yet. `from` can be left out when the state has a single
+    /// owner.
+    pub fn transfer_state(
+        &mut self,
+        state: &str,
+        from: Option<&str>,
+        to: &str,
+    ) -> Result<(), EditError> {
+        let from = self.resolve_state_owner(state, from)?;
+        let to_country = self.take_or_create_country(to, false)?;
+        self.transfer_state_to(state, &from, to_country)
+    }
+
+    /// Transfers `province` from whichever country owns it to `to`, moving the state's pops and
+    /// buildings along if it was the last province the owner had in the state.
+    pub fn transfer_province(&mut self, province: &str, to: &str) -> Result<(), EditError> {
+        let to_country = self.take_or_create_country(to, false)?;
+        self.transfer_province_to(province, to_country)
+    }
+
+    /// Creates the country `tag` out of a country's part of `state`.
+    pub fn create_country(
+        &mut self,
+        tag: &str,
+        state: &str,
+        from: Option<&str>,
+    ) -> Result<(), EditError> {
+        let from = self.resolve_state_owner(state, from)?;
+        let to_country = self.take_or_create_country(tag, true)?;
+        self.transfer_state_to(state, &from, to_country)
+    }
+
+    /// Creates the country `tag` out of a single province.
+    pub fn create_country_from_province(
+        &mut self,
+        tag: &str,
+        province: &str,
+    ) -> Result<(), EditError> {
+        let to_country = self.take_or_create_country(tag, true)?;
+        self.transfer_province_to(province, to_country)
+    }
+
+    /// The strategic regions of the mod stack, read the first time they are needed, with the
+    /// states they gained or lost to state region edits.
+    pub fn strategic_regions(&mut self) -> Vec<StrategicRegion> {
+        let mut regions = self
+            .strategic_regions
+            .get_or_insert_with(|| StrategicRegion::parse_from(&self.mod_stack))
+            .clone();
+        let edited = self
+            .state_region_edits
+            .iter()
+            .flat_map(|edits| edits.strategic_regions.iter());
+        for edited in edited {
+            if let Some(region) = regions.iter_mut().find(|region| region.name == edited.name) {
+                *region = edited.clone();
+            }
+        }
+        regions
+    }
+
+    /// Transfers every part of every state in the strategic region that `to` doesn't own yet,
+    /// returning the countries it was taken from.
+    pub fn transfer_strategic_region(
+        &mut self,
+        region: &str,
+        to: &str,
+    ) -> Result<Vec<String>, EditError> {
+        let region_key = match region.starts_with("sr:") {
+            true => region.to_string(),
+            false => format!("sr:{}", region),
+        };
+        let states = self
+            .strategic_regions()
+            .iter()
+            .find(|candidate| candidate.name == region_key)
+            .ok_or_else(|| EditError::UnknownStrategicRegion(region.to_string()))?
+            .states
+            .clone();
+
+        let countries = self.countries.clone();
+        let mut from_countries = vec![];
+        for state in states.iter() {
+            for owner in self.state_owners(state) {
+                if owner == to {
+                    continue;
+                }
+                if let Err(error) = self.transfer_state(state, Some(&owner), to) {
+                    self.countries = countries;
+                    return Err(error);
+                }
+                from_countries.push(owner);
+            }
+        }
+        if from_countries.is_empty() {
+            return Err(EditError::SameCountry(to.to_string()));
+        }
+        from_countries.sort();
+        from_countries.dedup();
+        Ok(from_countries)
+    }
+
+    /// Replaces the pops a country has in `state`.
+    pub fn set_pops(&mut self, tag: &str, state: &str, pops: Vec<Pop>) -> Result<(), EditError> {
+        let country = self.country_mut(tag)?;
+        let country_state = country
+            .states
+            .iter_mut()
+            .find(|country_state| country_state.name == state)
+            .ok_or_else(|| EditError::StateNotOwned {
+                state: state.to_string(),
+                country: tag.to_string(),
+            })?;
+        country_state.pops = pops;
+        Ok(())
+    }
+
+    /// Replaces a country's researched technologies, and its starting tech tier if one is
+    /// given. Without one the country keeps the tier it has.
+    pub fn set_technologies(
+        &mut self,
+        tag: &str,
+        base_tech: Option<String>,
+        technologies_researched: Vec<String>,
+    ) -> Result<(), EditError> {
+        let country = self.country_mut(tag)?;
+        country.setup = CountrySetup {
+            base_tech: base_tech.or(country.setup.base_tech.take()),
+            technologies_researched,
+        };
+        Ok(())
+    }
+
+    /// Sets a country's starting tech tier, keeping the technologies researched on top of it.
+    pub fn set_base_tech(&mut self, tag: &str, base_tech: &str) -> Result<(), EditError> {
+        let country = self.country_mut(tag)?;
+        country.setup.base_tech = Some(base_tech.to_string());
+        Ok(())
+    }
+
+    /// Adds a building to a country's part of `state`, with the first production method of
+    /// each group active, the same as adding it on the map.
+    pub fn add_building(
+        &mut self,
+        tag: &str,
+        state: &str,
+        building: &str,
+        level: i64,
+    ) -> Result<(), EditError> {
+        let buildings = self
+            .buildings
+            .get_or_insert_with(|| Building::parse_from(&self.mod_stack));
+        let activate_production_methods = buildings
+            .iter()
+            .find(|candidate| candidate.name == building)
+            .ok_or_else(|| EditError::UnknownBuilding(building.to_string()))?
+            .production_method_groups
+            .iter()
+            .filter_map(|group| group.production_methods.first())
+            .map(|production_method| production_method.name.clone())
+            .collect();
+
+        let country = self.country_mut(tag)?;
+        let country_state = country
+            .states
+            .iter_mut()
+            .find(|country_state| country_state.name == state)
+            .ok_or_else(|| EditError::StateNotOwned {
+                state: state.to_string(),
+                country: tag.to_string(),
+            })?;
+        if country_state
+            .state_buildings
+            .iter()
+            .any(|state_building| state_building.name == building)
+        {
+            return Err(EditError::BuildingAlreadyExists {
+                building: building.to_string(),
+                state: state.to_string(),
+            });
+        }
+        country_state.state_buildings.push(StateBuilding {
+            name: building.to_string(),
+            level: Some(level),
+            reserves: Some(1),
+            activate_production_methods: Some(activate_production_methods),
+            condition: None,
+            ownership: None,
+        });
+        Ok(())
+    }
+
+    /// Writes the project as a mod into `working_dir`.
+    ///
+    /// The working directory may be loaded as the top mod to keep editing it, but it can't be
+    /// the base it is written on top of, so it is left out of the mod stack here.
+    pub fn save(&self, working_dir: &Path) {
+        let mut mod_stack = self.mod_stack.clone();
+        mod_stack.mods.retain(|mod_path| mod_path != working_dir);
+        write_mod(
+            &mod_stack,
+            working_dir,
+            self.countries.clone(),
+            self.state_region_edits(),
+        );
+    }
+
+    pub(crate) fn state_region_edits_mut(&mut self) -> &mut StateRegionEdits {
+        self.state_region_edits
+            .get_or_insert_with(|| StateRegionEdits::new(StateRegion::parse_from(&self.mod_stack)))
+    }
+
+    fn country_mut(&mut self, tag: &str) -> Result<&mut Country, EditError> {
+        self.countries
+            .iter_mut()
+            .find(|country| country.name == tag)
+            .ok_or_else(|| EditError::UnknownCountry(tag.to_string()))
+    }
+
+    fn resolve_state_owner(&self, state: &str, from: Option<&str>) -> Result<String, EditError> {
+        let owners = self.state_owners(state);
+        match from {
+            Some(from) if owners.iter().any(|owner| owner == from) => Ok(from.to_string()),
+            Some(from) => Err(EditError::StateNotOwned {
+                state: state.to_string(),
+                country: from.to_string(),
+            }),
+            None => match owners.as_slice() {
+                [] => Err(EditError::UnknownState(state.to_string())),
+                [owner] => Ok(owner.clone()),
+                _ => Err(EditError::AmbiguousStateOwner {
+                    state: state.to_string(),
+                    owners,
+                }),
+            },
+        }
+    }
+
+    // Takes the country out of the project so it can be handed to the transfer functions by
+    // value. `put_country` puts it back.
+    fn take_or_create_country(&mut self, tag: &str, create: bool) -> Result<Country, EditError> {
+        let existing_index = self.countries.iter().position(|c| c.name == tag);
+        match (existing_index, create) {
+            (Some(_), true) => Err(EditError::CountryAlreadyExists(tag.to_string())),
+            (Some(index), false) => Ok(self.countries.remove(index)),
+            (None, _) => self
+                .country_definitions
+                .get(tag)
+                .map(|definition| Country::new(definition.clone()))
+                .ok_or_else(|| EditError::UnknownCountryDefinition(tag.to_string())),
+        }
+    }
+
+    fn put_country(&mut self, country: Country) {
+        if !country.states.is_empty() {
+            self.countries.push(country);
+        }
+    }
+
+    fn transfer_state_to(
+        &mut self,
+        state: &str,
+        from: &str,
+        to_country: Country,
+    ) -> Result<(), EditError> {
+        if from == to_country.name {
+            let to = to_country.name.clone();
+            self.put_country(to_country);
+            return Err(EditError::SameCountry(to));
+        }
+        let from_index = self.countries.iter().position(|c| c.name == from).unwrap();
+        let from_country = self.countries.remove(from_index);
+        let to = to_country.name.clone();
+
+        let (from_country, to_country) = transfer_state_ownership(state, from_country, to_country);
+        self.put_country(from_country);
+        self.put_country(to_country);
+        self.transfer_building_ownership(state, from, &to);
+
+        Ok(())
+    }
+
+    fn transfer_province_to(
+        &mut self,
+        province: &str,
+        to_country: Country,
+    ) -> Result<(), EditError> {
+        if self.province_types.is_none() {
+            match ProvinceTypes::parse_from(&self.mod_stack) {
+                Ok(province_types) => self.province_types = Some(province_types),
+                Err(e) => {
+                    self.put_country(to_country);
+                    return Err(EditError::ProvinceTypes(e));
+                }
+            }
+        }
+        let province_types = self.province_types.as_ref().unwrap();
+        if !province_types.is_ownable(province) {
+            self.put_country(to_country);
+            return Err(EditError::NotOwnable(province.to_string()));
+        }
+        let Some((from, state)) = self.province_owner(province) else {
+            self.put_country(to_country);
+            return Err(EditError::UnknownProvince(province.to_string()));
+        };
+        if from == to_country.name {
+            self.put_country(to_country);
+            return Err(EditError::SameCountry(from));
+        }
+        let from_index = self.countries.iter().position(|c| c.name == from).unwrap();
+        let from_country = self.countries.remove(from_index);
+        let to = to_country.name.clone();
+
+        let (from_country, to_country) =
+            transfer_province_ownership(&state, province, from_country, to_country);
+        let from_kept_state = from_country
+            .states
+            .iter()
+            .any(|from_state| from_state.name == state);
+        self.put_country(from_country);
+        self.put_country(to_country);
+        if !from_kept_state {
+            self.transfer_building_ownership(&state, &from, &to);
+        }
+
+        Ok(())
+    }
+
+    // Mirrors what the map does once a country loses the last of a state: ownership of
+    // buildings in that state moves with it.
+    fn transfer_building_ownership(&mut self, state: &str, from: &str, to: &str) {
+        let from_reference = format!("c:{}", from);
+        let to_reference = format!("c:{}", to);
+
+        for country in self.countries.iter_mut() {
+            for country_state in country.states.iter_mut() {
+                let is_transferred_state = country_state.name == state;
+                for building in country_state.state_buildings.iter_mut() {
+                    let Some(ownership) = building.ownership.as_mut() else {
+                        continue;
+                    };
+                    for owner in ownership.buildings.iter_mut() {
+                        if owner.country == from_reference && format!("s:{}", owner.region) == state
+                        {
+                            owner.country = to_reference.clone();
+                        }
+                    }
+                    if is_transferred_state {
+                        for owner in ownership.countries.iter_mut() {
+                            owner.country = to_reference.clone();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// States are keyed as they are in the history files, `s:STATE_NAME`.
+pub fn state_key(state: &str) -> String {
+    match state.starts_with("s:") {
+        true => state.to_string(),
+        false => format!("s:{}", state),
+    }
+}
+
+/// Provinces are keyed by their colour in provinces.png, `xRRGGBB` in upper case. Colours
+/// written as `0xRRGGBB` are keyed the same.
+pub fn province_key(province: &str) -> String {
+    let hex = province
+        .strip_prefix("0x")
+        .or_else(|| province.strip_prefix("0X"))
+        .unwrap_or(province)
+        .trim_start_matches(['x', 'X']);
+    format!("x{}", hex.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn province_keys_are_upper_case_with_a_lower_case_x() {
+        for province in ["x1a2b3c", "X1A2B3C", "0x1a2b3c", "0X1a2B3c", "1a2b3c"] {
+            assert_eq!(province_key(province), "x1A2B3C", "{}", province);
+        }
+    }
+
+    #[test]
+    fn colours_starting_with_zero_keep_it() {
+        assert_eq!(province_key("x00FF00"), "x00FF00");
+        assert_eq!(province_key("0x00ff00"), "x00FF00");
+    }
+
+    #[test]
+    fn state_keys_have_one_prefix() {
+        assert_eq!(state_key("STATE_SVEALAND"), "s:STATE_SVEALAND");
+        assert_eq!(state_key("s:STATE_SVEALAND"), "s:STATE_SVEALAND");
+    }
+}
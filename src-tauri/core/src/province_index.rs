@@ -0,0 +1,165 @@
+use image::ImageReader;
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+};
+use tracing::instrument;
+
+use crate::country::Country;
+
+/// The province map decoded once, for finding provinces by position instead of by their
+/// traced outlines. Positions are in map coordinates, pixel corners with y running up, the same
+/// as the topology.
+pub struct ProvinceIndex {
+    width: u32,
+    height: u32,
+    // Index into `provinces` of each pixel, row by row from the top of the image.
+    pixels: Vec<u32>,
+    provinces: Vec<String>,
+}
+
+/// Where a province is, as far as the history files go. Provinces in no state, like sea ones,
+/// have neither.
+#[derive(Serialize, Debug, Clone)]
+pub struct ProvinceLocation {
+    pub province: String,
+    pub state: Option<String>,
+    pub owner: Option<String>,
+}
+
+impl ProvinceIndex {
+    #[instrument(skip_all, fields(map = ?province_map))]
+    pub fn from_province_map(province_map: PathBuf) -> ProvinceIndex {
+        let image = ImageReader::open(province_map)
+            .unwrap()
+            .decode()
+            .unwrap()
+            .into_rgb8();
+        let mut province_indices: HashMap<[u8; 3], u32> = HashMap::new();
+        let mut provinces = vec![];
+        let pixels = image
+            .pixels()
+            .map(|pixel| {
+                *province_indices.entry(pixel.0).or_insert_with(|| {
+                    provinces.push(format!("x{:02X}{:02X}{:02X}", pixel[0], pixel[1], pixel[2]));
+                    (provinces.len() - 1) as u32
+                })
+            })
+            .collect();
+        ProvinceIndex {
+            width: image.width(),
+            height: image.height(),
+            pixels,
+            provinces,
+        }
+    }
+
+    pub fn province_at(&self, x: f32, y: f32) -> Option<&str> {
+        let (column, row) = (x.floor(), self.height as f32 - y.floor() - 1.0);
+        if column < 0.0 || row < 0.0 || column >= self.width as f32 || row >= self.height as f32 {
+            return None;
+        }
+        Some(self.province_of(column as u32, row as u32))
+    }
+
+    /// Provinces with a pixel centre inside the rectangle between two corners.
+    pub fn provinces_in_rect(&self, from: (f32, f32), to: (f32, f32)) -> Vec<String> {
+        let (left, right) = (from.0.min(to.0), from.0.max(to.0));
+        let (bottom, top) = (from.1.min(to.1), from.1.max(to.1));
+        let mut provinces = BTreeSet::new();
+        for y in self.centre_rows(bottom, top) {
+            for x in self.centre_columns(left, right) {
+                provinces.insert(self.province_of(x, self.height - y - 1));
+            }
+        }
+        provinces.into_iter().map(str::to_string).collect()
+    }
+
+    /// Provinces with a pixel centre inside the polygon, such as a lasso drawn on the map.
+    pub fn provinces_in_polygon(&self, polygon: &[(f32, f32)]) -> Vec<String> {
+        if polygon.len() < 3 {
+            return vec![];
+        }
+        let bottom = polygon.iter().map(|point| point.1).fold(f32::MAX, f32::min);
+        let top = polygon.iter().map(|point| point.1).fold(f32::MIN, f32::max);
+        let mut provinces = BTreeSet::new();
+
+        for y in self.centre_rows(bottom, top) {
+            let centre_y = y as f32 + 0.5;
+            // Where the row of centres crosses the outline, paired up into spans inside it.
+            let mut crossings: Vec<f32> = (0..polygon.len())
+                .filter_map(|index| {
+                    let (from, to) = (polygon[index], polygon[(index + 1) % polygon.len()]);
+                    match (from.1 > centre_y) != (to.1 > centre_y) {
+                        true => {
+                            Some(from.0 + (centre_y - from.1) / (to.1 - from.1) * (to.0 - from.0))
+                        }
+                        false => None,
+                    }
+                })
+                .collect();
+            crossings.sort_by(f32::total_cmp);
+            for span in crossings.chunks(2) {
+                if let [left, right] = span {
+                    for x in self.centre_columns(*left, *right) {
+                        provinces.insert(self.province_of(x, self.height - y - 1));
+                    }
+                }
+            }
+        }
+        provinces.into_iter().map(str::to_string).collect()
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The province of a pixel of the province map, in rows from the top of the image.
+    pub fn province_of(&self, column: u32, row: u32) -> &str {
+        let index = self.pixels[(row * self.width + column) as usize];
+        &self.provinces[index as usize]
+    }
+
+    // Rows counted from the bottom, and columns, whose pixel centres are between two positions.
+    fn centre_rows(&self, from: f32, to: f32) -> std::ops::Range<u32> {
+        centres_between(from, to, self.height)
+    }
+
+    fn centre_columns(&self, from: f32, to: f32) -> std::ops::Range<u32> {
+        centres_between(from, to, self.width)
+    }
+}
+
+fn centres_between(from: f32, to: f32, size: u32) -> std::ops::Range<u32> {
+    let first = (from - 0.5).ceil().clamp(0.0, size as f32) as u32;
+    let last = ((to - 0.5).floor() + 1.0).clamp(0.0, size as f32) as u32;
+    first..last.max(first)
+}
+
+/// The state and owner of each province, from the countries' current ownership.
+pub fn locate<'a>(
+    provinces: impl IntoIterator<Item = &'a str>,
+    countries: &[Country],
+) -> Vec<ProvinceLocation> {
+    let mut owners: HashMap<&str, (&str, &str)> = HashMap::new();
+    for country in countries {
+        for state in country.states.iter() {
+            for province in state.provinces.iter() {
+                owners.insert(province, (&state.name, &country.name));
+            }
+        }
+    }
+
+    provinces
+        .into_iter()
+        .map(|province| {
+            let owner = owners.get(province);
+            ProvinceLocation {
+                province: province.to_string(),
+                state: owner.map(|(state, _)| state.to_string()),
+                owner: owner.map(|(_, tag)| tag.to_string()),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,137 @@
+use crate::{
+    country::Country,
+    get_states::State,
+    labels::{place_label, Label},
+    topology::Topology,
+};
+use std::collections::HashMap;
+use tracing::{instrument, warn};
+
+pub type Coords = Vec<Vec<(f32, f32)>>;
+
+// Keyed by owner and state, `TAG:s:STATE`, since a state can be split between countries.
+#[instrument(skip_all)]
+pub fn state_map_to_geojson(topology: &Topology, states: Vec<State>) -> HashMap<String, Coords> {
+    let sub_states = states.iter().flat_map(|state| {
+        state.sub_states.iter().map(|sub_state| {
+            (
+                format!("{}:{}", sub_state.owner, state.name),
+                sub_state.provinces.as_slice(),
+            )
+        })
+    });
+
+    topology
+        .dissolve(sub_states)
+        .into_iter()
+        .map(|(key, polygons)| {
+            if polygons.is_empty() {
+                warn!(state = %key, "No valid provinces for state");
+            }
+            (key, topology.polygons_to_coords(&polygons))
+        })
+        .collect()
+}
+
+#[instrument(skip_all)]
+pub fn country_map_to_geojson(topology: &Topology, countries: Vec<Country>) -> Vec<Country> {
+    let country_provinces: Vec<(String, Vec<String>)> = countries
+        .iter()
+        .map(|country| {
+            let provinces = country
+                .states
+                .iter()
+                .flat_map(|state| state.provinces.iter().cloned())
+                .collect();
+            (country.name.clone(), provinces)
+        })
+        .collect();
+    let mut country_polygons = topology.dissolve(
+        country_provinces
+            .iter()
+            .map(|(tag, provinces)| (tag.clone(), provinces.as_slice())),
+    );
+
+    countries
+        .into_iter()
+        .map(|country| {
+            let polygons = country_polygons.remove(&country.name).unwrap_or_default();
+            if polygons.is_empty() {
+                warn!(country = %country.name, "No geometries for country");
+            }
+            let coordinates = topology.polygons_to_coords(&polygons);
+            Country {
+                label: place_label(&coordinates, &country.name),
+                coordinates,
+                ..country
+            }
+        })
+        .collect()
+}
+
+// Redraws a single country from the provinces it owns, for edits that only touch a few.
+pub fn country_coords(topology: &Topology, country: &Country) -> Coords {
+    let provinces: Vec<String> = country
+        .states
+        .iter()
+        .flat_map(|state| state.provinces.iter().cloned())
+        .collect();
+    dissolve_provinces(topology, &country.name, &provinces)
+}
+
+/// Redraws a country's outline and moves its label to match, after its provinces have changed.
+pub fn redraw_country(topology: &Topology, mut country: Country) -> Country {
+    country.coordinates = country_coords(topology, &country);
+    country.label = place_label(&country.coordinates, &country.name);
+    country
+}
+
+/// A label for each shape, such as states or provinces drawn on the map, keyed like the shapes.
+pub fn shape_labels(shapes: HashMap<String, (String, Coords)>) -> HashMap<String, Label> {
+    shapes
+        .into_iter()
+        .filter_map(|(key, (text, coords))| Some((key, place_label(&coords, &text)?)))
+        .collect()
+}
+
+// Empty when the country owns none of the state.
+pub fn sub_state_coords(topology: &Topology, country: &Country, state: &str) -> Coords {
+    match country
+        .states
+        .iter()
+        .find(|country_state| country_state.name == state)
+    {
+        Some(country_state) => dissolve_provinces(
+            topology,
+            &format!("{}:{}", country.name, state),
+            &country_state.provinces,
+        ),
+        None => vec![],
+    }
+}
+
+fn dissolve_provinces(topology: &Topology, key: &str, provinces: &[String]) -> Coords {
+    let polygons = topology
+        .dissolve([(key.to_string(), provinces)])
+        .remove(key)
+        .unwrap_or_default();
+    topology.polygons_to_coords(&polygons)
+}
+
+// Dissolves on the full topology, which stitching needs, and draws the outlines from the same
+// arcs of a simplified one.
+pub fn simplified_outlines(
+    topology: &Topology,
+    simplified: &Topology,
+    groups: &HashMap<String, Vec<String>>,
+) -> HashMap<String, Coords> {
+    topology
+        .dissolve(
+            groups
+                .iter()
+                .map(|(key, provinces)| (key.clone(), provinces.as_slice())),
+        )
+        .into_iter()
+        .map(|(key, polygons)| (key, simplified.polygons_to_coords(&polygons)))
+        .collect()
+}
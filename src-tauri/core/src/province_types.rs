@@ -0,0 +1,160 @@
+use jomini::TextTape;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    mod_stack::{ModStack, DEFAULT_MAP_PATH, PROVINCE_TERRAINS_PATH},
+    topology::GeometryCollection,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvinceKind {
+    Land,
+    Sea,
+    Lake,
+    Impassable,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProvinceInfo {
+    pub kind: ProvinceKind,
+    pub terrain: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ProvinceTypesError {
+    Parse(PathBuf, String),
+    InvalidProvince(PathBuf, String),
+}
+
+impl fmt::Display for ProvinceTypesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProvinceTypesError::Parse(path, e) => write!(f, "could not parse {:?}: {}", path, e),
+            ProvinceTypesError::InvalidProvince(path, province) => {
+                write!(
+                    f,
+                    "{:?} lists {:?}, which is not a province",
+                    path, province
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProvinceTypesError {}
+
+/// What each province is, from the lists in `default.map` and the terrain each province is
+/// given. Provinces in none of the lists are land, and a missing file lists none.
+#[derive(Default, Debug, Clone)]
+pub struct ProvinceTypes {
+    kinds: HashMap<String, ProvinceKind>,
+    terrains: HashMap<String, String>,
+}
+
+impl ProvinceTypes {
+    pub fn parse_from(mod_stack: &ModStack) -> Result<ProvinceTypes, ProvinceTypesError> {
+        Ok(ProvinceTypes {
+            kinds: parse_default_map(mod_stack.file(DEFAULT_MAP_PATH))?,
+            terrains: parse_province_terrains(mod_stack.file(PROVINCE_TERRAINS_PATH))?,
+        })
+    }
+
+    pub fn kind(&self, province: &str) -> ProvinceKind {
+        self.kinds
+            .get(province)
+            .copied()
+            .unwrap_or(ProvinceKind::Land)
+    }
+
+    pub fn terrain(&self, province: &str) -> Option<&str> {
+        self.terrains.get(province).map(String::as_str)
+    }
+
+    pub fn info(&self, province: &str) -> ProvinceInfo {
+        ProvinceInfo {
+            kind: self.kind(province),
+            terrain: self.terrain(province).map(str::to_string),
+        }
+    }
+
+    /// Sets each geometry's TopoJSON properties to its province's kind and terrain, so the map
+    /// can tell sea and lakes from land.
+    pub fn tag(&self, collection: &mut GeometryCollection) {
+        for geometry in collection.geometries.iter_mut() {
+            geometry.properties = Some(self.info(&geometry.id));
+        }
+    }
+
+    // Impassable provinces are still part of states, only sea and lakes have no owner.
+    pub fn is_ownable(&self, province: &str) -> bool {
+        matches!(
+            self.kind(province),
+            ProvinceKind::Land | ProvinceKind::Impassable
+        )
+    }
+}
+
+// Province ids are written as `x` and hex, in either case.
+fn province_id(path: &Path, raw: &str) -> Result<String, ProvinceTypesError> {
+    let raw = raw.trim_matches('"');
+    match raw.strip_prefix(['x', 'X']) {
+        Some(hex) if !hex.is_empty() => Ok(format!("x{}", hex.to_uppercase())),
+        _ => Err(ProvinceTypesError::InvalidProvince(
+            path.to_path_buf(),
+            raw.to_string(),
+        )),
+    }
+}
+
+fn parse_default_map(path: PathBuf) -> Result<HashMap<String, ProvinceKind>, ProvinceTypesError> {
+    let mut kinds = HashMap::new();
+    let Ok(data) = std::fs::read(&path) else {
+        return Ok(kinds);
+    };
+    let tape = TextTape::from_slice(&data)
+        .map_err(|e| ProvinceTypesError::Parse(path.clone(), e.to_string()))?;
+    let reader = tape.utf8_reader();
+
+    for (key, _op, value) in reader.fields() {
+        let kind = match key.read_str().as_ref() {
+            "sea_starts" => ProvinceKind::Sea,
+            "lakes" => ProvinceKind::Lake,
+            key if key.starts_with("impassable") => ProvinceKind::Impassable,
+            _ => continue,
+        };
+        let Ok(provinces) = value.read_array() else {
+            continue;
+        };
+        for province in provinces.values() {
+            if let Ok(province) = province.read_string() {
+                kinds.insert(province_id(&path, &province)?, kind);
+            }
+        }
+    }
+
+    Ok(kinds)
+}
+
+fn parse_province_terrains(path: PathBuf) -> Result<HashMap<String, String>, ProvinceTypesError> {
+    let mut terrains = HashMap::new();
+    let Ok(data) = std::fs::read(&path) else {
+        return Ok(terrains);
+    };
+    let tape = TextTape::from_slice(&data)
+        .map_err(|e| ProvinceTypesError::Parse(path.clone(), e.to_string()))?;
+    let reader = tape.utf8_reader();
+
+    for (key, _op, value) in reader.fields() {
+        if let Ok(terrain) = value.read_string() {
+            terrains.insert(province_id(&path, &key.read_str())?, terrain);
+        }
+    }
+
+    Ok(terrains)
+}
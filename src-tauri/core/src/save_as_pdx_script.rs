@@ -0,0 +1,507 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tracing::{info, info_span, instrument};
+
+use crate::{
+    country::Country,
+    country_setup::CountrySetup,
+    get_state_buildings::StateBuilding,
+    get_state_populations::Pop,
+    get_states::{get_states, State},
+    mod_stack::{
+        files_in_dir, ModStack, COUNTRY_SETUP_PATH, STATES_PATH, STATE_BUILDINGS_PATH,
+        STATE_POPS_PATH,
+    },
+    state_regions::{write_state_regions, StateRegionEdits},
+    strategic_regions::write_strategic_regions,
+};
+
+const STATES_FILE_NAME: &str = "00_states.txt";
+const POPS_FILE_NAME: &str = "00_pops.txt";
+const BUILDINGS_FILE_NAME: &str = "00_buildings.txt";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct SubState {
+    pub provinces: Vec<String>,
+    pub owner: String,
+    pub pops: Vec<Pop>,
+    pub state_buildings: Vec<StateBuilding>,
+}
+
+/// Writes the countries as a mod into the working directory, along with the state regions if
+/// they were edited. The mod stack is what the mod is loaded on top of, and must not include
+/// the working directory itself.
+#[instrument(skip_all, fields(working_dir = ?working_dir))]
+pub fn write_mod(
+    mod_stack: &ModStack,
+    working_dir: &Path,
+    current_countries: Vec<Country>,
+    state_regions: Option<&StateRegionEdits>,
+) {
+    let states = get_states(mod_stack.files_in(STATES_PATH));
+
+    let mut current_state_map: HashMap<String, Vec<SubState>> = HashMap::new();
+    current_countries.iter().for_each(|country| {
+        country
+            .states
+            .iter()
+            .for_each(|state| match current_state_map.get(&state.name) {
+                Some(sub_states) => {
+                    let mut new_sub_states = sub_states.clone();
+                    new_sub_states.push(SubState {
+                        provinces: state.provinces.clone(),
+                        owner: country.name.clone(),
+                        pops: state.pops.clone(),
+                        state_buildings: state.state_buildings.clone(),
+                    });
+                    current_state_map.insert(state.name.clone(), new_sub_states);
+                }
+                None => {
+                    current_state_map.insert(
+                        state.name.clone(),
+                        vec![SubState {
+                            provinces: state.provinces.clone(),
+                            owner: country.name.clone(),
+                            pops: state.pops.clone(),
+                            state_buildings: state.state_buildings.clone(),
+                        }],
+                    );
+                }
+            });
+    });
+
+    let country_setup_span = info_span!("save_country_setups").entered();
+    let working_dir_country_setup_path = working_dir.join(COUNTRY_SETUP_PATH);
+    std::fs::create_dir_all(&working_dir_country_setup_path).unwrap();
+    write_country_setup_to_pdx_script(
+        current_countries,
+        working_dir_country_setup_path,
+        mod_stack.files_in(COUNTRY_SETUP_PATH),
+    );
+
+    country_setup_span.exit();
+
+    let pops_span = info_span!("save_pops").entered();
+    let state_pop_path = working_dir.join(STATE_POPS_PATH);
+    std::fs::create_dir_all(&state_pop_path).unwrap();
+    write_state_pops_to_pdx_script(&current_state_map, &state_pop_path);
+    overwrite_existing_pops(&state_pop_path, &mod_stack.files_in(STATE_POPS_PATH));
+
+    pops_span.exit();
+
+    let buildings_span = info_span!("save_buildings").entered();
+    let state_buildings_path = working_dir.join(STATE_BUILDINGS_PATH);
+    std::fs::create_dir_all(&state_buildings_path).unwrap();
+    write_state_buildings_to_pdx_script(&current_state_map, &state_buildings_path);
+    overwrite_existing_buildings(
+        &state_buildings_path,
+        &mod_stack.files_in(STATE_BUILDINGS_PATH),
+    );
+
+    buildings_span.exit();
+
+    let states_span = info_span!("save_states").entered();
+    let states_dir = working_dir.join(STATES_PATH);
+    std::fs::create_dir_all(&states_dir).unwrap();
+    write_states_to_pdx_script(states, current_state_map, states_dir, state_regions);
+
+    states_span.exit();
+
+    if let Some(state_regions) = state_regions {
+        write_state_regions(state_regions, mod_stack, working_dir);
+        write_strategic_regions(&state_regions.strategic_regions, mod_stack, working_dir);
+    }
+    info!(working_dir = ?working_dir, "Saved as mod");
+}
+
+// This might be useful someday :')
+// fn compare_sub_states(state1: &[SubStateProvince], state2: &[SubState]) -> bool {
+//   let state1_map: HashMap<String, Vec<String>> = state1.iter().map(|sub_state| {
+//     let mut sub_state_provinces = sub_state.provinces.clone();
+//     sub_state_provinces.sort();
+//     (sub_state.owner.clone(), sub_state_provinces)
+//   }).collect();
+//   let state2_map: HashMap<String, Vec<String>> = state2.iter().map(|sub_state| {
+//     let mut sub_state_provinces = sub_state.provinces.clone();
+//     sub_state_provinces.sort();
+//     (sub_state.owner.clone(), sub_state_provinces)
+//   }).collect();
+
+//   state1_map == state2_map
+// }
+
+fn write_country_setup_to_pdx_script(
+    current_countries: Vec<Country>,
+    working_dir_country_setup_path: PathBuf,
+    game_country_setup_files: Vec<PathBuf>,
+) {
+    let country_setup_map = CountrySetup::parse_map_from(game_country_setup_files.clone());
+    let unprocessed_setup_map =
+        CountrySetup::parse_map_unprocessed_values(game_country_setup_files);
+    let unprocessed_working_dir_setup_map =
+        CountrySetup::parse_map_unprocessed_values(files_in_dir(&working_dir_country_setup_path));
+
+    current_countries.iter().for_each(|country| {
+        let save_path =
+            working_dir_country_setup_path.join(format!("{}.txt", country.name.to_lowercase()));
+        if save_path.exists() {
+            std::fs::remove_file(&save_path).unwrap();
+        }
+
+        let is_country_setup_changed = match country_setup_map.get(&country.name) {
+            Some(game_country_setup) => {
+                country.setup != *game_country_setup
+                    || match unprocessed_working_dir_setup_map.get(&country.name) {
+                        Some(unprocessed_working_dir_setup) => {
+                            unprocessed_working_dir_setup.trim_start()
+                                != unprocessed_setup_map
+                                    .get(&country.name)
+                                    .unwrap()
+                                    .trim_start()
+                        }
+                        None => false,
+                    }
+            }
+            None => true,
+        };
+
+        if is_country_setup_changed {
+            let unparsed_script = match unprocessed_working_dir_setup_map.get(&country.name) {
+                Some(script) => script,
+                None => match unprocessed_setup_map.get(&country.name) {
+                    Some(script) => script,
+                    None => "  ",
+                },
+            };
+            let mut country_setup_script = String::new();
+            country_setup_script.push_str("COUNTRIES = {\n");
+            country_setup_script.push_str(&format!("  c:{} = ", country.name));
+            country_setup_script.push_str("{\n");
+            if country.setup.base_tech.is_some() {
+                country_setup_script.push_str(&format!(
+                    "    effect_starting_technology_{}_tech = yes\n",
+                    country.setup.base_tech.clone().unwrap()
+                ));
+            }
+            country
+                .setup
+                .technologies_researched
+                .iter()
+                .for_each(|tech| {
+                    country_setup_script
+                        .push_str(&format!("    add_technology_researched = {}\n", tech));
+                });
+            country_setup_script.push_str(unparsed_script);
+            country_setup_script.push_str("}\n");
+            country_setup_script.push_str("}\n");
+
+            std::fs::write(save_path, country_setup_script).unwrap();
+        }
+    })
+}
+
+// States split off or merged into others by state region edits take on the homelands and
+// claims of the states they came from. States merged away are left out.
+fn write_states_to_pdx_script(
+    game_states: Vec<State>,
+    current_state_map: HashMap<String, Vec<SubState>>,
+    path: PathBuf,
+    state_regions: Option<&StateRegionEdits>,
+) {
+    // States merged into another region no longer exist, everything else is written as before.
+    let mut states: Vec<State> = game_states
+        .iter()
+        .filter(|state| {
+            !state_regions.is_some_and(|edits| edits.merged_into.contains_key(&state.name))
+        })
+        .cloned()
+        .collect();
+    let mut new_states: Vec<&String> = current_state_map
+        .keys()
+        .filter(|name| !game_states.iter().any(|state| state.name == **name))
+        .collect();
+    new_states.sort();
+    states.extend(new_states.into_iter().map(|name| State {
+        name: name.clone(),
+        sub_states: vec![],
+        homelands: vec![],
+        claims: vec![],
+    }));
+    if let Some(edits) = state_regions {
+        for state in states.iter_mut() {
+            for source in edits.history_sources(&state.name) {
+                let Some(source) = game_states
+                    .iter()
+                    .find(|game_state| game_state.name == source)
+                else {
+                    continue;
+                };
+                for homeland in source.homelands.iter() {
+                    if !state.homelands.contains(homeland) {
+                        state.homelands.push(homeland.clone());
+                    }
+                }
+                for claim in source.claims.iter() {
+                    if !state.claims.contains(claim) {
+                        state.claims.push(claim.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut pdx_script = String::new();
+
+    pdx_script.push_str("STATES = {\n");
+    states.iter().for_each(|state| {
+        pdx_script.push_str(&format!("  {} = ", state.name));
+        pdx_script.push_str("{\n");
+
+        current_state_map
+            .get(&state.name)
+            .into_iter()
+            .flatten()
+            .for_each(|sub_state| {
+                pdx_script.push_str("    create_state = {\n");
+                pdx_script.push_str(&format!("      country = c:{}\n", sub_state.owner));
+
+                pdx_script.push_str("      owned_provinces = { ");
+                sub_state.provinces.iter().for_each(|province| {
+                    pdx_script.push_str(&format!("{} ", province));
+                });
+                pdx_script.push_str("}\n");
+
+                pdx_script.push_str("    }\n");
+            });
+        state.homelands.iter().for_each(|homeland| {
+            pdx_script.push_str(&format!("    add_homeland = {}\n", homeland));
+        });
+        state.claims.iter().for_each(|claim| {
+            pdx_script.push_str(&format!("    add_claim = {}\n", claim));
+        });
+
+        pdx_script.push_str("  }\n");
+    });
+    pdx_script.push_str("}\n");
+
+    std::fs::write(path.join(STATES_FILE_NAME), pdx_script).unwrap();
+}
+
+fn write_state_pops_to_pdx_script(current_state_map: &HashMap<String, Vec<SubState>>, path: &Path) {
+    let mut pdx_script = String::new();
+
+    pdx_script.push_str("POPS = {\n");
+    current_state_map
+        .iter()
+        .for_each(|(state_name, sub_states)| {
+            pdx_script.push_str(&format!("  {} = ", state_name));
+            pdx_script.push_str("{\n");
+            sub_states.iter().for_each(|sub_state| {
+                pdx_script.push_str(&format!("    region_state:{} = ", sub_state.owner));
+                pdx_script.push_str("{\n");
+                sub_state.pops.iter().for_each(|pop| {
+                    pdx_script.push_str("      create_pop = {\n");
+                    pdx_script.push_str(&format!("        culture = {}\n", pop.culture));
+                    if let Some(religion) = &pop.religion {
+                        pdx_script.push_str(&format!("        religion = {}\n", religion));
+                    }
+                    pdx_script.push_str(&format!("        size = {}\n", pop.size));
+                    if let Some(pop_type) = &pop.pop_type {
+                        pdx_script.push_str(&format!("        pop_type = {}\n", pop_type));
+                    }
+                    pdx_script.push_str("      }\n");
+                });
+                pdx_script.push_str("    }\n");
+            });
+
+            pdx_script.push_str("  }\n");
+        });
+    pdx_script.push_str("}\n");
+    std::fs::write(path.join(POPS_FILE_NAME), pdx_script).unwrap();
+}
+
+fn overwrite_existing_pops(path: &Path, game_pop_files: &[PathBuf]) {
+    overwrite_files(path, game_pop_files, POPS_FILE_NAME);
+}
+
+fn write_state_buildings_to_pdx_script(
+    current_state_map: &HashMap<String, Vec<SubState>>,
+    path: &Path,
+) {
+    let mut pdx_script = String::new();
+
+    pdx_script.push_str("BUILDINGS = {\n");
+    current_state_map
+        .iter()
+        .for_each(|(state_name, sub_states)| {
+            let mut conditioned_sub_state_buildings: Vec<SubState> = vec![];
+
+            pdx_script.push_str(&format!("  {} = ", state_name));
+            pdx_script.push_str("{\n");
+            sub_states.iter().for_each(|sub_state| {
+                pdx_script.push_str(&format!("    region_state:{} = ", sub_state.owner));
+                pdx_script.push_str("{\n");
+                for building in &sub_state.state_buildings {
+                    if building.condition.is_some() {
+                        let mut conditioned_sub_state = sub_state.clone();
+                        conditioned_sub_state.state_buildings = vec![building.clone()];
+                        conditioned_sub_state_buildings.push(conditioned_sub_state);
+                        continue;
+                    }
+                    pdx_script.push_str("      create_building = {\n");
+                    pdx_script.push_str(&format!("        building=\"{}\"\n", building.name));
+                    if let Some(ownership) = &building.ownership {
+                        pdx_script.push_str("          add_ownership = {\n");
+                        ownership.countries.iter().for_each(|country| {
+                            pdx_script.push_str("            country = {\n");
+                            pdx_script.push_str(&format!(
+                                "              country=\"{}\"\n",
+                                country.country
+                            ));
+                            pdx_script
+                                .push_str(&format!("              levels={}\n", country.levels));
+                            pdx_script.push_str("            }\n");
+                        });
+                        ownership.buildings.iter().for_each(|building| {
+                            pdx_script.push_str("            building = {\n");
+                            pdx_script
+                                .push_str(&format!("              type=\"{}\"\n", building.type_));
+                            pdx_script.push_str(&format!(
+                                "              country=\"{}\"\n",
+                                building.country
+                            ));
+                            pdx_script
+                                .push_str(&format!("              levels={}\n", building.levels));
+                            pdx_script.push_str(&format!(
+                                "              region=\"{}\"\n",
+                                building.region
+                            ));
+                            pdx_script.push_str("            }\n");
+                        });
+                        pdx_script.push_str("          }\n");
+                    }
+                    if let Some(level) = building.level {
+                        pdx_script.push_str(&format!("        level={}\n", level));
+                    }
+                    if let Some(reserves) = building.reserves {
+                        pdx_script.push_str(&format!("        reserves={}\n", reserves));
+                    }
+                    if let Some(activate_production_methods) = &building.activate_production_methods
+                    {
+                        pdx_script.push_str("        activate_production_methods = { ");
+                        activate_production_methods.iter().for_each(|method| {
+                            pdx_script.push_str(&format!("\"{}\" ", method));
+                        });
+                        pdx_script.push_str("}\n");
+                    }
+                    pdx_script.push_str("      }\n");
+                }
+                pdx_script.push_str("    }\n");
+            });
+            pdx_script.push_str("  }\n");
+
+            pdx_script.push_str(
+                parse_building_edge_case_conditional_to_string(
+                    state_name.clone(),
+                    conditioned_sub_state_buildings,
+                )
+                .as_str(),
+            );
+        });
+    pdx_script.push_str("}\n");
+    std::fs::write(path.join(BUILDINGS_FILE_NAME), pdx_script).unwrap();
+}
+
+fn parse_building_edge_case_conditional_to_string(
+    state_name: String,
+    sub_states_with_conditional_buildings: Vec<SubState>,
+) -> String {
+    let mut pdx_script = String::new();
+
+    sub_states_with_conditional_buildings
+        .iter()
+        .for_each(|sub_state| {
+            pdx_script.push_str("  if = {\n");
+            pdx_script.push_str("    limit = {\n");
+            let condition = sub_state.state_buildings[0]
+                .condition
+                .as_ref()
+                .unwrap()
+                .as_array();
+            condition.unwrap().iter().for_each(|item| {
+                pdx_script.push_str(&format!(
+                    "      {} = {}\n",
+                    item[0].as_str().unwrap(),
+                    item[1].as_str().unwrap()
+                ));
+            });
+            pdx_script.push_str("    }\n");
+            pdx_script.push_str(&format!("    {} = ", state_name));
+            pdx_script.push_str("{\n");
+            pdx_script.push_str(&format!("      region_state:{} = ", sub_state.owner));
+            pdx_script.push_str("{\n");
+            sub_state.state_buildings.iter().for_each(|building| {
+                pdx_script.push_str("        create_building = {\n");
+                pdx_script.push_str(&format!("          building=\"{}\"\n", building.name));
+                if let Some(ownership) = &building.ownership {
+                    pdx_script.push_str("          add_ownership = {\n");
+                    ownership.countries.iter().for_each(|country| {
+                        pdx_script.push_str("            country = {\n");
+                        pdx_script
+                            .push_str(&format!("              country=\"{}\"\n", country.country));
+                        pdx_script.push_str(&format!("              levels={}\n", country.levels));
+                        pdx_script.push_str("            }\n");
+                    });
+                    ownership.buildings.iter().for_each(|building| {
+                        pdx_script.push_str("            building = {\n");
+                        pdx_script
+                            .push_str(&format!("              type=\"{}\"\n", building.type_));
+                        pdx_script
+                            .push_str(&format!("              country=\"{}\"\n", building.country));
+                        pdx_script.push_str(&format!("              levels={}\n", building.levels));
+                        pdx_script
+                            .push_str(&format!("              region=\"{}\"\n", building.region));
+                        pdx_script.push_str("            }\n");
+                    });
+                    pdx_script.push_str("          }\n");
+                }
+                if let Some(level) = building.level {
+                    pdx_script.push_str(&format!("          level={}\n", level));
+                }
+                if let Some(reserves) = building.reserves {
+                    pdx_script.push_str(&format!("          reserves={}\n", reserves));
+                }
+                if let Some(activate_production_methods) = &building.activate_production_methods {
+                    pdx_script.push_str("          activate_production_methods = { ");
+                    activate_production_methods.iter().for_each(|method| {
+                        pdx_script.push_str(&format!("\"{}\" ", method));
+                    });
+                    pdx_script.push_str("}\n");
+                }
+                pdx_script.push_str("        }\n");
+            });
+            pdx_script.push_str("      }\n");
+            pdx_script.push_str("    }\n");
+            pdx_script.push_str("  }\n");
+        });
+
+    pdx_script
+}
+
+fn overwrite_existing_buildings(path: &Path, game_building_files: &[PathBuf]) {
+    overwrite_files(path, game_building_files, BUILDINGS_FILE_NAME);
+}
+
+// Blanks out every file the mod is loaded on top of, except the one that was just written.
+fn overwrite_files(path: &Path, files: &[PathBuf], written_file_name: &str) {
+    for file in files {
+        let entry_name = file.file_name().unwrap().to_str().unwrap().to_string();
+        if entry_name == written_file_name {
+            continue;
+        }
+        std::fs::write(path.join(entry_name), "").unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::topology::{Point, Topology};
+
+/// Tolerances in pixels for each level of detail, from the full topology at level 0 to the
+/// coarsest outlines.
+pub const SIMPLIFICATION_TOLERANCES: [f32; 4] = [0.0, 0.75, 1.5, 3.0];
+
+const GRID_CELL_SIZE: i32 = 32;
+
+impl Topology {
+    /// A copy with every arc simplified to within `tolerance` pixels. Arcs keep their ends and
+    /// their indices, so outlines dissolved from the full topology can be drawn from this one,
+    /// and since arcs are shared, neighbours stay watertight. A point is only dropped when no
+    /// other point of the map ends up on the other side of the shortcut, so simplified arcs
+    /// never cross.
+    #[instrument(skip(self))]
+    pub fn simplify(&self, tolerance: f32) -> Topology {
+        if tolerance <= 0.0 {
+            return Topology {
+                arcs: self.arcs.clone(),
+                provinces: self.provinces.clone(),
+                ..Default::default()
+            };
+        }
+
+        let grid = VertexGrid::new(&self.arcs);
+        // Arcs between the same two junctions would collapse onto each other as straight lines.
+        let mut arcs_between: HashMap<(Point, Point), usize> = HashMap::new();
+        for arc in self.arcs.iter() {
+            *arcs_between.entry(ends(arc)).or_default() += 1;
+        }
+
+        let arcs = self
+            .arcs
+            .iter()
+            .enumerate()
+            .map(|(index, arc)| {
+                let shares_ends = arcs_between.get(&ends(arc)).is_some_and(|count| *count > 1);
+                ArcSimplifier {
+                    index,
+                    arc,
+                    tolerance,
+                    grid: &grid,
+                }
+                .simplify(shares_ends)
+            })
+            .collect();
+
+        Topology {
+            arcs,
+            provinces: self.provinces.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+fn ends(arc: &[Point]) -> (Point, Point) {
+    let (first, last) = (arc[0], arc[arc.len() - 1]);
+    (first.min(last), first.max(last))
+}
+
+// A point of an arc: the arc's index, the point's index along it, and the point.
+type ArcVertex = (usize, usize, Point);
+
+// Every point of every arc, bucketed so the points near a shortcut can be found quickly.
+struct VertexGrid {
+    cells: HashMap<(i32, i32), Vec<ArcVertex>>,
+}
+
+impl VertexGrid {
+    fn new(arcs: &[Vec<Point>]) -> VertexGrid {
+        let mut cells: HashMap<(i32, i32), Vec<ArcVertex>> = HashMap::new();
+        for (arc_index, arc) in arcs.iter().enumerate() {
+            for (point_index, point) in arc.iter().enumerate() {
+                cells
+                    .entry(cell_of(*point))
+                    .or_default()
+                    .push((arc_index, point_index, *point));
+            }
+        }
+        VertexGrid { cells }
+    }
+
+    fn within(&self, min: Point, max: Point) -> impl Iterator<Item = &ArcVertex> {
+        let (min_cell, max_cell) = (cell_of(min), cell_of(max));
+        (min_cell.0..=max_cell.0)
+            .flat_map(move |x| (min_cell.1..=max_cell.1).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |(_, _, (x, y))| *x >= min.0 && *x <= max.0 && *y >= min.1 && *y <= max.1)
+    }
+}
+
+fn cell_of(point: Point) -> (i32, i32) {
+    (
+        point.0.div_euclid(GRID_CELL_SIZE),
+        point.1.div_euclid(GRID_CELL_SIZE),
+    )
+}
+
+struct ArcSimplifier<'a> {
+    index: usize,
+    arc: &'a [Point],
+    tolerance: f32,
+    grid: &'a VertexGrid,
+}
+
+impl ArcSimplifier<'_> {
+    // Douglas-Peucker, keeping the furthest point of any shortcut that is too far off or
+    // would cut across another part of the map.
+    fn simplify(&self, shares_ends: bool) -> Vec<Point> {
+        let last = self.arc.len() - 1;
+        let mut keep = vec![false; self.arc.len()];
+        keep[0] = true;
+        keep[last] = true;
+
+        // Closed arcs keep a triangle and arcs sharing both ends with another keep a bend, so
+        // nothing collapses to a line.
+        if self.arc[0] == self.arc[last] {
+            let far = (1..last)
+                .max_by_key(|index| squared_distance(self.arc[0], self.arc[*index]))
+                .unwrap();
+            keep[far] = true;
+            if let Some((index, _)) = self
+                .furthest(0, far)
+                .into_iter()
+                .chain(self.furthest(far, last))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+            {
+                keep[index] = true;
+            }
+        } else if shares_ends {
+            if let Some((index, _)) = self.furthest(0, last) {
+                keep[index] = true;
+            }
+        }
+
+        let kept: Vec<usize> = (0..=last).filter(|index| keep[*index]).collect();
+        let mut pending: Vec<(usize, usize)> =
+            kept.windows(2).map(|pair| (pair[0], pair[1])).collect();
+        while let Some((from, to)) = pending.pop() {
+            let Some((index, distance)) = self.furthest(from, to) else {
+                continue;
+            };
+            if distance > self.tolerance || !self.is_clear(from, to) {
+                keep[index] = true;
+                pending.push((from, index));
+                pending.push((index, to));
+            }
+        }
+
+        (0..=last)
+            .filter(|index| keep[*index])
+            .map(|index| self.arc[index])
+            .collect()
+    }
+
+    fn furthest(&self, from: usize, to: usize) -> Option<(usize, f32)> {
+        (from + 1..to)
+            .map(|index| {
+                let distance = segment_distance(self.arc[index], self.arc[from], self.arc[to]);
+                (index, distance)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    // Whether the area between the shortcut and the points it skips is free of every other
+    // point of the map.
+    fn is_clear(&self, from: usize, to: usize) -> bool {
+        let region = &self.arc[from..=to];
+        let min = region.iter().fold(region[0], |min, point| {
+            (min.0.min(point.0), min.1.min(point.1))
+        });
+        let max = region.iter().fold(region[0], |max, point| {
+            (max.0.max(point.0), max.1.max(point.1))
+        });
+
+        self.grid
+            .within(min, max)
+            .filter(|(arc_index, point_index, _)| {
+                *arc_index != self.index || *point_index < from || *point_index > to
+            })
+            .filter(|(_, _, point)| *point != region[0] && *point != region[region.len() - 1])
+            .all(|(_, _, point)| !touches_region(region, *point))
+    }
+}
+
+// Inside the ring the points make when closed, or on its edge.
+fn touches_region(region: &[Point], point: Point) -> bool {
+    let (px, py) = (point.0 as i64, point.1 as i64);
+    let mut inside = false;
+    for (index, from) in region.iter().enumerate() {
+        let to = region[(index + 1) % region.len()];
+        let ((x1, y1), (x2, y2)) = ((from.0 as i64, from.1 as i64), (to.0 as i64, to.1 as i64));
+
+        let cross = (x2 - x1) * (py - y1) - (y2 - y1) * (px - x1);
+        if cross == 0
+            && px >= x1.min(x2)
+            && px <= x1.max(x2)
+            && py >= y1.min(y2)
+            && py <= y1.max(y2)
+        {
+            return true;
+        }
+        // Left of an upward edge or right of a downward one is left of where it crosses.
+        if (y1 > py) != (y2 > py) && (cross > 0) == (y2 > y1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn squared_distance(a: Point, b: Point) -> i64 {
+    let (dx, dy) = ((b.0 - a.0) as i64, (b.1 - a.1) as i64);
+    dx * dx + dy * dy
+}
+
+fn segment_distance(point: Point, from: Point, to: Point) -> f32 {
+    let (px, py) = ((point.0 - from.0) as f32, (point.1 - from.1) as f32);
+    let (dx, dy) = ((to.0 - from.0) as f32, (to.1 - from.1) as f32);
+    let length = dx * dx + dy * dy;
+    let along = match length > 0.0 {
+        true => ((px * dx + py * dy) / length).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    ((px - along * dx).powi(2) + (py - along * dy).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topology(arcs: Vec<Vec<Point>>) -> Topology {
+        Topology {
+            arcs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn level_zero_keeps_every_point() {
+        let arcs = vec![vec![(0, 0), (1, 0), (2, 0), (3, 1)]];
+        assert_eq!(topology(arcs.clone()).simplify(0.0).arcs, arcs);
+    }
+
+    #[test]
+    fn wiggles_within_the_tolerance_are_dropped() {
+        let simplified = topology(vec![vec![(0, 0), (2, 1), (4, 0), (6, 1), (8, 0)]]).simplify(1.5);
+        assert_eq!(simplified.arcs, vec![vec![(0, 0), (8, 0)]]);
+    }
+
+    #[test]
+    fn points_beyond_the_tolerance_are_kept() {
+        let arcs = vec![vec![(0, 0), (4, 4), (8, 0)]];
+        assert_eq!(topology(arcs.clone()).simplify(1.5).arcs, arcs);
+    }
+
+    #[test]
+    fn shortcuts_never_pass_another_arc() {
+        // The bend is within the tolerance, but cutting it would leave the second arc's end on
+        // the other side.
+        let simplified =
+            topology(vec![vec![(0, 0), (4, 2), (8, 0)], vec![(4, 1), (4, 10)]]).simplify(3.0);
+        assert_eq!(simplified.arcs[0], vec![(0, 0), (4, 2), (8, 0)]);
+    }
+
+    #[test]
+    fn closed_arcs_keep_a_triangle() {
+        let simplified =
+            topology(vec![vec![(0, 0), (4, 0), (4, 4), (0, 4), (0, 0)]]).simplify(10.0);
+        let arc = &simplified.arcs[0];
+        assert_eq!(arc.len(), 4);
+        assert_eq!(arc[0], arc[3]);
+    }
+
+    #[test]
+    fn arcs_between_the_same_junctions_keep_a_bend() {
+        let simplified = topology(vec![
+            vec![(0, 0), (4, 1), (8, 0)],
+            vec![(0, 0), (4, -1), (8, 0)],
+        ])
+        .simplify(3.0);
+        assert_eq!(simplified.arcs[0].len(), 3);
+        assert_eq!(simplified.arcs[1].len(), 3);
+    }
+}
@@ -0,0 +1,841 @@
+use jomini::{text::ValueReader, TextTape, Utf8Encoding};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+use tracing::{info, instrument};
+
+use crate::{
+    country::Country,
+    get_state_populations::Pop,
+    mod_stack::ModStack,
+    pdx_script_parser::{block_ranges, replace_ranges},
+    project::{province_key, state_key, EditError, Project},
+    strategic_regions::StrategicRegion,
+};
+
+pub const STATE_REGIONS_PATH: &str = "map_data/state_regions";
+const LOCALIZATION_PATH: &str = "localization/english";
+const LOCALIZATION_FILE_NAME: &str = "v3_history_state_regions_l_english.yml";
+
+/// A state as the map defines it in `map_data/state_regions`, keyed `s:STATE_NAME` like its
+/// history. This is what the land can hold, whoever owns it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct StateRegion {
+    pub name: String,
+    /// The file in `map_data/state_regions` it is defined in.
+    pub file: String,
+    pub id: Option<i64>,
+    pub subsistence_building: Option<String>,
+    pub provinces: Vec<String>,
+    pub impassable: Vec<String>,
+    pub prime_land: Vec<String>,
+    pub traits: Vec<String>,
+    pub hubs: Hubs,
+    pub arable_land: i64,
+    /// Building groups that can be farmed on the arable land, such as `bg_wheat_farms`.
+    pub arable_resources: Vec<String>,
+    /// Most levels of each building group, such as `bg_iron_mining`.
+    pub capped_resources: BTreeMap<String, i64>,
+    pub resources: Vec<Resource>,
+    /// The sea province fleets leave the state from.
+    pub naval_exit_id: Option<i64>,
+}
+
+/// The provinces the game places each kind of hub in.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Hubs {
+    pub city: Option<String>,
+    pub port: Option<String>,
+    pub farm: Option<String>,
+    pub mine: Option<String>,
+    pub wood: Option<String>,
+}
+
+/// A `resource` block: a deposit that is found, or discovered over the game, such as gold
+/// fields that deplete into gold mines.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Resource {
+    pub kind: String,
+    pub depleted_type: Option<String>,
+    pub discovered_amount: Option<i64>,
+    pub undiscovered_amount: Option<i64>,
+}
+
+impl StateRegion {
+    /// Regions in later files and mods replace earlier ones with the same name.
+    pub fn parse_from(mod_stack: &ModStack) -> Vec<StateRegion> {
+        let mut regions: Vec<StateRegion> = vec![];
+
+        for file in mod_stack.files_in(STATE_REGIONS_PATH) {
+            if file.extension().unwrap() != "txt" {
+                continue;
+            }
+            for region in parse_state_regions(file) {
+                regions.retain(|existing| existing.name != region.name);
+                regions.push(region);
+            }
+        }
+
+        regions
+    }
+}
+
+impl Hubs {
+    /// Each hub's kind and province, for the ones the state has.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &String)> {
+        [
+            ("city", &self.city),
+            ("port", &self.port),
+            ("farm", &self.farm),
+            ("mine", &self.mine),
+            ("wood", &self.wood),
+        ]
+        .into_iter()
+        .filter_map(|(kind, province)| Some((kind, province.as_ref()?)))
+    }
+}
+
+/// State regions after their borders were edited, with what the history needs to follow them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StateRegionEdits {
+    pub regions: Vec<StateRegion>,
+    /// Files with an edited region, which are written whole.
+    pub files: BTreeSet<String>,
+    /// Regions split off, and the region each was split from.
+    pub split_from: BTreeMap<String, String>,
+    /// Regions merged away, and the region each was merged into.
+    pub merged_into: BTreeMap<String, String>,
+    /// Strategic regions whose states changed, since every state region has to be in one.
+    pub strategic_regions: Vec<StrategicRegion>,
+}
+
+/// What editing state regions did: the countries whose states changed, and the hubs and naval
+/// exits that had to change with them.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct StateRegionReport {
+    pub countries: BTreeSet<String>,
+    pub notes: Vec<String>,
+}
+
+impl StateRegionEdits {
+    pub fn new(regions: Vec<StateRegion>) -> StateRegionEdits {
+        StateRegionEdits {
+            regions,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_edited(&self) -> bool {
+        !self.files.is_empty()
+    }
+
+    pub fn region(&self, state: &str) -> Option<&StateRegion> {
+        self.regions.iter().find(|region| region.name == state)
+    }
+
+    pub fn region_of(&self, province: &str) -> Option<&StateRegion> {
+        self.regions
+            .iter()
+            .find(|region| region.provinces.iter().any(|p| p == province))
+    }
+
+    /// The states whose homelands and claims `state` takes on: the one it was split from and
+    /// the ones merged into it, and in turn theirs.
+    pub fn history_sources(&self, state: &str) -> Vec<String> {
+        let mut sources: Vec<String> = vec![];
+        let mut queue = vec![state.to_string()];
+        while let Some(state) = queue.pop() {
+            let parents = self.split_from.get(&state).into_iter().chain(
+                self.merged_into
+                    .iter()
+                    .filter(|(_, into)| **into == state)
+                    .map(|(from, _)| from),
+            );
+            for parent in parents {
+                if !sources.contains(parent) {
+                    sources.push(parent.clone());
+                    queue.push(parent.clone());
+                }
+            }
+        }
+        sources
+    }
+
+    fn region_mut(&mut self, state: &str) -> Option<&mut StateRegion> {
+        self.regions.iter_mut().find(|region| region.name == state)
+    }
+}
+
+impl StateRegion {
+    /// The region as it is written in `map_data/state_regions`. Fields the editor doesn't read
+    /// are not written back, so only edited regions are written this way.
+    pub fn to_script(&self) -> String {
+        let quoted = |values: &[String]| -> String {
+            values
+                .iter()
+                .map(|value| format!("\"{}\" ", value))
+                .collect()
+        };
+        let mut script = String::new();
+        writeln!(script, "{} = {{", self.name.trim_start_matches("s:")).unwrap();
+        if let Some(id) = self.id {
+            writeln!(script, "    id = {}", id).unwrap();
+        }
+        if let Some(subsistence_building) = &self.subsistence_building {
+            writeln!(
+                script,
+                "    subsistence_building = \"{}\"",
+                subsistence_building
+            )
+            .unwrap();
+        }
+        writeln!(script, "    provinces = {{ {}}}", quoted(&self.provinces)).unwrap();
+        for (field, provinces) in [
+            ("impassable", &self.impassable),
+            ("prime_land", &self.prime_land),
+        ] {
+            if !provinces.is_empty() {
+                writeln!(script, "    {} = {{ {}}}", field, quoted(provinces)).unwrap();
+            }
+        }
+        if !self.traits.is_empty() {
+            writeln!(script, "    traits = {{ {}}}", quoted(&self.traits)).unwrap();
+        }
+        for (kind, province) in self.hubs.iter() {
+            writeln!(script, "    {} = \"{}\"", kind, province).unwrap();
+        }
+        writeln!(script, "    arable_land = {}", self.arable_land).unwrap();
+        writeln!(
+            script,
+            "    arable_resources = {{ {}}}",
+            quoted(&self.arable_resources)
+        )
+        .unwrap();
+        writeln!(script, "    capped_resources = {{").unwrap();
+        for (resource, amount) in self.capped_resources.iter() {
+            writeln!(script, "        {} = {}", resource, amount).unwrap();
+        }
+        writeln!(script, "    }}").unwrap();
+        for resource in self.resources.iter() {
+            writeln!(script, "    resource = {{").unwrap();
+            writeln!(script, "        type = \"{}\"", resource.kind).unwrap();
+            if let Some(depleted_type) = &resource.depleted_type {
+                writeln!(script, "        depleted_type = \"{}\"", depleted_type).unwrap();
+            }
+            if let Some(amount) = resource.undiscovered_amount {
+                writeln!(script, "        undiscovered_amount = {}", amount).unwrap();
+            }
+            if let Some(amount) = resource.discovered_amount {
+                writeln!(script, "        discovered_amount = {}", amount).unwrap();
+            }
+            writeln!(script, "    }}").unwrap();
+        }
+        if let Some(naval_exit_id) = self.naval_exit_id {
+            writeln!(script, "    naval_exit_id = {}", naval_exit_id).unwrap();
+        }
+        writeln!(script, "}}").unwrap();
+        script
+    }
+
+    /// Keeps the hubs inside the region, moving the ones that left it to its first passable
+    /// province. Which provinces are coastal isn't known here, so a port that left is cleared
+    /// along with the naval exit instead. Returns what changed.
+    fn validate_hubs(&mut self) -> Vec<String> {
+        let mut notes = vec![];
+        let fallback = self
+            .provinces
+            .iter()
+            .find(|province| !self.impassable.contains(province))
+            .or(self.provinces.first())
+            .cloned();
+        let Hubs {
+            city,
+            port,
+            farm,
+            mine,
+            wood,
+        } = &mut self.hubs;
+
+        for (kind, hub) in [
+            ("city", city),
+            ("farm", farm),
+            ("mine", mine),
+            ("wood", wood),
+        ] {
+            if hub
+                .as_ref()
+                .is_some_and(|province| self.provinces.contains(province))
+            {
+                continue;
+            }
+            let Some(fallback) = fallback.clone() else {
+                continue;
+            };
+            notes.push(match hub.replace(fallback.clone()) {
+                Some(previous) => format!(
+                    "{} hub of {} moved from {} to {}",
+                    kind, self.name, previous, fallback
+                ),
+                None => format!("{} hub of {} set to {}", kind, self.name, fallback),
+            });
+        }
+        if let Some(previous) = port.take_if(|province| !self.provinces.contains(province)) {
+            notes.push(format!(
+                "{} lost its port {}, give it a coastal province if it still has one",
+                self.name, previous
+            ));
+        }
+        if port.is_none() {
+            if let Some(naval_exit_id) = self.naval_exit_id.take() {
+                notes.push(format!(
+                    "{} has no port, so its naval exit {} was removed",
+                    self.name, naval_exit_id
+                ));
+            }
+        }
+        notes
+    }
+}
+
+impl Project {
+    /// The state regions of the mod stack, with any edits to them, read the first time they are
+    /// needed.
+    pub fn state_regions(&mut self) -> &[StateRegion] {
+        &self.state_region_edits_mut().regions
+    }
+
+    /// Moves provinces into the state region `to`, along with their owners' pops in proportion
+    /// to the provinces moved. An owner's buildings follow once it has no provinces left in the
+    /// region they were in, and a region left without provinces is merged into `to`.
+    #[instrument(skip(self))]
+    pub fn move_to_state_region(
+        &mut self,
+        provinces: &[String],
+        to: &str,
+    ) -> Result<StateRegionReport, EditError> {
+        let mut seen = BTreeSet::new();
+        let provinces: Vec<String> = provinces
+            .iter()
+            .filter(|province| seen.insert(province.as_str()))
+            .cloned()
+            .collect();
+        if provinces.is_empty() {
+            return Err(EditError::NothingToTransfer);
+        }
+        let edits = self.state_region_edits_mut();
+        if edits.region(to).is_none() {
+            return Err(EditError::UnknownStateRegion(to.to_string()));
+        }
+        // Checked before anything is moved, so a refused edit changes nothing.
+        let mut moves = vec![];
+        for province in provinces {
+            let from = edits
+                .region_of(&province)
+                .ok_or_else(|| EditError::NotInAnyStateRegion(province.clone()))?;
+            if from.name == to {
+                return Err(EditError::AlreadyInStateRegion {
+                    province,
+                    state: to.to_string(),
+                });
+            }
+            moves.push((province, from.name.clone()));
+        }
+
+        let mut report = StateRegionReport::default();
+        let mut edited_regions = BTreeSet::from([to.to_string()]);
+        for (province, from) in moves {
+            self.move_province_to_region(&province, &from, to, &mut report);
+            edited_regions.insert(from);
+        }
+        let edits = self.state_region_edits_mut();
+        for state in edited_regions {
+            if let Some(region) = edits.region_mut(&state) {
+                report.notes.extend(region.validate_hubs());
+            }
+        }
+        Ok(report)
+    }
+
+    /// Splits `provinces` off `state` into the new region `new_state`, with the next free id
+    /// and the provinces' share of the arable land and capped resources. Traits and arable
+    /// resources are copied, deposits stay with `state`.
+    #[instrument(skip(self))]
+    pub fn split_state_region(
+        &mut self,
+        state: &str,
+        provinces: &[String],
+        new_state: &str,
+    ) -> Result<StateRegionReport, EditError> {
+        let edits = self.state_region_edits_mut();
+        if edits.region(new_state).is_some() {
+            return Err(EditError::StateRegionAlreadyExists(new_state.to_string()));
+        }
+        let region = edits
+            .region(state)
+            .ok_or_else(|| EditError::UnknownStateRegion(state.to_string()))?;
+        if provinces.is_empty() {
+            return Err(EditError::NothingToTransfer);
+        }
+        if let Some(province) = provinces
+            .iter()
+            .find(|province| !region.provinces.contains(province))
+        {
+            return Err(EditError::NotInStateRegion {
+                province: province.clone(),
+                state: state.to_string(),
+            });
+        }
+        let moved = provinces.iter().collect::<BTreeSet<_>>().len() as i64;
+        let total = region.provinces.len() as i64;
+        if moved == total {
+            return Err(EditError::EmptyStateRegion(state.to_string()));
+        }
+
+        let share = |amount: i64| amount * moved / total;
+        let new_region = StateRegion {
+            name: new_state.to_string(),
+            file: region.file.clone(),
+            id: Some(
+                edits
+                    .regions
+                    .iter()
+                    .filter_map(|region| region.id)
+                    .max()
+                    .unwrap_or(0)
+                    + 1,
+            ),
+            subsistence_building: region.subsistence_building.clone(),
+            traits: region.traits.clone(),
+            arable_land: share(region.arable_land),
+            arable_resources: region.arable_resources.clone(),
+            capped_resources: region
+                .capped_resources
+                .iter()
+                .map(|(resource, amount)| (resource.clone(), share(*amount)))
+                .filter(|(_, amount)| *amount > 0)
+                .collect(),
+            ..Default::default()
+        };
+        let region = edits.region_mut(state).unwrap();
+        region.arable_land -= new_region.arable_land;
+        for (resource, amount) in new_region.capped_resources.iter() {
+            *region.capped_resources.get_mut(resource).unwrap() -= amount;
+        }
+        edits.files.insert(new_region.file.clone());
+        edits
+            .split_from
+            .insert(new_state.to_string(), state.to_string());
+        edits.regions.push(new_region);
+        let new_state = new_state.to_string();
+        self.edit_strategic_region(state, |states| {
+            let index = states.iter().position(|member| member == state).unwrap();
+            states.insert(index + 1, new_state.clone());
+        });
+
+        self.move_to_state_region(provinces, &new_state)
+    }
+
+    /// Merges `from` into `into`: its provinces with their pops and buildings, and its arable
+    /// land, resources and traits. `from` is left out of the mod.
+    #[instrument(skip(self))]
+    pub fn merge_state_regions(
+        &mut self,
+        from: &str,
+        into: &str,
+    ) -> Result<StateRegionReport, EditError> {
+        if from == into {
+            return Err(EditError::SameStateRegion(from.to_string()));
+        }
+        let edits = self.state_region_edits_mut();
+        let from_region = edits
+            .region(from)
+            .ok_or_else(|| EditError::UnknownStateRegion(from.to_string()))?
+            .clone();
+        let into_region = edits
+            .region_mut(into)
+            .ok_or_else(|| EditError::UnknownStateRegion(into.to_string()))?;
+
+        into_region.arable_land += from_region.arable_land;
+        for (resource, amount) in from_region.capped_resources.iter() {
+            *into_region
+                .capped_resources
+                .entry(resource.clone())
+                .or_default() += amount;
+        }
+        for resource in from_region.arable_resources.iter() {
+            if !into_region.arable_resources.contains(resource) {
+                into_region.arable_resources.push(resource.clone());
+            }
+        }
+        for state_trait in from_region.traits.iter() {
+            if !into_region.traits.contains(state_trait) {
+                into_region.traits.push(state_trait.clone());
+            }
+        }
+        into_region
+            .resources
+            .extend(from_region.resources.iter().cloned());
+        let into_file = into_region.file.clone();
+        edits.files.insert(into_file);
+
+        if from_region.provinces.is_empty() {
+            edits.regions.retain(|region| region.name != from);
+            edits.files.insert(from_region.file.clone());
+            edits.merged_into.insert(from.to_string(), into.to_string());
+            self.edit_strategic_region(from, |states| states.retain(|member| member != from));
+            return Ok(StateRegionReport::default());
+        }
+        self.move_to_state_region(&from_region.provinces, into)
+    }
+
+    fn move_province_to_region(
+        &mut self,
+        province: &str,
+        from: &str,
+        to: &str,
+        report: &mut StateRegionReport,
+    ) {
+        let edits = self.state_region_edits_mut();
+        let from_region = edits.region_mut(from).unwrap();
+        let take = |provinces: &mut Vec<String>| {
+            let had = provinces.iter().any(|p| p == province);
+            provinces.retain(|p| p != province);
+            had
+        };
+        take(&mut from_region.provinces);
+        let impassable = take(&mut from_region.impassable);
+        let prime_land = take(&mut from_region.prime_land);
+        let was_port = from_region.hubs.port.as_deref() == Some(province);
+        let naval_exit_id = match was_port {
+            true => {
+                from_region.hubs.port = None;
+                from_region.naval_exit_id.take()
+            }
+            false => None,
+        };
+        let from_file = from_region.file.clone();
+        let from_is_empty = from_region.provinces.is_empty();
+
+        let to_region = edits.region_mut(to).unwrap();
+        to_region.provinces.push(province.to_string());
+        if impassable {
+            to_region.impassable.push(province.to_string());
+        }
+        if prime_land {
+            to_region.prime_land.push(province.to_string());
+        }
+        // A port takes its naval exit along to a region without a port of its own.
+        if was_port && to_region.hubs.port.is_none() {
+            to_region.hubs.port = Some(province.to_string());
+            to_region.naval_exit_id = to_region.naval_exit_id.or(naval_exit_id);
+            report
+                .notes
+                .push(format!("port {} moved from {} to {}", province, from, to));
+        } else if was_port {
+            report.notes.push(format!(
+                "{} lost its port {} to {}, which already has one",
+                from, province, to
+            ));
+        }
+        let to_file = to_region.file.clone();
+        edits.files.extend([from_file, to_file]);
+        if from_is_empty {
+            edits.regions.retain(|region| region.name != from);
+            edits.merged_into.insert(from.to_string(), to.to_string());
+            report.notes.push(format!(
+                "{} has no provinces left and was merged into {}",
+                from, to
+            ));
+            self.edit_strategic_region(from, |states| states.retain(|member| member != from));
+        }
+
+        self.move_owned_province(province, from, to, report);
+    }
+
+    // Edits the states of the strategic region `member` is in, keeping the edited region with
+    // the state region edits.
+    fn edit_strategic_region(&mut self, member: &str, edit: impl FnOnce(&mut Vec<String>)) {
+        let Some(mut region) = self
+            .strategic_regions()
+            .into_iter()
+            .find(|region| region.states.iter().any(|state| state == member))
+        else {
+            return;
+        };
+        edit(&mut region.states);
+        let edits = self.state_region_edits_mut();
+        edits
+            .strategic_regions
+            .retain(|edited| edited.name != region.name);
+        edits.strategic_regions.push(region);
+    }
+
+    // The history keeps pops per state, so the province takes its share of them along, the
+    // rest following with the owner's last province in the region.
+    fn move_owned_province(
+        &mut self,
+        province: &str,
+        from: &str,
+        to: &str,
+        report: &mut StateRegionReport,
+    ) {
+        let Some((owner, state)) = self.province_owner(province) else {
+            return;
+        };
+        if state != from {
+            return;
+        }
+        let index = self
+            .countries
+            .iter()
+            .position(|country| country.name == owner)
+            .unwrap();
+        let country = self.countries.remove(index);
+        let province_count = country
+            .states
+            .iter()
+            .find(|country_state| country_state.name == from)
+            .unwrap()
+            .provinces
+            .len() as i64;
+
+        let (mut country, mut pops, state_buildings) = country.remove_province(from, province);
+        if let Some(kept_state) = country
+            .states
+            .iter_mut()
+            .find(|country_state| country_state.name == from)
+        {
+            pops = take_share(&mut kept_state.pops, province_count);
+        }
+        let left_region = !country
+            .states
+            .iter()
+            .any(|country_state| country_state.name == from);
+        self.countries.insert(
+            index,
+            country.add_province(to, province, pops, state_buildings),
+        );
+        if left_region {
+            rename_building_region(&mut self.countries, &owner, from, to);
+        }
+        report.countries.insert(owner);
+    }
+}
+
+// One province's share of each pop, out of `provinces`.
+fn take_share(pops: &mut [Pop], provinces: i64) -> Vec<Pop> {
+    pops.iter_mut()
+        .filter_map(|pop| {
+            let size = pop.size / provinces;
+            pop.size -= size;
+            (size > 0).then(|| Pop {
+                size,
+                ..pop.clone()
+            })
+        })
+        .collect()
+}
+
+// Buildings owned through the owner's buildings in a region point at the region by name, so
+// they follow its buildings to the region they moved to.
+fn rename_building_region(countries: &mut [Country], owner: &str, from: &str, to: &str) {
+    let owner_reference = format!("c:{}", owner);
+    let (from, to) = (from.trim_start_matches("s:"), to.trim_start_matches("s:"));
+    for country in countries.iter_mut() {
+        for building in country
+            .states
+            .iter_mut()
+            .flat_map(|country_state| country_state.state_buildings.iter_mut())
+        {
+            let Some(ownership) = building.ownership.as_mut() else {
+                continue;
+            };
+            for owning_building in ownership.buildings.iter_mut() {
+                if owning_building.country == owner_reference && owning_building.region == from {
+                    owning_building.region = to.to_string();
+                }
+            }
+        }
+    }
+}
+
+/// Writes the edited files of `map_data/state_regions` into the working directory, and names
+/// for the regions split off so the game doesn't show their keys. Only the blocks of regions
+/// that changed are rewritten, the rest of each file is copied as it is.
+#[instrument(skip_all, fields(working_dir = ?working_dir))]
+pub fn write_state_regions(edits: &StateRegionEdits, mod_stack: &ModStack, working_dir: &Path) {
+    let originals = StateRegion::parse_from(mod_stack);
+    let state_regions_dir = working_dir.join(STATE_REGIONS_PATH);
+    std::fs::create_dir_all(&state_regions_dir).unwrap();
+    for file in edits.files.iter() {
+        let script =
+            std::fs::read_to_string(mod_stack.file(&format!("{}/{}", STATE_REGIONS_PATH, file)))
+                .unwrap_or_default();
+        let blocks = block_ranges(&script);
+        let mut replacements = vec![];
+        for (key, range) in blocks.iter() {
+            let state = state_key(key);
+            match edits.region(&state) {
+                Some(region) => {
+                    let original = originals.iter().find(|original| original.name == state);
+                    if original != Some(region) {
+                        let edited = region.to_script();
+                        replacements.push((range.clone(), edited.trim_end().to_string()));
+                    }
+                }
+                None if edits.merged_into.contains_key(&state) => {
+                    replacements.push((range.clone(), String::new()))
+                }
+                None => {}
+            }
+        }
+        let mut script = replace_ranges(&script, replacements);
+        for region in edits.regions.iter().filter(|region| {
+            region.file == *file && !blocks.iter().any(|(key, _)| state_key(key) == region.name)
+        }) {
+            script.push('\n');
+            script.push_str(&region.to_script());
+        }
+        std::fs::write(state_regions_dir.join(file), script).unwrap();
+    }
+
+    let new_regions: Vec<&String> = edits
+        .split_from
+        .keys()
+        .filter(|state| edits.region(state).is_some())
+        .collect();
+    let localization_path = working_dir
+        .join(LOCALIZATION_PATH)
+        .join(LOCALIZATION_FILE_NAME);
+    if new_regions.is_empty() {
+        if localization_path.exists() {
+            std::fs::remove_file(localization_path).unwrap();
+        }
+        return;
+    }
+    // Localization files have to start with a byte order mark for the game to read them.
+    let mut localization = String::from("\u{feff}l_english:\n");
+    for state in new_regions {
+        let key = state.trim_start_matches("s:");
+        writeln!(localization, " {}:0 \"{}\"", key, display_name(key)).unwrap();
+    }
+    std::fs::create_dir_all(localization_path.parent().unwrap()).unwrap();
+    std::fs::write(localization_path, localization).unwrap();
+    info!(files = edits.files.len(), "Wrote state regions");
+}
+
+// `STATE_NEW_ENGLAND` as `New England`, until someone names it properly.
+fn display_name(key: &str) -> String {
+    key.trim_start_matches("STATE_")
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let word = word.to_lowercase();
+            let mut characters = word.chars();
+            match characters.next() {
+                Some(first) => first.to_uppercase().chain(characters).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn parse_state_regions(file: PathBuf) -> Vec<StateRegion> {
+    let file_name = file.file_name().unwrap().to_string_lossy().to_string();
+    let data = std::fs::read(&file).unwrap();
+    let tape = TextTape::from_slice(&data).unwrap();
+    let mut regions = vec![];
+
+    for (key, _op, value) in tape.utf8_reader().fields() {
+        let Ok(fields) = value.read_object() else {
+            continue;
+        };
+        let mut region = StateRegion {
+            name: state_key(&key.read_str()),
+            file: file_name.clone(),
+            ..Default::default()
+        };
+        for (field, _op, value) in fields.fields() {
+            match field.read_str().as_ref() {
+                "id" => region.id = read_number(&value),
+                "subsistence_building" => region.subsistence_building = value.read_string().ok(),
+                "provinces" => region.provinces = read_provinces(&value),
+                "impassable" => region.impassable = read_provinces(&value),
+                "prime_land" => region.prime_land = read_provinces(&value),
+                "traits" => region.traits = read_strings(&value),
+                "city" => region.hubs.city = read_province(&value),
+                "port" => region.hubs.port = read_province(&value),
+                "farm" => region.hubs.farm = read_province(&value),
+                "mine" => region.hubs.mine = read_province(&value),
+                "wood" => region.hubs.wood = read_province(&value),
+                "arable_land" => region.arable_land = read_number(&value).unwrap_or(0),
+                "arable_resources" => region.arable_resources = read_strings(&value),
+                "capped_resources" => region.capped_resources = read_amounts(&value),
+                "resource" => region.resources.extend(read_resource(&value)),
+                "naval_exit_id" => region.naval_exit_id = read_number(&value),
+                _ => {}
+            }
+        }
+        regions.push(region);
+    }
+
+    regions
+}
+
+fn read_resource(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<Resource> {
+    let mut resource = Resource::default();
+    for (field, _op, value) in value.read_object().ok()?.fields() {
+        match field.read_str().as_ref() {
+            "type" => resource.kind = value.read_string().unwrap_or_default(),
+            "depleted_type" => resource.depleted_type = value.read_string().ok(),
+            "discovered_amount" => resource.discovered_amount = read_number(&value),
+            "undiscovered_amount" => resource.undiscovered_amount = read_number(&value),
+            _ => {}
+        }
+    }
+    Some(resource)
+}
+
+fn read_amounts(value: &ValueReader<'_, '_, Utf8Encoding>) -> BTreeMap<String, i64> {
+    let Ok(fields) = value.read_object() else {
+        return BTreeMap::new();
+    };
+    fields
+        .fields()
+        .filter_map(|(key, _op, value)| Some((key.read_str().to_string(), read_number(&value)?)))
+        .collect()
+}
+
+fn read_number(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<i64> {
+    value.read_string().ok()?.parse().ok()
+}
+
+fn read_strings(value: &ValueReader<'_, '_, Utf8Encoding>) -> Vec<String> {
+    value
+        .read_array()
+        .map(|values| {
+            values
+                .values()
+                .filter_map(|value| value.read_string().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn read_province(value: &ValueReader<'_, '_, Utf8Encoding>) -> Option<String> {
+    value
+        .read_string()
+        .ok()
+        .map(|province| province_key(&province))
+}
+
+fn read_provinces(value: &ValueReader<'_, '_, Utf8Encoding>) -> Vec<String> {
+    read_strings(value)
+        .iter()
+        .map(|province| province_key(province))
+        .collect()
+}
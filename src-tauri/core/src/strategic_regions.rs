@@ -0,0 +1,195 @@
+use jomini::TextTape;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+use tracing::{info, instrument};
+
+use crate::{
+    color_converter::ColorConverter,
+    country::Country,
+    mod_stack::ModStack,
+    pdx_script_parser::{block_ranges, replace_ranges},
+    project::{province_key, state_key},
+    province_map_to_geojson::{simplified_outlines, Coords},
+    topology::Topology,
+};
+
+pub const STRATEGIC_REGIONS_PATH: &str = "common/strategic_regions";
+
+/// A group of states from `common/strategic_regions`, keyed as it is there, `sr:region_name`.
+/// States are keyed `s:STATE_NAME` like everywhere else.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StrategicRegion {
+    pub name: String,
+    /// The file in `common/strategic_regions` it came from.
+    pub file: String,
+    pub color: Option<(u8, u8, u8)>,
+    pub capital_province: Option<String>,
+    pub states: Vec<String>,
+}
+
+/// What a region adds up to across all of its owners.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct RegionTotals {
+    pub pops: i64,
+    /// Levels of each building.
+    pub buildings: BTreeMap<String, i64>,
+    /// Provinces each country owns in the region.
+    pub owners: BTreeMap<String, usize>,
+}
+
+impl StrategicRegion {
+    pub fn parse_from(mod_stack: &ModStack) -> Vec<StrategicRegion> {
+        let mut regions: Vec<StrategicRegion> = vec![];
+
+        for file in mod_stack.files_in(STRATEGIC_REGIONS_PATH) {
+            if file.extension().unwrap() != "txt" {
+                continue;
+            }
+            for region in parse_regions(file) {
+                regions.retain(|existing| existing.name != region.name);
+                regions.push(region);
+            }
+        }
+
+        regions
+    }
+
+    /// Whoever owns them, once the region's states are found in `countries`.
+    pub fn totals(&self, countries: &[Country]) -> RegionTotals {
+        let mut totals = RegionTotals::default();
+        for country in countries {
+            for state in country.states.iter() {
+                if !self.states.contains(&state.name) {
+                    continue;
+                }
+                totals.pops += state.pops.iter().map(|pop| pop.size).sum::<i64>();
+                for building in state.state_buildings.iter() {
+                    *totals.buildings.entry(building.name.clone()).or_default() +=
+                        building.level.unwrap_or(0);
+                }
+                *totals.owners.entry(country.name.clone()).or_default() += state.provinces.len();
+            }
+        }
+        totals
+    }
+
+    pub fn provinces(&self, countries: &[Country]) -> Vec<String> {
+        countries
+            .iter()
+            .flat_map(|country| country.states.iter())
+            .filter(|state| self.states.contains(&state.name))
+            .flat_map(|state| state.provinces.iter().cloned())
+            .collect()
+    }
+}
+
+/// Each region's outline, dissolved from the provinces of its states on the full topology and
+/// drawn from `simplified`.
+pub fn region_outlines(
+    topology: &Topology,
+    simplified: &Topology,
+    regions: &[StrategicRegion],
+    countries: &[Country],
+) -> HashMap<String, Coords> {
+    let groups: HashMap<String, Vec<String>> = regions
+        .iter()
+        .map(|region| (region.name.clone(), region.provinces(countries)))
+        .collect();
+    simplified_outlines(topology, simplified, &groups)
+}
+
+/// Writes the files of `common/strategic_regions` with an edited region into the working
+/// directory. Only the edited regions' `states` lists change, the rest of each file is copied.
+#[instrument(skip_all, fields(working_dir = ?working_dir))]
+pub fn write_strategic_regions(
+    regions: &[StrategicRegion],
+    mod_stack: &ModStack,
+    working_dir: &Path,
+) {
+    let mut files: BTreeMap<&str, Vec<&StrategicRegion>> = BTreeMap::new();
+    for region in regions {
+        files.entry(&region.file).or_default().push(region);
+    }
+
+    let strategic_regions_dir = working_dir.join(STRATEGIC_REGIONS_PATH);
+    std::fs::create_dir_all(&strategic_regions_dir).unwrap();
+    for (file, regions) in files.iter() {
+        let script = std::fs::read_to_string(
+            mod_stack.file(&format!("{}/{}", STRATEGIC_REGIONS_PATH, file)),
+        )
+        .unwrap();
+        let mut replacements = vec![];
+        for (key, range) in block_ranges(&script) {
+            let Some(region) = regions.iter().find(|region| region.name == key) else {
+                continue;
+            };
+            let block = &script[range.clone()];
+            let body_start = block.find('{').unwrap() + 1;
+            let Some((_, states)) = block_ranges(&block[body_start..block.len() - 1])
+                .into_iter()
+                .find(|(field, _)| field == "states")
+            else {
+                continue;
+            };
+            let states_start = range.start + body_start;
+            let states_list: String = region
+                .states
+                .iter()
+                .map(|state| format!("{} ", state.trim_start_matches("s:")))
+                .collect();
+            replacements.push((
+                states_start + states.start..states_start + states.end,
+                format!("states = {{ {}}}", states_list),
+            ));
+        }
+        std::fs::write(
+            strategic_regions_dir.join(file),
+            replace_ranges(&script, replacements),
+        )
+        .unwrap();
+    }
+    info!(files = files.len(), "Wrote strategic regions");
+}
+
+fn parse_regions(file: PathBuf) -> Vec<StrategicRegion> {
+    let data = std::fs::read(&file).unwrap();
+    let tape = TextTape::from_slice(&data).unwrap();
+    let mut regions = vec![];
+
+    for (key, _op, value) in tape.utf8_reader().fields() {
+        let Ok(fields) = value.read_object() else {
+            continue;
+        };
+        let mut region = StrategicRegion {
+            name: key.read_str().to_string(),
+            file: file.file_name().unwrap().to_string_lossy().to_string(),
+            color: None,
+            capital_province: None,
+            states: vec![],
+        };
+        for (field, _op, value) in fields.fields() {
+            match field.read_str().as_ref() {
+                "map_color" => region.color = ColorConverter::read_color(&value),
+                "capital_province" => {
+                    region.capital_province = value.read_string().ok().map(|p| province_key(&p))
+                }
+                "states" => {
+                    if let Ok(states) = value.read_array() {
+                        region.states = states
+                            .values()
+                            .filter_map(|state| state.read_string().ok())
+                            .map(|state| state_key(&state))
+                            .collect();
+                    }
+                }
+                _ => {}
+            }
+        }
+        regions.push(region);
+    }
+
+    regions
+}
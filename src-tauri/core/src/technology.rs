@@ -1,10 +1,9 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::collections::HashMap;
 
 use jomini::text::de::from_utf8_reader;
 use serde::{Deserialize, Serialize};
-use tauri::{Manager, Window};
 
-use crate::cache_config::CacheConfig;
+use crate::mod_stack::ModStack;
 
 const TECHNOLOGIES_PATH: &str = "common/technology/technologies";
 
@@ -22,11 +21,10 @@ struct RawTechnology {
 }
 
 impl Technology {
-    fn parse_from(path: PathBuf) -> Vec<Technology> {
+    pub fn parse_from(mod_stack: &ModStack) -> Vec<Technology> {
         let mut technologies: Vec<Technology> = Vec::new();
 
-        for entry in std::fs::read_dir(path.join(TECHNOLOGIES_PATH)).unwrap() {
-            let entry = entry.unwrap().path();
+        for entry in mod_stack.files_in(TECHNOLOGIES_PATH) {
             if entry.extension().unwrap() != "txt" {
                 continue;
             };
@@ -45,11 +43,4 @@ impl Technology {
 
         technologies
     }
-
-    pub fn parse_from_game_folder(window: Window) -> Vec<Technology> {
-        let cache_dir = window.app_handle().path().app_cache_dir().unwrap();
-        let cache_config: CacheConfig = CacheConfig::get_config(cache_dir.join("config.json"));
-
-        Self::parse_from(cache_config.game_folder.unwrap().join("game"))
-    }
 }
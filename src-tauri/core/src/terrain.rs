@@ -0,0 +1,209 @@
+use image::{imageops, imageops::FilterType, GrayImage, ImageBuffer, Luma, Rgba, RgbaImage};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tracing::{instrument, warn};
+
+use crate::{
+    background::{available_threads, TILES_DIR_NAME},
+    province_index::ProvinceIndex,
+    province_types::{ProvinceKind, ProvinceTypes},
+    tiles::TilePyramid,
+};
+
+pub const RELIEF_FILE_NAME: &str = "terrain.png";
+pub const HILLSHADE_FILE_NAME: &str = "terrain-hillshade.png";
+pub const TERRAIN_TYPES_FILE_NAME: &str = "terrain-types.png";
+
+// Light from the north west, halfway up the sky, as relief maps are usually lit.
+const SUN_AZIMUTH: f32 = 315.0;
+const SUN_ALTITUDE: f32 = 45.0;
+// How many pixels of the map the full range of the heightmap rises over. Higher exaggerates
+// the relief.
+const HEIGHT_SCALE: f32 = 24.0;
+// How dark the steepest shadow gets in the relief, so terrain colours still show through.
+const SHADOW_FLOOR: f32 = 0.35;
+
+const WATER_COLOR: [u8; 3] = [107, 145, 179];
+const IMPASSABLE_COLOR: [u8; 3] = [120, 110, 100];
+const UNKNOWN_TERRAIN_COLOR: [u8; 3] = [190, 190, 170];
+
+/// Relief for judging whether borders follow the land: each province in the colour of its
+/// terrain from `province_terrains.txt`, shaded by the slopes of `map_data/heightmap.png`.
+/// The hillshade and the terrain colours are cached on their own too.
+#[derive(Serialize, Debug, Clone)]
+pub struct TerrainLayer {
+    pub relief: PathBuf,
+    pub hillshade: PathBuf,
+    pub terrain_types: PathBuf,
+    pub tiles: Option<TilePyramid>,
+}
+
+impl TerrainLayer {
+    pub fn cached(cache_dir: &Path) -> Option<TerrainLayer> {
+        let layer = TerrainLayer::in_dir(cache_dir);
+        if !layer.relief.exists() {
+            return None;
+        }
+        Some(TerrainLayer {
+            tiles: TilePyramid::cached(&cache_dir.join(TILES_DIR_NAME), &pyramid_name()),
+            ..layer
+        })
+    }
+
+    /// Renders the layer into `cache_dir` and tiles the relief for the map. Without a
+    /// heightmap the relief is left flat.
+    #[instrument(skip(province_index, province_types))]
+    pub fn render(
+        heightmap: &Path,
+        province_index: &ProvinceIndex,
+        province_types: &ProvinceTypes,
+        cache_dir: &Path,
+    ) -> TerrainLayer {
+        let (width, height) = province_index.dimensions();
+        let (hillshade, terrain_types) = std::thread::scope(|scope| {
+            let hillshade = scope.spawn(|| match read_heightmap(heightmap, width, height) {
+                Some(heights) => hillshade(&heights),
+                None => {
+                    warn!(?heightmap, "No heightmap, the relief will be flat");
+                    GrayImage::from_pixel(width, height, Luma([255]))
+                }
+            });
+            let terrain_types = scope.spawn(|| terrain_types(province_index, province_types));
+            (hillshade.join().unwrap(), terrain_types.join().unwrap())
+        });
+
+        let mut relief = terrain_types.clone();
+        for (pixel, shade) in relief.pixels_mut().zip(hillshade.pixels()) {
+            let light = SHADOW_FLOOR + (1.0 - SHADOW_FLOOR) * shade[0] as f32 / 255.0;
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] as f32 * light).round() as u8;
+            }
+        }
+
+        let layer = TerrainLayer::in_dir(cache_dir);
+        std::thread::scope(|scope| {
+            scope.spawn(|| hillshade.save(&layer.hillshade).unwrap());
+            scope.spawn(|| terrain_types.save(&layer.terrain_types).unwrap());
+        });
+        let tiles = TilePyramid::build(
+            &relief,
+            (width, height),
+            &cache_dir.join(TILES_DIR_NAME),
+            &pyramid_name(),
+        );
+        relief.save(&layer.relief).unwrap();
+        TerrainLayer {
+            tiles: Some(tiles),
+            ..layer
+        }
+    }
+
+    fn in_dir(cache_dir: &Path) -> TerrainLayer {
+        TerrainLayer {
+            relief: cache_dir.join(RELIEF_FILE_NAME),
+            hillshade: cache_dir.join(HILLSHADE_FILE_NAME),
+            terrain_types: cache_dir.join(TERRAIN_TYPES_FILE_NAME),
+            tiles: None,
+        }
+    }
+}
+
+fn pyramid_name() -> String {
+    Path::new(RELIEF_FILE_NAME)
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// The colour the relief gives a terrain, named as in `province_terrains.txt`.
+pub fn terrain_color(terrain: &str) -> [u8; 3] {
+    match terrain {
+        "plains" => [183, 201, 120],
+        "farmlands" => [205, 212, 126],
+        "forest" => [84, 130, 72],
+        "jungle" => [52, 110, 64],
+        "hills" => [176, 160, 110],
+        "mountain" => [150, 132, 118],
+        "snow" => [236, 240, 244],
+        "tundra" => [170, 180, 168],
+        "desert" => [226, 205, 150],
+        "savanna" => [206, 190, 112],
+        "wetland" => [110, 150, 120],
+        "ocean" | "lakes" => WATER_COLOR,
+        _ => UNKNOWN_TERRAIN_COLOR,
+    }
+}
+
+// Scaled to the province map if the heightmap is another size.
+fn read_heightmap(
+    path: &Path,
+    width: u32,
+    height: u32,
+) -> Option<ImageBuffer<Luma<u16>, Vec<u16>>> {
+    let heights = image::open(path).ok()?.into_luma16();
+    match heights.dimensions() == (width, height) {
+        true => Some(heights),
+        false => Some(imageops::resize(
+            &heights,
+            width,
+            height,
+            FilterType::Triangle,
+        )),
+    }
+}
+
+// Horn's method: the slope and aspect of each pixel from its eight neighbours, lit by the sun.
+fn hillshade(heights: &ImageBuffer<Luma<u16>, Vec<u16>>) -> GrayImage {
+    let (width, height) = heights.dimensions();
+    let zenith = (90.0 - SUN_ALTITUDE).to_radians();
+    let azimuth = (360.0 - SUN_AZIMUTH + 90.0).to_radians();
+    let elevation = |x: i64, y: i64| {
+        let x = x.clamp(0, width as i64 - 1) as u32;
+        let y = y.clamp(0, height as i64 - 1) as u32;
+        heights.get_pixel(x, y)[0] as f32 / u16::MAX as f32 * HEIGHT_SCALE
+    };
+
+    let mut shade = GrayImage::new(width, height);
+    let rows = height.div_ceil(available_threads()).max(1) as usize;
+    std::thread::scope(|scope| {
+        for (index, chunk) in shade.chunks_mut(rows * width as usize).enumerate() {
+            scope.spawn(move || {
+                for (offset, value) in chunk.iter_mut().enumerate() {
+                    let x = (offset % width as usize) as i64;
+                    let y = (index * rows + offset / width as usize) as i64;
+                    let z = |dx: i64, dy: i64| elevation(x + dx, y + dy);
+                    let dz_dx = ((z(1, -1) + 2.0 * z(1, 0) + z(1, 1))
+                        - (z(-1, -1) + 2.0 * z(-1, 0) + z(-1, 1)))
+                        / 8.0;
+                    let dz_dy = ((z(-1, 1) + 2.0 * z(0, 1) + z(1, 1))
+                        - (z(-1, -1) + 2.0 * z(0, -1) + z(1, -1)))
+                        / 8.0;
+                    let slope = (dz_dx.powi(2) + dz_dy.powi(2)).sqrt().atan();
+                    let aspect = dz_dy.atan2(-dz_dx);
+                    let light = zenith.cos() * slope.cos()
+                        + zenith.sin() * slope.sin() * (azimuth - aspect).cos();
+                    // Flat ground is as bright as the sun's altitude allows, rescaled to full.
+                    *value = ((light / zenith.cos()).clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+            });
+        }
+    });
+    shade
+}
+
+fn terrain_types(province_index: &ProvinceIndex, province_types: &ProvinceTypes) -> RgbaImage {
+    let (width, height) = province_index.dimensions();
+    RgbaImage::from_fn(width, height, |x, y| {
+        let province = province_index.province_of(x, y);
+        let [r, g, b] = match province_types.kind(province) {
+            ProvinceKind::Sea | ProvinceKind::Lake => WATER_COLOR,
+            kind => match province_types.terrain(province) {
+                Some(terrain) => terrain_color(terrain),
+                None if kind == ProvinceKind::Impassable => IMPASSABLE_COLOR,
+                None => UNKNOWN_TERRAIN_COLOR,
+            },
+        };
+        Rgba([r, g, b, 255])
+    })
+}
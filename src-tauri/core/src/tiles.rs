@@ -0,0 +1,116 @@
+use image::{imageops, imageops::FilterType, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::instrument;
+
+pub const TILE_SIZE: u32 = 256;
+const PYRAMID_FILE_NAME: &str = "pyramid.json";
+
+/// An image cut into tiles at every zoom level from the map's extent down to a single tile,
+/// written as `{name}/{z}/{x}/{y}.png`.
+///
+/// The grid is the one Leaflet requests with `CRS.Simple`, where a map unit is a pixel of the
+/// province map at zoom 0 and halves with each zoom level below it. Its origin is the bottom left
+/// corner of the image, so x runs right from 0 and y runs up from -1.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TilePyramid {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub min_zoom: i32,
+    pub max_zoom: i32,
+    pub tile_size: u32,
+}
+
+impl TilePyramid {
+    /// The pyramid named `name` in `tiles_dir`, if every tile of it has been written.
+    pub fn cached(tiles_dir: &Path, name: &str) -> Option<TilePyramid> {
+        let pyramid = std::fs::read_to_string(tiles_dir.join(name).join(PYRAMID_FILE_NAME)).ok()?;
+        serde_json::from_str(&pyramid).ok()
+    }
+
+    /// Tiles `image` into `tiles_dir`, scaled to `extent` at zoom 0 and each level halved from
+    /// the one above it. The pyramid's description is written last, so it is only cached once
+    /// it is whole.
+    #[instrument(skip(image))]
+    pub fn build(
+        image: &RgbaImage,
+        extent: (u32, u32),
+        tiles_dir: &Path,
+        name: &str,
+    ) -> TilePyramid {
+        let (width, height) = extent;
+        let mut min_zoom = 0;
+        while (width.max(height) >> -min_zoom) > TILE_SIZE {
+            min_zoom -= 1;
+        }
+        let pyramid = TilePyramid {
+            name: name.to_string(),
+            width,
+            height,
+            min_zoom,
+            max_zoom: 0,
+            tile_size: TILE_SIZE,
+        };
+
+        let mut level = match image.dimensions() == extent {
+            true => image.clone(),
+            false => imageops::resize(image, width, height, FilterType::Triangle),
+        };
+        for zoom in (min_zoom..=0).rev() {
+            if zoom < 0 {
+                level = imageops::resize(
+                    &level,
+                    level.width().div_ceil(2),
+                    level.height().div_ceil(2),
+                    FilterType::Triangle,
+                );
+            }
+            pyramid.write_level(&level, tiles_dir, zoom);
+        }
+
+        std::fs::write(
+            tiles_dir.join(name).join(PYRAMID_FILE_NAME),
+            serde_json::to_string(&pyramid).unwrap(),
+        )
+        .unwrap();
+        pyramid
+    }
+
+    pub fn tile_path(tiles_dir: &Path, name: &str, zoom: i32, x: i32, y: i32) -> PathBuf {
+        tiles_dir
+            .join(name)
+            .join(zoom.to_string())
+            .join(x.to_string())
+            .join(format!("{}.png", y))
+    }
+
+    // Tiles along the top and right edges are padded with transparency.
+    fn write_level(&self, level: &RgbaImage, tiles_dir: &Path, zoom: i32) {
+        let columns = level.width().div_ceil(TILE_SIZE) as i32;
+        let rows = level.height().div_ceil(TILE_SIZE) as i32;
+        std::thread::scope(|scope| {
+            for x in 0..columns {
+                scope.spawn(move || {
+                    let column_dir = tiles_dir.join(&self.name).join(zoom.to_string());
+                    std::fs::create_dir_all(column_dir.join(x.to_string())).unwrap();
+                    for y in -rows..0 {
+                        // The tile's top edge, in rows of the level from its top.
+                        let top = level.height() as i64 + y as i64 * TILE_SIZE as i64;
+                        let mut tile = RgbaImage::new(TILE_SIZE, TILE_SIZE);
+                        let source = imageops::crop_imm(
+                            level,
+                            x as u32 * TILE_SIZE,
+                            top.max(0) as u32,
+                            TILE_SIZE,
+                            TILE_SIZE - (-top).max(0) as u32,
+                        );
+                        imageops::replace(&mut tile, &*source, 0, (-top).max(0));
+                        tile.save(TilePyramid::tile_path(tiles_dir, &self.name, zoom, x, y))
+                            .unwrap();
+                    }
+                });
+            }
+        });
+    }
+}
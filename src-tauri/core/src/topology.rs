@@ -0,0 +1,730 @@
+use image::{ImageReader, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
+use tracing::{instrument, warn};
+
+use crate::{province_map_to_geojson::Coords, province_types::ProvinceInfo};
+
+pub type Point = (i32, i32);
+
+// TopoJSON's arc references: `!index`, which is `-index - 1`, is the arc walked backwards.
+pub type ArcRef = i32;
+
+// Polygons as rings of arcs, each outer ring followed by its holes.
+pub type ArcPolygon = Vec<Vec<ArcRef>>;
+
+// Provinces a cached outline was dissolved from, sorted, and the outline.
+type Dissolved = (Vec<String>, Vec<ArcPolygon>);
+
+/// Province borders as arcs shared between the two provinces on either side of them, so
+/// neighbours always line up exactly. Points are pixel corners with y running up.
+#[derive(Default)]
+pub struct Topology {
+    pub arcs: Vec<Vec<Point>>,
+    pub provinces: HashMap<String, Vec<ArcPolygon>>,
+    // Worked out the first time anything is dissolved, the arcs never change after that.
+    pub(crate) arc_sides: OnceLock<Vec<[Option<String>; 2]>>,
+    // The last outline dissolved for each key with the provinces it was dissolved from, so a
+    // transfer only dissolves the countries and states whose provinces changed.
+    pub(crate) dissolved: Mutex<HashMap<String, Dissolved>>,
+}
+
+impl Topology {
+    #[instrument(skip_all, fields(map = ?province_map))]
+    pub fn from_province_map(province_map: PathBuf) -> Topology {
+        let provinces = ImageReader::open(province_map)
+            .unwrap()
+            .decode()
+            .unwrap()
+            .into_rgb8();
+        Self::from_provinces(&provinces)
+    }
+
+    fn from_provinces(provinces: &RgbImage) -> Topology {
+        let mut province_edges: HashMap<Rgb<u8>, BorderEdges> = HashMap::new();
+        let height = provinces.height() as i32;
+
+        for (x, y, pixel) in provinces.enumerate_pixels() {
+            let (x, y) = (x as i32, y as i32);
+            // The map is flipped so y runs up, which makes the pixel span bottom to top.
+            let (bottom, top) = (height - y - 1, height - y);
+
+            let sides = [
+                ((x, y + 1), (x, bottom), (x + 1, bottom)),
+                ((x + 1, y), (x + 1, bottom), (x + 1, top)),
+                ((x, y - 1), (x + 1, top), (x, top)),
+                ((x - 1, y), (x, top), (x, bottom)),
+            ];
+
+            for ((neighbor_x, neighbor_y), from, to) in sides {
+                if pixel_at(provinces, neighbor_x, neighbor_y) != Some(pixel) {
+                    province_edges.entry(*pixel).or_default().push(from, to);
+                }
+            }
+        }
+
+        let mut topology = Topology::default();
+        let mut arc_starts: HashMap<(Point, Point), usize> = HashMap::new();
+
+        for (pixel, edges) in province_edges {
+            let province_id = format!("x{:02X}{:02X}{:02X}", pixel[0], pixel[1], pixel[2]);
+            let rings = trace_rings(edges)
+                .into_iter()
+                .map(|ring| {
+                    let arc_refs = topology.split_into_arcs(provinces, &mut arc_starts, &ring);
+                    (ring, arc_refs)
+                })
+                .collect();
+            topology
+                .provinces
+                .insert(province_id, group_into_polygons(rings));
+        }
+
+        topology
+    }
+
+    // Splits a ring at every corner where more than two provinces meet, reusing the arcs the
+    // neighbour on the other side already traced in the opposite direction.
+    fn split_into_arcs(
+        &mut self,
+        provinces: &RgbImage,
+        arc_starts: &mut HashMap<(Point, Point), usize>,
+        ring: &[Point],
+    ) -> Vec<ArcRef> {
+        let height = provinces.height() as i32;
+        let corners = &ring[..ring.len() - 1];
+        let junctions: Vec<usize> = (0..corners.len())
+            .filter(|index| is_junction(provinces, height, corners[*index]))
+            .collect();
+
+        // Rings without junctions border a single neighbour all the way round, so both sides
+        // start from the same corner to trace the same arc.
+        let start = match junctions.first() {
+            Some(start) => *start,
+            None => (0..corners.len())
+                .min_by_key(|index| corners[*index])
+                .unwrap(),
+        };
+        let mut rotated: Vec<Point> = corners[start..]
+            .iter()
+            .chain(&corners[..start])
+            .copied()
+            .collect();
+        rotated.push(rotated[0]);
+        let junctions: HashSet<usize> = junctions
+            .iter()
+            .map(|index| (index + corners.len() - start) % corners.len())
+            .collect();
+
+        let mut arc_refs = vec![];
+        let mut arc = vec![rotated[0]];
+        for (index, point) in rotated.iter().enumerate().skip(1) {
+            arc.push(*point);
+            if index == rotated.len() - 1 || junctions.contains(&index) {
+                arc_refs.push(self.arc_ref(arc_starts, std::mem::replace(&mut arc, vec![*point])));
+            }
+        }
+        arc_refs
+    }
+
+    fn arc_ref(
+        &mut self,
+        arc_starts: &mut HashMap<(Point, Point), usize>,
+        arc: Vec<Point>,
+    ) -> ArcRef {
+        let reversed_start = (arc[arc.len() - 1], arc[arc.len() - 2]);
+        if let Some(index) = arc_starts.get(&reversed_start) {
+            return !(*index as ArcRef);
+        }
+        arc_starts.insert((arc[0], arc[1]), self.arcs.len());
+        self.arcs.push(remove_unnecessary_coords(arc));
+        self.arcs.len() as ArcRef - 1
+    }
+
+    fn arc_points(&self, arc_ref: ArcRef) -> Vec<Point> {
+        match arc_ref >= 0 {
+            true => self.arcs[arc_ref as usize].clone(),
+            false => self.arcs[!arc_ref as usize].iter().rev().copied().collect(),
+        }
+    }
+
+    fn ring_points(&self, arc_refs: &[ArcRef]) -> Vec<Point> {
+        let mut points = vec![];
+        for arc_ref in arc_refs {
+            let arc = self.arc_points(*arc_ref);
+            let skip = usize::from(!points.is_empty());
+            points.extend(arc.into_iter().skip(skip));
+        }
+        points
+    }
+
+    /// The far corner of the map, from the arcs along its edges.
+    pub fn extent(&self) -> Point {
+        self.arcs.iter().flatten().fold((0, 0), |extent, point| {
+            (extent.0.max(point.0), extent.1.max(point.1))
+        })
+    }
+
+    pub fn polygons_to_coords(&self, polygons: &[ArcPolygon]) -> Coords {
+        polygons
+            .iter()
+            .flatten()
+            .map(|arc_refs| {
+                self.ring_points(arc_refs)
+                    .into_iter()
+                    .map(|(x, y)| (x as f32, y as f32))
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn province_coords(&self) -> HashMap<String, Coords> {
+        self.provinces
+            .iter()
+            .map(|(province, polygons)| (province.clone(), self.polygons_to_coords(polygons)))
+            .collect()
+    }
+
+    /// Outlines of groups of provinces, such as states or countries, made of the arcs between
+    /// provinces of different groups. A group dissolved before under the same key from the same
+    /// provinces is reused.
+    pub fn dissolve<'a>(
+        &self,
+        groups: impl IntoIterator<Item = (String, &'a [String])>,
+    ) -> HashMap<String, Vec<ArcPolygon>> {
+        let mut dissolved = self.dissolved.lock().unwrap();
+
+        groups
+            .into_iter()
+            .map(|(key, provinces)| {
+                let mut sorted = provinces.to_vec();
+                sorted.sort();
+                if let Some((cached, polygons)) = dissolved.get(&key) {
+                    if *cached == sorted {
+                        return (key, polygons.clone());
+                    }
+                }
+                let polygons = self.dissolve_group(&sorted);
+                dissolved.insert(key.clone(), (sorted, polygons.clone()));
+                (key, polygons)
+            })
+            .collect()
+    }
+
+    fn dissolve_group(&self, provinces: &[String]) -> Vec<ArcPolygon> {
+        let arc_sides = self.arc_sides();
+        let provinces: HashSet<&str> = provinces.iter().map(String::as_str).collect();
+        let border: Vec<ArcRef> = provinces
+            .iter()
+            .filter_map(|province| self.provinces.get(*province))
+            .flatten()
+            .flatten()
+            .flatten()
+            .copied()
+            .filter(|arc_ref| {
+                let other_side = match *arc_ref >= 0 {
+                    true => &arc_sides[*arc_ref as usize][1],
+                    false => &arc_sides[!*arc_ref as usize][0],
+                };
+                !other_side
+                    .as_deref()
+                    .is_some_and(|province| provinces.contains(province))
+            })
+            .collect();
+        self.stitch(border)
+    }
+
+    /// The province on the left and on the right of each arc, if it is not the edge of the map.
+    pub fn arc_sides(&self) -> &[[Option<String>; 2]] {
+        self.arc_sides.get_or_init(|| {
+            let mut arc_sides: Vec<[Option<String>; 2]> = vec![[None, None]; self.arcs.len()];
+            for (province, polygons) in self.provinces.iter() {
+                for arc_ref in polygons.iter().flatten().flatten() {
+                    match *arc_ref >= 0 {
+                        true => arc_sides[*arc_ref as usize][0] = Some(province.clone()),
+                        false => arc_sides[!*arc_ref as usize][1] = Some(province.clone()),
+                    }
+                }
+            }
+            arc_sides
+        })
+    }
+
+    // Joins border arcs end to start into rings, turning left at junctions the same way
+    // province rings are traced.
+    fn stitch(&self, border: Vec<ArcRef>) -> Vec<ArcPolygon> {
+        let mut arcs_from: HashMap<Point, Vec<(ArcRef, Vec<Point>)>> = HashMap::new();
+        for arc_ref in border {
+            let points = self.arc_points(arc_ref);
+            arcs_from
+                .entry(points[0])
+                .or_default()
+                .push((arc_ref, points));
+        }
+
+        let mut rings = vec![];
+        while let Some(start) = arcs_from.keys().min().copied() {
+            let mut steps = vec![];
+            let mut current = start;
+            let mut direction = (0, 0);
+            loop {
+                let Some(candidates) = arcs_from.get_mut(&current) else {
+                    warn!(corner = ?current, "Dissolved border does not close");
+                    break;
+                };
+                let (dx, dy) = direction;
+                let index = [(-dy, dx), (dx, dy), (dy, -dx)]
+                    .iter()
+                    .find_map(|turn| {
+                        candidates
+                            .iter()
+                            .position(|(_, points)| step_direction(points[0], points[1]) == *turn)
+                    })
+                    .unwrap_or(0);
+                let (arc_ref, points) = candidates.swap_remove(index);
+                if candidates.is_empty() {
+                    arcs_from.remove(&current);
+                }
+                current = points[points.len() - 1];
+                direction = step_direction(points[points.len() - 2], current);
+                steps.push((arc_ref, current));
+                if current == start {
+                    break;
+                }
+            }
+
+            for walk in split_at_touches(start, steps) {
+                let arc_refs: Vec<ArcRef> = walk.into_iter().map(|(arc_ref, _)| arc_ref).collect();
+                rings.push((self.ring_points(&arc_refs), arc_refs));
+            }
+        }
+
+        group_into_polygons(rings)
+    }
+}
+
+fn pixel_at(image: &RgbImage, x: i32, y: i32) -> Option<&Rgb<u8>> {
+    match x < 0 || y < 0 || x >= image.width() as i32 || y >= image.height() as i32 {
+        true => None,
+        false => Some(image.get_pixel(x as u32, y as u32)),
+    }
+}
+
+// A corner where three or more provinces, counting the edge of the map, meet, or where two
+// touch diagonally.
+fn is_junction(provinces: &RgbImage, height: i32, corner: Point) -> bool {
+    let (x, y) = (corner.0, height - corner.1);
+    let around = [
+        pixel_at(provinces, x - 1, y - 1),
+        pixel_at(provinces, x, y - 1),
+        pixel_at(provinces, x, y),
+        pixel_at(provinces, x - 1, y),
+    ];
+    let mut distinct = vec![];
+    for pixel in around {
+        if !distinct.contains(&pixel) {
+            distinct.push(pixel);
+        }
+    }
+    distinct.len() > 2
+        || (around[0] == around[2] && around[1] == around[3] && around[0] != around[1])
+}
+
+fn step_direction(from: Point, to: Point) -> Point {
+    ((to.0 - from.0).signum(), (to.1 - from.1).signum())
+}
+
+// Boundary edges of a province between pixel corners, directed so the province is on their
+// left. Outer rings run counter-clockwise and holes clockwise.
+#[derive(Default)]
+struct BorderEdges {
+    edges: HashMap<Point, Vec<Point>>,
+}
+
+impl BorderEdges {
+    fn push(&mut self, from: Point, to: Point) {
+        self.edges.entry(from).or_default().push(to);
+    }
+
+    // Prefers turning left, so provinces touching themselves diagonally are traced as separate
+    // rings rather than crossing over at the shared corner.
+    fn take_next(&mut self, from: Point, direction: Point) -> Option<Point> {
+        let ends = self.edges.get_mut(&from)?;
+        let (dx, dy) = direction;
+        let turns = [(-dy, dx), (dx, dy), (dy, -dx)];
+        let index = turns
+            .iter()
+            .find_map(|(tx, ty)| {
+                ends.iter()
+                    .position(|end| *end == (from.0 + tx, from.1 + ty))
+            })
+            .unwrap_or(0);
+        let end = ends.swap_remove(index);
+        if ends.is_empty() {
+            self.edges.remove(&from);
+        }
+        Some(end)
+    }
+
+    fn take_any(&mut self) -> Option<(Point, Point)> {
+        let from = *self.edges.keys().min()?;
+        let end = self.take_next(from, (0, 0))?;
+        Some((from, end))
+    }
+}
+
+// Every corner has as many edges leaving as arriving, so walking edges always gets back to
+// where it started.
+fn trace_rings(mut edges: BorderEdges) -> Vec<Vec<Point>> {
+    let mut rings = vec![];
+
+    while let Some((start, second)) = edges.take_any() {
+        let mut steps = vec![((), second)];
+        let mut current = second;
+        let mut previous = start;
+        while current != start {
+            let Some(next) = edges.take_next(current, step_direction(previous, current)) else {
+                warn!(corner = ?current, "Province border does not close");
+                break;
+            };
+            steps.push(((), next));
+            (previous, current) = (current, next);
+        }
+
+        for walk in split_at_touches(start, steps) {
+            let mut ring = vec![walk[walk.len() - 1].1];
+            ring.extend(walk.into_iter().map(|(_, point)| point));
+            rings.push(ring);
+        }
+    }
+
+    rings
+}
+
+// A closed walk that passes the same corner twice is split there into closed walks that only
+// touch at a point, such as a hole that meets the outer ring at a corner. Each step is paired
+// with the corner it leads to.
+fn split_at_touches<T>(start: Point, steps: Vec<(T, Point)>) -> Vec<Vec<(T, Point)>> {
+    let mut walks = vec![];
+    let mut stack: Vec<(T, Point)> = vec![];
+    let mut positions: HashMap<Point, usize> = HashMap::from([(start, 0)]);
+
+    for (step, point) in steps {
+        stack.push((step, point));
+        if let Some(&position) = positions.get(&point) {
+            let walk = stack.split_off(position);
+            for (_, passed) in walk.iter() {
+                positions.remove(passed);
+            }
+            walks.push(walk);
+        }
+        positions.insert(point, stack.len());
+    }
+
+    walks
+}
+
+fn remove_unnecessary_coords(line: Vec<Point>) -> Vec<Point> {
+    let mut corners = vec![line[0]];
+    for window in line.windows(3) {
+        if step_direction(window[0], window[1]) != step_direction(window[1], window[2]) {
+            corners.push(window[1]);
+        }
+    }
+    corners.push(line[line.len() - 1]);
+    corners
+}
+
+// Twice the signed area, positive for counter-clockwise rings.
+fn doubled_area(ring: &[Point]) -> i64 {
+    ring.windows(2)
+        .map(|pair| pair[0].0 as i64 * pair[1].1 as i64 - pair[1].0 as i64 * pair[0].1 as i64)
+        .sum()
+}
+
+// Ray casts from a point that is never on a pixel edge, so there are no ties to break.
+fn contains(ring: &[Point], point: (f32, f32)) -> bool {
+    let mut inside = false;
+    for pair in ring.windows(2) {
+        let ((x1, y1), (x2, y2)) = (
+            (pair[0].0 as f32, pair[0].1 as f32),
+            (pair[1].0 as f32, pair[1].1 as f32),
+        );
+        if (y1 > point.1) != (y2 > point.1) && point.0 < x1 + (point.1 - y1) / (y2 - y1) * (x2 - x1)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// A ring's points and what it stands for.
+type Ring<T> = (Vec<Point>, T);
+
+// Orders rings as each outer ring followed by its holes. A hole belongs to the smallest outer
+// ring around it.
+fn group_into_polygons<T>(rings: Vec<Ring<T>>) -> Vec<Vec<T>> {
+    let (outers, holes): (Vec<Ring<T>>, Vec<Ring<T>>) = rings
+        .into_iter()
+        .partition(|(ring, _)| doubled_area(ring) > 0);
+    let mut polygons: Vec<(Vec<Point>, Vec<T>)> = outers
+        .into_iter()
+        .map(|(ring, outer)| (ring, vec![outer]))
+        .collect();
+
+    for (hole, value) in holes {
+        // The hole is on the right of its edges.
+        let (from, to) = (hole[0], hole[1]);
+        let (dx, dy) = step_direction(from, to);
+        let inside_hole = (
+            (from.0 + to.0) as f32 / 2.0 + dy as f32 * 0.25,
+            (from.1 + to.1) as f32 / 2.0 - dx as f32 * 0.25,
+        );
+        let outer = polygons
+            .iter_mut()
+            .filter(|(outer, _)| contains(outer, inside_hole))
+            .min_by_key(|(outer, _)| doubled_area(outer));
+        match outer {
+            Some((_, polygon)) => polygon.push(value),
+            None => warn!(corner = ?from, "Hole outside of every outer ring"),
+        }
+    }
+
+    polygons.into_iter().map(|(_, polygon)| polygon).collect()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TopoJson {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub transform: Transform,
+    pub arcs: Vec<Vec<[i32; 2]>>,
+    pub objects: HashMap<String, GeometryCollection>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Transform {
+    pub scale: [f32; 2],
+    pub translate: [f32; 2],
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GeometryCollection {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub geometries: Vec<Geometry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Geometry {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub id: String,
+    pub arcs: Vec<ArcPolygon>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<ProvinceInfo>,
+}
+
+impl GeometryCollection {
+    pub fn new(polygons: HashMap<String, Vec<ArcPolygon>>) -> GeometryCollection {
+        GeometryCollection {
+            type_: "GeometryCollection".to_string(),
+            geometries: polygons
+                .into_iter()
+                .map(|(id, arcs)| Geometry {
+                    type_: "MultiPolygon".to_string(),
+                    id,
+                    arcs,
+                    properties: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Topology {
+    /// The topology as TopoJSON, with the provinces and any `objects` given, such as dissolved
+    /// states. Corners are whole pixels already, so arcs are only delta encoded.
+    pub fn to_topojson(&self, objects: HashMap<String, GeometryCollection>) -> TopoJson {
+        let arcs = self
+            .arcs
+            .iter()
+            .map(|arc| {
+                let mut previous = (0, 0);
+                arc.iter()
+                    .map(|point| {
+                        let delta = [point.0 - previous.0, point.1 - previous.1];
+                        previous = *point;
+                        delta
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut objects = objects;
+        objects.insert(
+            "provinces".to_string(),
+            GeometryCollection::new(self.provinces.clone()),
+        );
+
+        TopoJson {
+            type_: "Topology".to_string(),
+            transform: Transform {
+                scale: [1.0, 1.0],
+                translate: [0.0, 0.0],
+            },
+            arcs,
+            objects,
+        }
+    }
+
+    pub fn from_topojson(topojson: TopoJson) -> Topology {
+        let arcs = topojson
+            .arcs
+            .into_iter()
+            .map(|arc| {
+                let mut position = (0, 0);
+                arc.into_iter()
+                    .map(|[dx, dy]| {
+                        position = (position.0 + dx, position.1 + dy);
+                        position
+                    })
+                    .collect()
+            })
+            .collect();
+        let provinces = topojson
+            .objects
+            .into_iter()
+            .find(|(name, _)| name == "provinces")
+            .map(|(_, collection)| {
+                collection
+                    .geometries
+                    .into_iter()
+                    .map(|geometry| (geometry.id, geometry.arcs))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Topology {
+            arcs,
+            provinces,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One pixel per character, each character its own province.
+    fn fixture(rows: &[&str]) -> RgbImage {
+        RgbImage::from_fn(rows[0].len() as u32, rows.len() as u32, |x, y| {
+            Rgb([rows[y as usize].as_bytes()[x as usize], 0, 0])
+        })
+    }
+
+    fn key(province: char) -> String {
+        format!("x{:02X}0000", province as u8)
+    }
+
+    fn polygons(topology: &Topology, province: char) -> &Vec<ArcPolygon> {
+        topology.provinces.get(&key(province)).unwrap()
+    }
+
+    fn ring_area(topology: &Topology, ring: &[ArcRef]) -> i64 {
+        doubled_area(&topology.ring_points(ring))
+    }
+
+    fn corners(topology: &Topology, ring: &[ArcRef]) -> HashSet<Point> {
+        topology.ring_points(ring).into_iter().collect()
+    }
+
+    // Every polygon is an outer ring running counter-clockwise followed by holes running
+    // clockwise.
+    fn assert_oriented(topology: &Topology) {
+        for (province, polygons) in topology.provinces.iter() {
+            for polygon in polygons {
+                assert!(
+                    ring_area(topology, &polygon[0]) > 0,
+                    "{} outer ring",
+                    province
+                );
+                for hole in &polygon[1..] {
+                    assert!(ring_area(topology, hole) < 0, "{} hole", province);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn enclave_is_a_hole() {
+        let topology = Topology::from_provinces(&fixture(&["AAA", "ABA", "AAA"]));
+        assert_oriented(&topology);
+
+        let outer = polygons(&topology, 'A');
+        assert_eq!(outer.len(), 1);
+        assert_eq!(outer[0].len(), 2);
+        let enclave = polygons(&topology, 'B');
+        assert_eq!(enclave.len(), 1);
+        assert_eq!(enclave[0].len(), 1);
+        assert_eq!(
+            corners(&topology, &outer[0][1]),
+            corners(&topology, &enclave[0][0])
+        );
+    }
+
+    #[test]
+    fn hole_pinched_against_the_outer_ring() {
+        // The enclave touches the corner notch diagonally, so A's border passes that corner
+        // twice.
+        let topology = Topology::from_provinces(&fixture(&["AAA", "ABA", "AAC"]));
+        assert_oriented(&topology);
+
+        let pinched = polygons(&topology, 'A');
+        assert_eq!(pinched.len(), 1);
+        assert_eq!(pinched[0].len(), 2);
+        assert_eq!(
+            corners(&topology, &pinched[0][1]),
+            corners(&topology, &polygons(&topology, 'B')[0][0])
+        );
+        assert_eq!(polygons(&topology, 'C').len(), 1);
+    }
+
+    #[test]
+    fn diagonal_touch_splits_polygons() {
+        let topology = Topology::from_provinces(&fixture(&["AB", "BA"]));
+        assert_oriented(&topology);
+
+        for province in ['A', 'B'] {
+            let diagonal = polygons(&topology, province);
+            assert_eq!(diagonal.len(), 2);
+            assert!(diagonal.iter().all(|polygon| polygon.len() == 1));
+        }
+    }
+
+    #[test]
+    fn holes_go_with_the_polygon_around_them() {
+        let topology = Topology::from_provinces(&fixture(&["AAACAAA", "ABACADA", "AAACAAA"]));
+        assert_oriented(&topology);
+
+        let split = polygons(&topology, 'A');
+        assert_eq!(split.len(), 2);
+        for enclave in ['B', 'D'] {
+            let enclave_corners = corners(&topology, &polygons(&topology, enclave)[0][0]);
+            let around: Vec<&ArcPolygon> = split
+                .iter()
+                .filter(|polygon| {
+                    polygon[1..]
+                        .iter()
+                        .any(|hole| corners(&topology, hole) == enclave_corners)
+                })
+                .collect();
+            assert_eq!(around.len(), 1);
+            assert_eq!(around[0].len(), 2);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::country::Country;
+use crate::project::EditError;
+use crate::province_map_to_geojson::{redraw_country, sub_state_coords};
+use crate::province_types::ProvinceTypes;
+use crate::topology::Topology;
+
+#[derive(Serialize)]
+pub struct TransferProvinceResponse {
+    pub to_country: Country,
+    pub from_country: Country,
+    pub to_state_coords: Vec<Vec<(f32, f32)>>,
+    pub from_state_coords: Vec<Vec<(f32, f32)>>,
+}
+
+#[instrument(
+    skip(topology, province_types, from_country, to_country),
+    fields(from = %from_country.name, to = %to_country.name)
+)]
+pub fn transfer_province(
+    topology: &Topology,
+    province_types: &ProvinceTypes,
+    state: &str,
+    province: &str,
+    from_country: Country,
+    to_country: Country,
+) -> Result<TransferProvinceResponse, EditError> {
+    if !province_types.is_ownable(province) {
+        return Err(EditError::NotOwnable(province.to_string()));
+    }
+    let (new_from_country, new_to_country) =
+        transfer_province_ownership(state, province, from_country, to_country);
+    let new_from_country = redraw_country(topology, new_from_country);
+    let new_to_country = redraw_country(topology, new_to_country);
+
+    Ok(TransferProvinceResponse {
+        from_state_coords: sub_state_coords(topology, &new_from_country, state),
+        to_state_coords: sub_state_coords(topology, &new_to_country, state),
+        from_country: new_from_country,
+        to_country: new_to_country,
+    })
+}
+
+// Returns the from and to countries, leaving their coordinates as they were.
+pub fn transfer_province_ownership(
+    state: &str,
+    province: &str,
+    from_country: Country,
+    to_country: Country,
+) -> (Country, Country) {
+    let (new_from_country, pops_given, new_state_buildings) =
+        from_country.remove_province(state, province);
+    let new_to_country = to_country.add_province(state, province, pops_given, new_state_buildings);
+
+    (new_from_country, new_to_country)
+}
@@ -0,0 +1,49 @@
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::country::Country;
+use crate::province_map_to_geojson::{redraw_country, sub_state_coords};
+use crate::topology::Topology;
+
+#[derive(Serialize)]
+pub struct TransferStateResponse {
+    pub to_country: Country,
+    pub from_country: Country,
+    pub state_coords: Vec<Vec<(f32, f32)>>,
+}
+
+// Both countries are redrawn from the provinces they own after the transfer, so the outlines
+// never drift from the province map however many edits are made.
+#[instrument(
+    skip(topology, from_country, to_country),
+    fields(from = %from_country.name, to = %to_country.name)
+)]
+pub fn transfer_state(
+    topology: &Topology,
+    state: &str,
+    from_country: Country,
+    to_country: Country,
+) -> TransferStateResponse {
+    let (new_from_country, new_to_country) =
+        transfer_state_ownership(state, from_country, to_country);
+    let new_from_country = redraw_country(topology, new_from_country);
+    let new_to_country = redraw_country(topology, new_to_country);
+
+    TransferStateResponse {
+        state_coords: sub_state_coords(topology, &new_to_country, state),
+        from_country: new_from_country,
+        to_country: new_to_country,
+    }
+}
+
+// Returns the from and to countries, leaving their coordinates as they were.
+pub fn transfer_state_ownership(
+    state: &str,
+    from_country: Country,
+    to_country: Country,
+) -> (Country, Country) {
+    let new_to_country = to_country.add_state(&from_country, state);
+    let new_from_country = from_country.remove_state(state);
+
+    (new_from_country, new_to_country)
+}
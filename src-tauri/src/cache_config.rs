@@ -1,10 +1,15 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use v3_history_core::mod_stack::ModStack;
+
+// Bumped whenever the cached map geometry changes format, so older caches are rebuilt.
+pub const CACHE_VERSION: u32 = 4;
 
 #[derive(Serialize, Deserialize)]
 pub struct CacheConfig {
     pub game_folder: Option<PathBuf>,
     pub working_dir: Option<PathBuf>,
+    pub cache_version: Option<u32>,
 }
 
 impl CacheConfig {
@@ -12,6 +17,7 @@ impl CacheConfig {
         Self {
             game_folder: None,
             working_dir: None,
+            cache_version: None,
         }
     }
 
@@ -19,4 +25,8 @@ impl CacheConfig {
         let config = std::fs::read_to_string(path).unwrap();
         serde_json::from_str(&config).unwrap()
     }
+
+    pub fn mod_stack(&self) -> ModStack {
+        ModStack::new(self.game_folder.clone().unwrap(), vec![])
+    }
 }
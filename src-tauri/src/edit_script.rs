@@ -0,0 +1,169 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{error, info, instrument};
+use v3_history_core::{
+    country::Country,
+    edit_script::{EditScript, ScriptError, ScriptReport},
+    ownership_layers::{read_assignments, ExchangeError, ImportReport, OwnershipLayers},
+    project::Project,
+    province_map_to_geojson::{country_map_to_geojson, Coords},
+    state_regions::StateRegionEdits,
+    strategic_regions::StrategicRegion,
+};
+
+use crate::{cache_config::CacheConfig, file_watcher::FileWatcher, game_folder};
+
+// Applies the script to the countries the map is showing, then redraws the ones it touched.
+#[instrument(skip(app_handle))]
+pub fn apply_edit_script(
+    app_handle: &AppHandle,
+    script_path: &Path,
+) -> Result<ScriptReport, ScriptError> {
+    let script = EditScript::read(script_path)?;
+    apply_script(app_handle, &script)
+}
+
+// Applies operations the app builds itself, like transferring a strategic region, the same way
+// as a script read from a file.
+#[instrument(skip(app_handle))]
+pub fn apply_script(
+    app_handle: &AppHandle,
+    script: &EditScript,
+) -> Result<ScriptReport, ScriptError> {
+    let mut project = cached_project(app_handle);
+    let report = project.apply_script(script)?;
+    if let Some(edits) = project.state_region_edits() {
+        write_state_region_edits(app_handle, edits);
+    }
+    write_edited_countries(app_handle, project, &report.changed_countries());
+    info!(
+        countries = report.changed_countries().len(),
+        "Applied edit script"
+    );
+
+    Ok(report)
+}
+
+// Transfers the provinces of an edited ownership export or CSV, keeping the ones that can be
+// and reporting the rest.
+#[instrument(skip(app_handle))]
+pub fn import_ownership(
+    app_handle: &AppHandle,
+    path: &Path,
+) -> Result<ImportReport, ExchangeError> {
+    let assignments = read_assignments(path)?;
+    let mut project = cached_project(app_handle);
+    let report = project.apply_assignments(&assignments);
+    write_edited_countries(app_handle, project, &report.countries);
+    info!(
+        transferred = report.transferred,
+        rejected = report.rejected.len(),
+        "Imported ownership"
+    );
+
+    Ok(report)
+}
+
+// Writes the countries the map is showing as layers for GIS tools, which an edited copy of can
+// be imported back.
+#[instrument(skip(app_handle))]
+pub fn export_ownership(app_handle: &AppHandle, path: &Path) -> Result<(), ExchangeError> {
+    let project = cached_project(app_handle);
+    OwnershipLayers {
+        topology: &game_folder(app_handle).topology(),
+        countries: &project.countries,
+    }
+    .write(path)
+}
+
+// The strategic regions with the unsaved state region splits and merges in them.
+pub fn strategic_regions(app_handle: &AppHandle) -> Vec<StrategicRegion> {
+    cached_project(app_handle).strategic_regions()
+}
+
+// The countries the map is showing, with the mod stack they came from.
+fn cached_project(app_handle: &AppHandle) -> Project {
+    let cache_dir = app_handle.path().app_cache_dir().unwrap();
+    let config = CacheConfig::get_config(cache_dir.join("config.json"));
+    let countries: Vec<Country> =
+        serde_json::from_str(&std::fs::read_to_string(cache_dir.join("countries.json")).unwrap())
+            .unwrap();
+    let project = Project::with_countries(config.mod_stack(), countries);
+    match std::fs::read_to_string(cache_dir.join("state_regions.json")) {
+        Ok(edits) => project.with_state_region_edits(serde_json::from_str(&edits).unwrap()),
+        Err(_) => project,
+    }
+}
+
+// Keeps the edited state regions until they are saved.
+fn write_state_region_edits(app_handle: &AppHandle, edits: &StateRegionEdits) {
+    let cache_dir = app_handle.path().app_cache_dir().unwrap();
+    std::fs::write(
+        cache_dir.join("state_regions.json"),
+        serde_json::to_string(edits).unwrap(),
+    )
+    .unwrap();
+
+    if let Err(e) = app_handle.emit("state-regions-changed", true) {
+        error!(error = ?e, "Failed to send state-regions-changed to frontend");
+    }
+}
+
+// Redraws the countries that were edited from the topology, since edits leave geometry alone,
+// and has the map reload them.
+fn write_edited_countries(
+    app_handle: &AppHandle,
+    project: Project,
+    changed_countries: &BTreeSet<String>,
+) {
+    let cache_dir = app_handle.path().app_cache_dir().unwrap();
+    let topology = game_folder(app_handle).topology();
+    let mut state_coords: HashMap<String, Coords> =
+        serde_json::from_str(&std::fs::read_to_string(cache_dir.join("states.json")).unwrap())
+            .unwrap();
+    state_coords.retain(|key, _| {
+        let tag = key.split(':').next().unwrap();
+        !changed_countries.contains(tag)
+    });
+
+    let (changed, mut countries): (Vec<Country>, Vec<Country>) = project
+        .countries
+        .into_iter()
+        .partition(|country| changed_countries.contains(&country.name));
+    let changed_states: Vec<(String, &[String])> = changed
+        .iter()
+        .flat_map(|country| {
+            country.states.iter().map(|state| {
+                (
+                    format!("{}:{}", country.name, state.name),
+                    state.provinces.as_slice(),
+                )
+            })
+        })
+        .collect();
+    for (key, polygons) in topology.dissolve(changed_states) {
+        state_coords.insert(key, topology.polygons_to_coords(&polygons));
+    }
+    countries.extend(country_map_to_geojson(&topology, changed));
+
+    std::fs::write(
+        cache_dir.join("states.json"),
+        serde_json::to_string(&state_coords).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(
+        cache_dir.join("countries.json"),
+        serde_json::to_string(&countries).unwrap(),
+    )
+    .unwrap();
+    FileWatcher::mark_unsaved(app_handle);
+
+    for event_id in ["load-state-coords", "load-country-data"] {
+        if let Err(e) = app_handle.emit(event_id, true) {
+            error!(event_id, error = ?e, "Failed to send event to frontend");
+        }
+    }
+}
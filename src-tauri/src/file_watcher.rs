@@ -0,0 +1,344 @@
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{debug, error, info, info_span, warn};
+
+use crate::{cache_config::CacheConfig, game_folder::GameFolder};
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+const SAVE_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    States,
+    Pops,
+    StateBuildings,
+    CountrySetups,
+    Buildings,
+    CountryDefinitions,
+    Provinces,
+    CoatsOfArms,
+}
+
+impl FileChange {
+    fn classify(relative_path: &Path) -> Option<FileChange> {
+        if relative_path.starts_with("common/history/states") {
+            Some(FileChange::States)
+        } else if relative_path.starts_with("common/history/pops") {
+            Some(FileChange::Pops)
+        } else if relative_path.starts_with("common/history/buildings") {
+            Some(FileChange::StateBuildings)
+        } else if relative_path.starts_with("common/history/countries") {
+            Some(FileChange::CountrySetups)
+        } else if relative_path.starts_with("common/buildings") {
+            Some(FileChange::Buildings)
+        } else if relative_path.starts_with("common/country_definitions") {
+            Some(FileChange::CountryDefinitions)
+        } else if relative_path.starts_with("common/coat_of_arms")
+            || relative_path.starts_with("common/named_colors")
+        {
+            Some(FileChange::CoatsOfArms)
+        } else if relative_path == Path::new("map_data/provinces.png")
+            || relative_path == Path::new("map_data/default.map")
+            || relative_path == Path::new("map_data/province_terrains.txt")
+            || relative_path == Path::new("map_data/adjacencies.csv")
+        {
+            Some(FileChange::Provinces)
+        } else {
+            None
+        }
+    }
+
+    // Reloading anything that ends up in countries.json or states.json overwrites the edits
+    // the frontend has cached there.
+    fn overwrites_edits(&self) -> bool {
+        !matches!(self, FileChange::Buildings | FileChange::CoatsOfArms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalChangeConflict {
+    pub changes: Vec<FileChange>,
+    pub paths: Vec<PathBuf>,
+    pub in_working_dir: bool,
+}
+
+#[derive(Default)]
+pub struct FileWatcherState {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    unsaved_edits: Mutex<bool>,
+    last_save: Mutex<Option<Instant>>,
+    pending_changes: Mutex<HashSet<FileChange>>,
+}
+
+struct WatchedRoots {
+    game_folder: PathBuf,
+    working_dir: Option<PathBuf>,
+}
+
+impl WatchedRoots {
+    fn game_path(&self) -> PathBuf {
+        self.game_folder.join("game")
+    }
+}
+
+pub struct FileWatcher;
+
+impl FileWatcher {
+    pub fn watch(app_handle: &AppHandle) {
+        let config_path = app_handle
+            .path()
+            .app_cache_dir()
+            .unwrap()
+            .join("config.json");
+        let config: CacheConfig = match std::fs::read_to_string(config_path) {
+            Ok(config) => serde_json::from_str(&config).unwrap(),
+            Err(_) => CacheConfig::new(),
+        };
+        let Some(game_folder) = config.game_folder else {
+            return;
+        };
+        let roots = WatchedRoots {
+            game_folder,
+            working_dir: config.working_dir,
+        };
+
+        let (sender, receiver) = channel();
+        let mut watcher = match notify::recommended_watcher(sender) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!(error = ?e, "Failed to create file watcher");
+                return;
+            }
+        };
+        for watched_path in [Some(roots.game_path()), roots.working_dir.clone()]
+            .into_iter()
+            .flatten()
+        {
+            match watcher.watch(&watched_path, RecursiveMode::Recursive) {
+                Ok(_) => info!(path = ?watched_path, "Watching for changes"),
+                Err(e) => error!(path = ?watched_path, error = ?e, "Failed to watch"),
+            }
+        }
+
+        // Replacing the previous watcher drops its sender, which ends its event thread.
+        let state = app_handle.state::<FileWatcherState>();
+        *state.watcher.lock().unwrap() = Some(watcher);
+        state.pending_changes.lock().unwrap().clear();
+
+        let app_handle = app_handle.clone();
+        std::thread::spawn(move || watch_events(app_handle, roots, receiver));
+    }
+
+    pub fn mark_unsaved(app_handle: &AppHandle) {
+        *app_handle
+            .state::<FileWatcherState>()
+            .unsaved_edits
+            .lock()
+            .unwrap() = true;
+    }
+
+    pub fn mark_saving(app_handle: &AppHandle) {
+        *app_handle
+            .state::<FileWatcherState>()
+            .last_save
+            .lock()
+            .unwrap() = Some(Instant::now());
+    }
+
+    // Game file changes held back while there were unsaved edits stay pending after a save.
+    // Reloading them would read the game files without the saved mod and drop the edits, so
+    // they are only applied when the user asks, through `apply_pending_changes`.
+    pub fn mark_saved(app_handle: &AppHandle) {
+        let state = app_handle.state::<FileWatcherState>();
+        *state.unsaved_edits.lock().unwrap() = false;
+        *state.last_save.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub fn apply_pending_changes(app_handle: &AppHandle) {
+        let state = app_handle.state::<FileWatcherState>();
+        let changes: HashSet<FileChange> = state.pending_changes.lock().unwrap().drain().collect();
+        *state.unsaved_edits.lock().unwrap() = false;
+
+        reload(app_handle, &changes);
+    }
+}
+
+fn watch_events(
+    app_handle: AppHandle,
+    roots: WatchedRoots,
+    receiver: Receiver<notify::Result<Event>>,
+) {
+    while let Ok(event) = receiver.recv() {
+        let mut events = vec![event];
+        loop {
+            match receiver.recv_timeout(DEBOUNCE) {
+                Ok(event) => events.push(event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        let paths: HashSet<PathBuf> = events
+            .into_iter()
+            .filter_map(|event| match event {
+                Ok(event) if !event.kind.is_access() => Some(event.paths),
+                Ok(_) => None,
+                Err(e) => {
+                    warn!(error = ?e, "File watcher error");
+                    None
+                }
+            })
+            .flatten()
+            .collect();
+        handle_changed_paths(&app_handle, &roots, paths);
+    }
+}
+
+fn handle_changed_paths(app_handle: &AppHandle, roots: &WatchedRoots, paths: HashSet<PathBuf>) {
+    let state = app_handle.state::<FileWatcherState>();
+    let game_path = roots.game_path();
+    let recently_saved = state
+        .last_save
+        .lock()
+        .unwrap()
+        .is_some_and(|last_save| last_save.elapsed() < SAVE_GRACE_PERIOD);
+
+    let mut game_changes: HashSet<FileChange> = HashSet::new();
+    let mut game_paths: Vec<PathBuf> = vec![];
+    let mut working_dir_changes: HashSet<FileChange> = HashSet::new();
+    let mut working_dir_paths: Vec<PathBuf> = vec![];
+
+    for path in paths {
+        if let Ok(relative_path) = path.strip_prefix(&game_path) {
+            if let Some(change) = FileChange::classify(relative_path) {
+                game_changes.insert(change);
+                game_paths.push(path.clone());
+            }
+        } else if let Some(working_dir) = &roots.working_dir {
+            if recently_saved {
+                continue;
+            }
+            if let Ok(relative_path) = path.strip_prefix(working_dir) {
+                if let Some(change) = FileChange::classify(relative_path) {
+                    working_dir_changes.insert(change);
+                    working_dir_paths.push(path.clone());
+                }
+            }
+        }
+    }
+
+    let unsaved_edits = *state.unsaved_edits.lock().unwrap();
+
+    // The working directory is only read when saving, so an external change there matters
+    // only if saving would overwrite it.
+    if unsaved_edits && !working_dir_changes.is_empty() {
+        send_conflict(
+            app_handle,
+            ExternalChangeConflict {
+                changes: working_dir_changes.into_iter().collect(),
+                paths: working_dir_paths,
+                in_working_dir: true,
+            },
+        );
+    }
+
+    if game_changes.is_empty() {
+        return;
+    }
+    info!(paths = ?game_paths, "Game files changed");
+
+    let (conflicting, safe): (HashSet<FileChange>, HashSet<FileChange>) = game_changes
+        .into_iter()
+        .partition(|change| unsaved_edits && change.overwrites_edits());
+    reload(app_handle, &safe);
+
+    if !conflicting.is_empty() {
+        state
+            .pending_changes
+            .lock()
+            .unwrap()
+            .extend(conflicting.iter().copied());
+        send_conflict(
+            app_handle,
+            ExternalChangeConflict {
+                changes: conflicting.into_iter().collect(),
+                paths: game_paths,
+                in_working_dir: false,
+            },
+        );
+    }
+}
+
+fn reload(app_handle: &AppHandle, changes: &HashSet<FileChange>) {
+    if changes.is_empty() {
+        return;
+    }
+    let _span = info_span!("reload", changes = ?changes).entered();
+    let config = CacheConfig::get_config(
+        app_handle
+            .path()
+            .app_cache_dir()
+            .unwrap()
+            .join("config.json"),
+    );
+    let game_folder = GameFolder {
+        folder_path: config.game_folder.unwrap(),
+        app_handle: app_handle.clone(),
+    };
+
+    if changes.contains(&FileChange::Provinces) {
+        game_folder.bust_cached_maps();
+        game_folder.load_provinces();
+        game_folder.load_states();
+        game_folder.load_countries();
+    } else if changes.contains(&FileChange::States) {
+        game_folder.load_states();
+        game_folder.load_countries();
+    } else if changes.iter().any(|change| {
+        matches!(
+            change,
+            FileChange::Pops
+                | FileChange::StateBuildings
+                | FileChange::CountrySetups
+                | FileChange::CountryDefinitions
+        )
+    }) {
+        game_folder.load_countries();
+    }
+
+    if changes.contains(&FileChange::Buildings) {
+        send_event(app_handle, "load-buildings");
+    }
+    if changes.contains(&FileChange::CountryDefinitions) {
+        send_event(app_handle, "load-country-definitions");
+    }
+    if changes.contains(&FileChange::CoatsOfArms) {
+        game_folder.bust_cached_flags();
+        send_event(app_handle, "flags-changed");
+    }
+}
+
+fn send_conflict(app_handle: &AppHandle, conflict: ExternalChangeConflict) {
+    match app_handle.emit("external-change-conflict", conflict) {
+        Ok(_) => warn!("Sent external-change-conflict to frontend"),
+        Err(e) => error!(error = ?e, "Failed to send external-change-conflict to frontend"),
+    }
+}
+
+fn send_event(app_handle: &AppHandle, event_id: &str) {
+    match app_handle.emit(event_id, true) {
+        Ok(_) => debug!(event_id, "Sent event to frontend"),
+        Err(e) => error!(event_id, error = ?e, "Failed to send event to frontend"),
+    }
+}
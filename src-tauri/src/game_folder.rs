@@ -1,30 +1,58 @@
 use crate::{
-    cache_config::CacheConfig,
+    cache_config::{CacheConfig, CACHE_VERSION},
+    file_watcher::FileWatcher,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{debug, error, info, instrument};
+use v3_history_core::{
+    adjacency::{Adjacency, Crossing},
+    background::{Background, BackgroundTextures, TILES_DIR_NAME},
+    coat_of_arms::{FlagError, FlagRenderer, FLAGS_DIR_NAME},
     country_definition::CountryDefinition,
     country_setup::CountrySetup,
-    dds_to_png::DdsToPng,
     get_countries::get_countries,
     get_state_buildings::get_state_buildings,
     get_state_populations::get_state_populations,
     get_states::get_states,
-    province_map_to_geojson::{
-        country_map_to_geojson, province_map_to_geojson, state_map_to_geojson,
+    mod_stack::{
+        ModStack, ADJACENCIES_PATH, COUNTRY_DEFINITIONS_PATH, COUNTRY_SETUP_PATH, HEIGHTMAP_PATH,
+        PROVINCES_PATH, STATES_PATH, STATE_BUILDINGS_PATH, STATE_POPS_PATH,
     },
+    province_index::ProvinceIndex,
+    province_map_to_geojson::{country_map_to_geojson, state_map_to_geojson},
+    province_types::{ProvinceTypes, ProvinceTypesError},
+    simplify::SIMPLIFICATION_TOLERANCES,
+    terrain::{TerrainLayer, HILLSHADE_FILE_NAME, RELIEF_FILE_NAME, TERRAIN_TYPES_FILE_NAME},
+    topology::{TopoJson, Topology},
 };
-use image_dds::image::Rgba;
-use std::{collections::HashMap, path::PathBuf};
-use tauri::{AppHandle, Emitter, Manager};
 
 const FLATMAP_PATH: &str = "game/dlc/dlc004_voice_of_the_people/gfx/map/textures/flatmap_votp.dds";
 const LAND_MASK_PATH: &str = "game/gfx/map/textures/land_mask.dds";
 const FLATMAP_OVERLAY_PATH: &str =
     "game/dlc/dlc004_voice_of_the_people/gfx/map/textures/flatmap_overlay_votp.dds";
-const PROVINCE_PATH: &str = "game/map_data/provinces.png";
-pub const STATES_PATH: &str = "game/common/history/states/00_states.txt";
-pub const STATE_POPS_PATH: &str = "game/common/history/pops";
-const STATE_BUILDINGS_PATH: &str = "game/common/history/buildings";
-pub const COUNTRY_DEFINITIONS_PATH: &str = "common/country_definitions";
-pub const COUNTRY_SETUP_PATH: &str = "common/history/countries";
+
+// The topology the transfers redraw countries from, and its simplified levels, kept in memory
+// once they have been read.
+#[derive(Default)]
+pub struct TopologyState(Mutex<HashMap<usize, Arc<Topology>>>);
+
+#[derive(Default)]
+pub struct AdjacencyState(Mutex<Option<Arc<Adjacency>>>);
+
+#[derive(Default)]
+pub struct ProvinceIndexState(Mutex<Option<Arc<ProvinceIndex>>>);
+
+#[derive(Default)]
+pub struct ProvinceTypesState(Mutex<Option<Arc<ProvinceTypes>>>);
+
+// Holds on to the parsed coats of arms and the emblem textures between flags.
+#[derive(Default)]
+pub struct FlagState(Mutex<Option<Arc<FlagRenderer>>>);
 
 pub struct GameFolder {
     pub folder_path: PathBuf,
@@ -32,14 +60,71 @@ pub struct GameFolder {
 }
 
 impl GameFolder {
+    #[instrument(skip_all, fields(folder_path = ?self.folder_path))]
     pub fn load(&self) {
         self.write_path_to_config();
-        self.load_flatmap();
-        self.load_land_mask();
-        self.load_flatmap_overlay();
+        self.load_background();
         self.load_states();
         self.load_countries();
         self.load_provinces();
+        FileWatcher::watch(&self.app_handle);
+    }
+
+    pub fn bust_cached_maps(&self) {
+        self.app_handle
+            .state::<TopologyState>()
+            .0
+            .lock()
+            .unwrap()
+            .clear();
+        *self.app_handle.state::<AdjacencyState>().0.lock().unwrap() = None;
+        *self
+            .app_handle
+            .state::<ProvinceIndexState>()
+            .0
+            .lock()
+            .unwrap() = None;
+        *self
+            .app_handle
+            .state::<ProvinceTypesState>()
+            .0
+            .lock()
+            .unwrap() = None;
+        for level in 0..SIMPLIFICATION_TOLERANCES.len() {
+            remove_cached_file(&self.app_handle, &topology_file_name(level));
+        }
+        // Terrain is drawn from the provinces, so it is redrawn with them.
+        for file_name in [
+            RELIEF_FILE_NAME,
+            HILLSHADE_FILE_NAME,
+            TERRAIN_TYPES_FILE_NAME,
+        ] {
+            remove_cached_file(&self.app_handle, file_name);
+        }
+        remove_cached_dir(
+            &self.app_handle,
+            &PathBuf::from(TILES_DIR_NAME).join(RELIEF_FILE_NAME.trim_end_matches(".png")),
+        );
+        // Left behind by caches from before the topology.
+        remove_cached_file(&self.app_handle, "provinces.json");
+        remove_cached_file(&self.app_handle, "states.png");
+        remove_cached_file(&self.app_handle, "countries.png");
+        // Converted one by one and layered by the map before the composited background.
+        remove_cached_file(&self.app_handle, "flatmap_votp.png");
+        remove_cached_file(&self.app_handle, "land_mask.png");
+        remove_cached_file(&self.app_handle, "flatmap_overlay_votp.png");
+    }
+
+    // The background is only composited from the game's textures, so it is kept when provinces
+    // change and only rebuilt for a new cache version or another game folder.
+    fn bust_cached_background(&self) {
+        for file_name in Background::file_names() {
+            remove_cached_file(&self.app_handle, file_name);
+            remove_cached_dir(
+                &self.app_handle,
+                &PathBuf::from(TILES_DIR_NAME).join(file_name.trim_end_matches(".png")),
+            );
+        }
     }
 
     fn write_path_to_config(&self) {
@@ -49,83 +134,76 @@ impl GameFolder {
             Ok(config) => serde_json::from_str(&config).unwrap(),
             Err(_) => CacheConfig::new(),
         };
+        let game_folder_changed = config.game_folder.as_ref() != Some(&self.folder_path);
         config.game_folder = Some(self.folder_path.clone());
+        if config.cache_version != Some(CACHE_VERSION) || game_folder_changed {
+            info!(
+                cache_version = ?config.cache_version,
+                game_folder_changed,
+                "Rebuilding outdated map cache"
+            );
+            self.bust_cached_maps();
+            self.bust_cached_background();
+            self.bust_cached_flags();
+            config.cache_version = Some(CACHE_VERSION);
+        }
         std::fs::write(config_path, serde_json::to_string(&config).unwrap()).unwrap();
     }
 
-    fn load_flatmap(&self) {
-        let dds_to_png = DdsToPng {
-            dds_file_path: self.flatmap(),
-        };
-
-        match dds_to_png.cache(cache_dir(&self.app_handle)) {
-            Ok(_) => handle_send_map(&self.app_handle, "load-flatmap"),
-            Err(_) => println!("Flatmap already in cache"),
-        };
-    }
-
-    fn load_land_mask(&self) {
-        let dds_to_png = DdsToPng {
-            dds_file_path: self.land_mask(),
-        };
-
-        if !dds_to_png.exists_in_cache(cache_dir(&self.app_handle)) {
-            let mut png_buffer = dds_to_png.dds_to_buffer();
-            for pixel in png_buffer.pixels_mut() {
-                if pixel[0] == 0 && pixel[1] == 0 && pixel[2] == 0 {
-                    *pixel = Rgba([0, 0, 0, 0]);
+    // The map only hears about the background once it is whole, so its layers can't arrive out
+    // of order.
+    #[instrument(skip_all)]
+    fn load_background(&self) {
+        let background = match Background::cached(&cache_dir(&self.app_handle)) {
+            Some(background) => {
+                info!("Background already in cache");
+                background
+            }
+            None => {
+                let textures = BackgroundTextures {
+                    flatmap: self.flatmap(),
+                    land_mask: self.land_mask(),
+                    overlay: self.flatmap_overlay(),
+                };
+                match textures.composite(&cache_dir(&self.app_handle)) {
+                    Ok(background) => background,
+                    Err(e) => {
+                        error!(error = %e, "Failed to composite the background");
+                        return;
+                    }
                 }
             }
-
-            dds_to_png
-                .write_image(
-                    png_buffer,
-                    dds_to_png.png_file_path(cache_dir(&self.app_handle)),
-                )
-                .unwrap();
-            handle_send_map(&self.app_handle, "load-land-mask");
-        } else {
-            println!("Land mask already in cache");
-        }
-    }
-
-    fn load_flatmap_overlay(&self) {
-        let dds_to_png = DdsToPng {
-            dds_file_path: self.flatmap_overlay(),
         };
+        // Tiled at the size of the province map, which the map's bounds are in.
+        let extent = image::image_dimensions(self.provinces()).unwrap();
+        let background = background.tile(extent, &cache_dir(&self.app_handle));
 
-        match dds_to_png.cache(cache_dir(&self.app_handle)) {
-            Ok(_) => handle_send_map(&self.app_handle, "load-flatmap-overlay"),
-            Err(_) => println!("Flatmap overlay already in cache"),
-        };
+        match self.app_handle.emit("background-ready", background) {
+            Ok(_) => debug!("Sent background-ready to frontend"),
+            Err(e) => error!(error = ?e, "Failed to send background-ready to frontend"),
+        }
     }
 
-    fn load_provinces(&self) {
-        match cache_dir(&self.app_handle).join("provinces.json").exists() {
-            true => {}
-            false => {
-                let provinces = province_map_to_geojson(self.provinces());
-                std::fs::write(
-                    cache_dir(&self.app_handle).join("provinces.json"),
-                    serde_json::to_string(&provinces).unwrap(),
-                )
-                .unwrap();
-            }
-        };
+    #[instrument(skip_all)]
+    pub fn load_provinces(&self) {
+        for level in 0..SIMPLIFICATION_TOLERANCES.len() {
+            self.simplified_topology(level);
+        }
 
-        match self.app_handle.emit("load-province-coords", true) {
-            Ok(_) => println!("Sent load-province-coords to frontend"),
-            Err(e) => println!("Failed to send load-province-coords to frontend: {:?}", e),
+        // With the number of levels, so the map knows how far it can simplify.
+        match self
+            .app_handle
+            .emit("load-province-coords", SIMPLIFICATION_TOLERANCES.len())
+        {
+            Ok(_) => debug!("Sent load-province-coords to frontend"),
+            Err(e) => error!(error = ?e, "Failed to send load-province-coords to frontend"),
         }
     }
 
-    fn load_states(&self) {
+    #[instrument(skip_all)]
+    pub fn load_states(&self) {
         let states = get_states(self.states());
-        let state_coords = state_map_to_geojson(
-            self.provinces(),
-            cache_dir(&self.app_handle).join("states.png"),
-            states,
-        );
+        let state_coords = state_map_to_geojson(&self.topology(), states);
         std::fs::write(
             cache_dir(&self.app_handle).join("states.json"),
             serde_json::to_string(&state_coords).unwrap(),
@@ -133,12 +211,13 @@ impl GameFolder {
         .unwrap();
 
         match self.app_handle.emit("load-state-coords", true) {
-            Ok(_) => println!("Sent load-state-coords to frontend"),
-            Err(e) => println!("Failed to send load-state-coords to frontend: {:?}", e),
+            Ok(_) => debug!("Sent load-state-coords to frontend"),
+            Err(e) => error!(error = ?e, "Failed to send load-state-coords to frontend"),
         }
     }
 
-    fn load_countries(&self) {
+    #[instrument(skip_all)]
+    pub fn load_countries(&self) {
         let countries = get_countries(
             get_states(self.states()),
             get_state_populations(self.state_pops()),
@@ -146,21 +225,141 @@ impl GameFolder {
             self.country_definitions(),
             self.country_setups(),
         );
-        let countries_with_coords = country_map_to_geojson(
-            cache_dir(&self.app_handle).join("states.png"),
-            cache_dir(&self.app_handle).join("countries.png"),
-            countries.clone(),
-        );
+        let countries_with_coords = country_map_to_geojson(&self.topology(), countries);
         std::fs::write(
             cache_dir(&self.app_handle).join("countries.json"),
             serde_json::to_string(&countries_with_coords).unwrap(),
         )
         .unwrap();
+        // Edited state regions are unsaved edits too, which the game files replace.
+        remove_cached_file(&self.app_handle, "state_regions.json");
 
         match self.app_handle.emit("load-country-data", true) {
-            Ok(_) => println!("Sent load-country-data to frontend"),
-            Err(e) => println!("Failed to send load-country-data to frontend: {:?}", e),
+            Ok(_) => debug!("Sent load-country-data to frontend"),
+            Err(e) => error!(error = ?e, "Failed to send load-country-data to frontend"),
+        }
+    }
+
+    pub fn topology(&self) -> Arc<Topology> {
+        self.simplified_topology(0)
+    }
+
+    // Level 0 is traced once from provinces.png and the others are simplified from it. Each is
+    // cached as TopoJSON, which the map reads the province shapes for its zoom level from.
+    pub fn simplified_topology(&self, level: usize) -> Arc<Topology> {
+        let state = self.app_handle.state::<TopologyState>();
+        if let Some(topology) = state.0.lock().unwrap().get(&level) {
+            return topology.clone();
+        }
+
+        let topology_path = cache_dir(&self.app_handle).join(topology_file_name(level));
+        let topology = match std::fs::read_to_string(&topology_path) {
+            Ok(topojson) => {
+                let topojson: TopoJson = serde_json::from_str(&topojson).unwrap();
+                Topology::from_topojson(topojson)
+            }
+            Err(_) => {
+                let topology = match level {
+                    0 => Topology::from_province_map(self.provinces()),
+                    _ => self.topology().simplify(SIMPLIFICATION_TOLERANCES[level]),
+                };
+                let mut topojson = topology.to_topojson(HashMap::new());
+                match self.province_types() {
+                    Ok(province_types) => {
+                        province_types.tag(topojson.objects.get_mut("provinces").unwrap())
+                    }
+                    Err(e) => error!(error = %e, "Failed to tell sea from land on the map"),
+                }
+                std::fs::write(topology_path, serde_json::to_string(&topojson).unwrap()).unwrap();
+                topology
+            }
+        };
+        let topology = Arc::new(topology);
+        state.0.lock().unwrap().insert(level, topology.clone());
+        topology
+    }
+
+    // Built from the topology the first time it is needed, provinces only touch along arcs.
+    pub fn adjacency(&self) -> Arc<Adjacency> {
+        let state = self.app_handle.state::<AdjacencyState>();
+        if let Some(adjacency) = state.0.lock().unwrap().as_ref() {
+            return adjacency.clone();
+        }
+
+        let crossings = Crossing::parse_from(&self.mod_stack().file(ADJACENCIES_PATH));
+        let adjacency = Arc::new(self.topology().adjacency().with_crossings(crossings));
+        *state.0.lock().unwrap() = Some(adjacency.clone());
+        adjacency
+    }
+
+    // Decoded from provinces.png the first time a province is looked up by position.
+    pub fn province_index(&self) -> Arc<ProvinceIndex> {
+        let state = self.app_handle.state::<ProvinceIndexState>();
+        if let Some(province_index) = state.0.lock().unwrap().as_ref() {
+            return province_index.clone();
+        }
+
+        let province_index = Arc::new(ProvinceIndex::from_province_map(self.provinces()));
+        *state.0.lock().unwrap() = Some(province_index.clone());
+        province_index
+    }
+
+    // Parsed from default.map and province_terrains.txt the first time a province's kind is
+    // needed.
+    pub fn province_types(&self) -> Result<Arc<ProvinceTypes>, ProvinceTypesError> {
+        let state = self.app_handle.state::<ProvinceTypesState>();
+        if let Some(province_types) = state.0.lock().unwrap().as_ref() {
+            return Ok(province_types.clone());
+        }
+
+        let province_types = Arc::new(ProvinceTypes::parse_from(&self.mod_stack())?);
+        *state.0.lock().unwrap() = Some(province_types.clone());
+        Ok(province_types)
+    }
+
+    // Rendered from the coat of arms named after the tag the first time it is asked for. The
+    // renderer is only locked to take it out of the state, so flags render side by side.
+    pub fn flag(&self, tag: &str, width: u32) -> Result<PathBuf, FlagError> {
+        let flag_renderer = self
+            .app_handle
+            .state::<FlagState>()
+            .0
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| Arc::new(FlagRenderer::new(self.mod_stack())))
+            .clone();
+        flag_renderer.cache(tag, width, &cache_dir(&self.app_handle))
+    }
+
+    // Flags are only drawn from the coats of arms and named colours, so they are kept when the
+    // map changes.
+    pub fn bust_cached_flags(&self) {
+        *self.app_handle.state::<FlagState>().0.lock().unwrap() = None;
+        remove_cached_dir(&self.app_handle, &PathBuf::from(FLAGS_DIR_NAME));
+    }
+
+    // Rendered from the heightmap and province terrains the first time it is shown.
+    pub fn terrain_layer(&self) -> TerrainLayer {
+        let cache_dir = cache_dir(&self.app_handle);
+        if let Some(terrain_layer) = TerrainLayer::cached(&cache_dir) {
+            return terrain_layer;
         }
+        TerrainLayer::render(
+            &self.mod_stack().file(HEIGHTMAP_PATH),
+            &self.province_index(),
+            &self.province_types().unwrap_or_else(|e| {
+                error!(error = %e, "Failed to read province types, the terrain will be blank");
+                Arc::default()
+            }),
+            &cache_dir,
+        )
+    }
+
+    // The composited background the map is drawn over, once it is cached.
+    pub fn background_layers(&self) -> Vec<PathBuf> {
+        Background::cached(&cache_dir(&self.app_handle))
+            .map(|background| vec![background.composite])
+            .unwrap_or_default()
     }
 
     fn flatmap(&self) -> PathBuf {
@@ -175,42 +374,59 @@ impl GameFolder {
         self.folder_path.join(PathBuf::from(FLATMAP_OVERLAY_PATH))
     }
 
-    fn provinces(&self) -> PathBuf {
-        self.folder_path.join(PathBuf::from(PROVINCE_PATH))
+    fn mod_stack(&self) -> ModStack {
+        ModStack::new(self.folder_path.clone(), vec![])
     }
 
-    fn states(&self) -> PathBuf {
-        self.folder_path.join(PathBuf::from(STATES_PATH))
+    fn provinces(&self) -> PathBuf {
+        self.mod_stack().file(PROVINCES_PATH)
     }
 
-    fn state_pops(&self) -> PathBuf {
-        self.folder_path.join(PathBuf::from(STATE_POPS_PATH))
+    fn states(&self) -> Vec<PathBuf> {
+        self.mod_stack().files_in(STATES_PATH)
     }
 
-    fn state_buildings(&self) -> PathBuf {
-        self.folder_path.join(PathBuf::from(STATE_BUILDINGS_PATH))
+    fn state_pops(&self) -> Vec<PathBuf> {
+        self.mod_stack().files_in(STATE_POPS_PATH)
     }
 
-    fn game_path(&self) -> PathBuf {
-        self.folder_path.join(PathBuf::from("game"))
+    fn state_buildings(&self) -> Vec<PathBuf> {
+        self.mod_stack().files_in(STATE_BUILDINGS_PATH)
     }
 
-    fn country_definitions(&self) -> HashMap<String, CountryDefinition> {
-        CountryDefinition::parse_map_from(
-            self.game_path()
-                .join(PathBuf::from(COUNTRY_DEFINITIONS_PATH)),
-        )
+    pub fn country_definitions(&self) -> HashMap<String, CountryDefinition> {
+        CountryDefinition::parse_map_from(self.mod_stack().files_in(COUNTRY_DEFINITIONS_PATH))
     }
 
     fn country_setups(&self) -> HashMap<String, CountrySetup> {
-        CountrySetup::parse_map_from(self.game_path().join(PathBuf::from(COUNTRY_SETUP_PATH)))
+        CountrySetup::parse_map_from(self.mod_stack().files_in(COUNTRY_SETUP_PATH))
+    }
+}
+
+pub fn topology_file_name(level: usize) -> String {
+    match level {
+        0 => "topology.json".to_string(),
+        _ => format!("topology-{}.json", level),
     }
 }
 
-fn handle_send_map(app_handle: &AppHandle, event_id: &str) {
-    match app_handle.emit(event_id, true) {
-        Ok(_) => println!("Sent {:?} to frontend", event_id),
-        Err(e) => println!("Failed to send {:?} to frontend: {:?}", event_id, e),
+fn remove_cached_file(app_handle: &AppHandle, file_name: &str) {
+    let path = cache_dir(app_handle).join(file_name);
+    if path.exists() {
+        match std::fs::remove_file(&path) {
+            Ok(_) => info!(file_name, "Removed file from cache"),
+            Err(e) => error!(file_name, error = ?e, "Failed to remove file from cache"),
+        }
+    }
+}
+
+fn remove_cached_dir(app_handle: &AppHandle, dir_name: &Path) {
+    let path = cache_dir(app_handle).join(dir_name);
+    if path.exists() {
+        match std::fs::remove_dir_all(&path) {
+            Ok(_) => info!(?dir_name, "Removed folder from cache"),
+            Err(e) => error!(?dir_name, error = ?e, "Failed to remove folder from cache"),
+        }
     }
 }
 
@@ -1,16 +1,15 @@
 use std::collections::HashSet;
 use tauri::{Manager, Window};
 
-use crate::{
-    cache_config::CacheConfig, country_definition::CountryDefinition,
-    game_folder::COUNTRY_DEFINITIONS_PATH,
-};
+use v3_history_core::{country_definition::CountryDefinition, mod_stack::COUNTRY_DEFINITIONS_PATH};
+
+use crate::cache_config::CacheConfig;
 
 pub fn get_uncreated_country_definitions(
     window: Window,
     created_tag_set: HashSet<String>,
 ) -> Vec<CountryDefinition> {
-    let country_definition_path = CacheConfig::get_config(
+    let mod_stack = CacheConfig::get_config(
         window
             .app_handle()
             .path()
@@ -18,12 +17,9 @@ pub fn get_uncreated_country_definitions(
             .unwrap()
             .join("config.json"),
     )
-    .game_folder
-    .unwrap()
-    .join("game")
-    .join(COUNTRY_DEFINITIONS_PATH);
+    .mod_stack();
 
-    CountryDefinition::parse_from(country_definition_path)
+    CountryDefinition::parse_from(mod_stack.files_in(COUNTRY_DEFINITIONS_PATH))
         .iter()
         .filter(|definition| !created_tag_set.contains(&definition.tag))
         .cloned()
@@ -0,0 +1,213 @@
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+use tauri::{ipc::Channel, App, Manager};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Event, Subscriber,
+};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    fmt::{self, format::FmtSpan},
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+const LOG_FILE_PREFIX: &str = "editor";
+const MAX_LOG_FILES: usize = 7;
+const MAX_BUFFERED_RECORDS: usize = 2000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    pub timestamp_ms: u128,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub fields: BTreeMap<String, String>,
+    pub duration_ms: Option<f64>,
+}
+
+#[derive(Default)]
+pub struct LogBuffer {
+    records: Mutex<VecDeque<LogRecord>>,
+    subscribers: Mutex<Vec<Channel<LogRecord>>>,
+}
+
+impl LogBuffer {
+    // The record is appended and the subscribers copied under the records lock, which
+    // `stream_to` replays and subscribes under, so a new subscriber gets every record once.
+    // Sending can log in turn, so nothing is locked while sending.
+    fn push(&self, record: LogRecord) {
+        let subscribers = {
+            let mut records = self.records.lock().unwrap();
+            if records.len() == MAX_BUFFERED_RECORDS {
+                records.pop_front();
+            }
+            records.push_back(record.clone());
+            self.subscribers.lock().unwrap().clone()
+        };
+        let closed: Vec<u32> = subscribers
+            .iter()
+            .filter(|channel| channel.send(record.clone()).is_err())
+            .map(Channel::id)
+            .collect();
+        if !closed.is_empty() {
+            self.subscribers
+                .lock()
+                .unwrap()
+                .retain(|channel| !closed.contains(&channel.id()));
+        }
+    }
+
+    pub fn stream_to(&self, channel: Channel<LogRecord>, limit: Option<usize>) {
+        let records = self.records.lock().unwrap();
+        let skip = records.len().saturating_sub(limit.unwrap_or(records.len()));
+        for record in records.iter().skip(skip) {
+            if channel.send(record.clone()).is_err() {
+                return;
+            }
+        }
+        self.subscribers.lock().unwrap().push(channel);
+    }
+}
+
+// Keeps the file writer flushing until the app exits.
+struct LogGuard {
+    _guard: WorkerGuard,
+}
+
+pub fn init(app: &App) {
+    let log_dir = app.path().app_data_dir().unwrap().join("logs");
+    std::fs::create_dir_all(&log_dir).unwrap();
+
+    let file_appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(&log_dir)
+        .unwrap();
+    let (file_writer, guard) = tracing_appender::non_blocking(file_appender);
+
+    let log_buffer = Arc::new(LogBuffer::default());
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with(fmt::layer().with_span_events(FmtSpan::CLOSE))
+        .with(
+            fmt::layer()
+                .json()
+                .with_writer(file_writer)
+                .with_span_events(FmtSpan::CLOSE),
+        )
+        .with(LogBufferLayer {
+            buffer: log_buffer.clone(),
+        })
+        .init();
+
+    app.manage(log_buffer);
+    app.manage(LogGuard { _guard: guard });
+    tracing::info!(log_dir = ?log_dir, "Logging initialized");
+}
+
+struct LogBufferLayer {
+    buffer: Arc<LogBuffer>,
+}
+
+struct SpanTiming {
+    start: Instant,
+    fields: BTreeMap<String, String>,
+}
+
+impl<S> Layer<S> for LogBufferLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanTiming {
+                start: Instant::now(),
+                fields: visitor.fields,
+            });
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        self.buffer.push(LogRecord {
+            timestamp_ms: now_ms(),
+            level: event.metadata().level().to_string(),
+            target: event.metadata().target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+            duration_ms: None,
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let Some(timing) = extensions.get::<SpanTiming>() else {
+            return;
+        };
+
+        self.buffer.push(LogRecord {
+            timestamp_ms: now_ms(),
+            level: span.metadata().level().to_string(),
+            target: span.metadata().target().to_string(),
+            message: format!("{} finished", span.name()),
+            fields: timing.fields.clone(),
+            duration_ms: Some(timing.start.elapsed().as_secs_f64() * 1000.0),
+        });
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields
+                .insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default()
+}
@@ -1,75 +1,89 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod building;
 mod cache_config;
-mod color_converter;
-mod country;
-mod country_definition;
-mod country_setup;
-mod dds_to_png;
+mod edit_script;
+mod file_watcher;
 mod game_folder;
-mod geo_converters;
-mod get_countries;
-mod get_state_buildings;
-mod get_state_populations;
-mod get_states;
 mod get_uncreated_country_definitions;
+mod logging;
 mod main_menu;
-mod merge_buildings;
-mod merge_pops;
-mod pdx_script_parser;
-mod province_map_to_geojson;
 mod save_as_pdx_script;
-mod technology;
-mod transfer_provinces;
-mod transfer_state;
+mod tile_protocol;
 
-use building::Building;
-use country::Country;
-use country_definition::CountryDefinition;
+use cache_config::CacheConfig;
+use file_watcher::{FileWatcher, FileWatcherState};
+use game_folder::{
+    AdjacencyState, FlagState, GameFolder, ProvinceIndexState, ProvinceTypesState, TopologyState,
+};
+use logging::{LogBuffer, LogRecord};
 use main_menu::MainMenu;
-use province_map_to_geojson::Coords;
-use std::collections::HashSet;
-use tauri::{App, Manager, Window};
-use technology::Technology;
-use transfer_provinces::{transfer_province as handle_transfer_province, TransferProvinceResponse};
-use transfer_state::{transfer_state as handle_transfer_state, TransferStateResponse};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
+use tauri::{ipc::Channel, App, AppHandle, Manager, State, Window};
+use tracing::debug;
+use v3_history_core::{
+    adjacency::{CrossingLine, Neighbour, Region},
+    background::Background,
+    building::Building,
+    country::Country,
+    country_definition::CountryDefinition,
+    dds_to_png::{convert_gfx as handle_convert_gfx, GfxConversion},
+    edit_script::{EditScript, Operation, ScriptReport},
+    labels::Label,
+    map_export::{MapExport, MapExportOptions, MIN_WIDTH},
+    map_modes::{MapMode, MapModeColors, MapModes},
+    mod_stack::ModStack,
+    province_index::{locate, ProvinceLocation},
+    province_map_to_geojson::{shape_labels, simplified_outlines, Coords},
+    state_regions::{StateRegion, StateRegionEdits},
+    strategic_regions::{region_outlines, RegionTotals, StrategicRegion},
+    technology::Technology,
+    terrain::TerrainLayer,
+    topology::Topology,
+    transfer_provinces::{transfer_province as handle_transfer_province, TransferProvinceResponse},
+    transfer_state::{transfer_state as handle_transfer_state, TransferStateResponse},
+};
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
 fn transfer_state(
+    app_handle: AppHandle,
     state: String,
     from_country: Country,
     to_country: Country,
-    from_coords: Vec<Vec<(f32, f32)>>,
-    to_coords: Vec<Vec<(f32, f32)>>,
 ) -> TransferStateResponse {
-    handle_transfer_state(&state, from_country, to_country, from_coords, to_coords)
+    FileWatcher::mark_unsaved(&app_handle);
+    handle_transfer_state(&topology(&app_handle), &state, from_country, to_country)
 }
 #[tauri::command]
 fn transfer_province(
+    app_handle: AppHandle,
     state: String,
     province: String,
     from_country: Country,
     to_country: Country,
-    from_coords: Vec<Vec<(f32, f32)>>,
-    to_coords: Vec<Vec<(f32, f32)>>,
-    province_coords: Vec<Vec<(f32, f32)>>,
-) -> TransferProvinceResponse {
-    handle_transfer_province(
+) -> Result<TransferProvinceResponse, String> {
+    let response = handle_transfer_province(
+        &topology(&app_handle),
+        &game_folder(&app_handle)
+            .province_types()
+            .map_err(|e| e.to_string())?,
         &state,
         &province,
         from_country,
         to_country,
-        from_coords,
-        to_coords,
-        province_coords,
     )
+    .map_err(|e| e.to_string())?;
+    FileWatcher::mark_unsaved(&app_handle);
+    Ok(response)
 }
 #[tauri::command]
 fn get_building(window: Window, name: String) -> Building {
-    Building::parse_from_game_folder(window)
+    Building::parse_from(&mod_stack(&window))
         .iter()
         .find(|building| building.name == name)
         .unwrap()
@@ -77,11 +91,11 @@ fn get_building(window: Window, name: String) -> Building {
 }
 #[tauri::command]
 fn get_buildings(window: Window) -> Vec<Building> {
-    Building::parse_from_game_folder(window)
+    Building::parse_from(&mod_stack(&window))
 }
 #[tauri::command]
 fn get_technologies(window: Window) -> Vec<Technology> {
-    Technology::parse_from_game_folder(window)
+    Technology::parse_from(&mod_stack(&window))
 }
 #[tauri::command]
 fn get_uncreated_country_definitions(
@@ -92,37 +106,278 @@ fn get_uncreated_country_definitions(
 }
 #[tauri::command]
 fn create_country(
+    app_handle: AppHandle,
     country_definition: CountryDefinition,
     from_country: Country,
     state: String,
-    coords: Coords,
 ) -> TransferStateResponse {
+    FileWatcher::mark_unsaved(&app_handle);
     handle_transfer_state(
+        &topology(&app_handle),
         &state,
         from_country,
         Country::new(country_definition),
-        coords,
-        vec![],
     )
 }
 #[tauri::command]
 fn create_country_from_province(
+    app_handle: AppHandle,
     country_definition: CountryDefinition,
     from_country: Country,
     state: String,
     province: String,
-    state_coords: Coords,
-    province_coords: Coords,
-) -> TransferProvinceResponse {
-    handle_transfer_province(
+) -> Result<TransferProvinceResponse, String> {
+    let response = handle_transfer_province(
+        &topology(&app_handle),
+        &game_folder(&app_handle)
+            .province_types()
+            .map_err(|e| e.to_string())?,
         &state,
         &province,
         from_country,
         Country::new(country_definition),
-        state_coords,
-        vec![],
-        province_coords,
     )
+    .map_err(|e| e.to_string())?;
+    FileWatcher::mark_unsaved(&app_handle);
+    Ok(response)
+}
+// The background composited on an earlier run, before background-ready is sent for this one.
+#[tauri::command]
+fn get_background(app_handle: AppHandle) -> Option<Background> {
+    Background::cached(&app_handle.path().app_cache_dir().unwrap())
+}
+// Converts a folder of textures, such as `gfx/interface/icons/goods_icons`, into the cache for
+// the UI to show.
+#[tauri::command]
+fn convert_gfx(window: Window, dir: String, max_size: Option<u32>) -> GfxConversion {
+    let out_dir = window
+        .app_handle()
+        .path()
+        .app_cache_dir()
+        .unwrap()
+        .join("gfx");
+    handle_convert_gfx(&mod_stack(&window), &dir, &out_dir, max_size)
+}
+// Rendered the first time it is asked for, which takes a while, so off the main thread.
+#[tauri::command]
+async fn get_terrain_layer(app_handle: AppHandle) -> TerrainLayer {
+    game_folder(&app_handle).terrain_layer()
+}
+// Flags are `size` pixels wide. Countries without a coat of arms have none. Lists ask for many
+// at once, so they are rendered off the main thread.
+#[tauri::command]
+async fn get_flag(app_handle: AppHandle, tag: String, size: u32) -> Option<PathBuf> {
+    game_folder(&app_handle)
+        .flag(&tag, size)
+        .inspect_err(|e| debug!(tag, error = %e, "No flag for country"))
+        .ok()
+}
+#[tauri::command]
+fn get_simplified_outlines(
+    app_handle: AppHandle,
+    level: usize,
+    groups: HashMap<String, Vec<String>>,
+) -> HashMap<String, Coords> {
+    let game_folder = game_folder(&app_handle);
+    simplified_outlines(
+        &game_folder.topology(),
+        &game_folder.simplified_topology(level),
+        &groups,
+    )
+}
+// Shapes are keyed however the map draws them, each with the text to label it with.
+#[tauri::command]
+fn get_labels(shapes: HashMap<String, (String, Coords)>) -> HashMap<String, Label> {
+    shape_labels(shapes)
+}
+#[tauri::command]
+fn export_map(
+    app_handle: AppHandle,
+    path: PathBuf,
+    options: MapExportOptions,
+) -> Result<(), String> {
+    if options.width < MIN_WIDTH {
+        return Err(format!(
+            "the map must be at least {} pixels wide",
+            MIN_WIDTH
+        ));
+    }
+    let game_folder = game_folder(&app_handle);
+    let topology = game_folder.topology();
+    MapExport {
+        simplified: &game_folder.simplified_topology(options.simplification_level(&topology)),
+        topology: &topology,
+        countries: cached_countries(&app_handle),
+        country_definitions: &game_folder.country_definitions(),
+        backgrounds: game_folder.background_layers(),
+        options,
+    }
+    .write(&path)
+    .map_err(|e| e.to_string())
+}
+#[tauri::command]
+fn get_map_mode(window: Window, mode: MapMode) -> MapModeColors {
+    let app_handle = window.app_handle();
+    MapModes {
+        mod_stack: &mod_stack(&window),
+        topology: &topology(app_handle),
+        countries: &cached_countries(app_handle),
+    }
+    .colors(&mode)
+}
+// Regions with their outlines at the map's simplification level.
+#[tauri::command]
+fn get_strategic_regions(window: Window, level: usize) -> Vec<(StrategicRegion, Coords)> {
+    let app_handle = window.app_handle();
+    let game_folder = game_folder(app_handle);
+    let regions = edit_script::strategic_regions(app_handle);
+    let mut outlines = region_outlines(
+        &game_folder.topology(),
+        &game_folder.simplified_topology(level),
+        &regions,
+        &cached_countries(app_handle),
+    );
+    regions
+        .into_iter()
+        .map(|region| {
+            let outline = outlines.remove(&region.name).unwrap_or_default();
+            (region, outline)
+        })
+        .collect()
+}
+#[tauri::command]
+fn get_state_regions(window: Window) -> Vec<StateRegion> {
+    state_regions(&window)
+}
+// What the map gives a state, `s:STATE_NAME`, whoever owns it.
+#[tauri::command]
+fn get_state_region(window: Window, state: String) -> Option<StateRegion> {
+    state_regions(&window)
+        .into_iter()
+        .find(|region| region.name == state)
+}
+#[tauri::command]
+fn get_region_totals(window: Window, region: String) -> Option<RegionTotals> {
+    let countries = cached_countries(window.app_handle());
+    edit_script::strategic_regions(window.app_handle())
+        .into_iter()
+        .find(|candidate| candidate.name == region)
+        .map(|region| region.totals(&countries))
+}
+#[tauri::command]
+fn transfer_strategic_region(
+    app_handle: AppHandle,
+    region: String,
+    to: String,
+) -> Result<ScriptReport, String> {
+    let script = EditScript {
+        operations: vec![Operation::TransferStrategicRegion { region, to }],
+    };
+    edit_script::apply_script(&app_handle, &script).map_err(|e| e.to_string())
+}
+#[tauri::command]
+fn move_to_state_region(
+    app_handle: AppHandle,
+    provinces: Vec<String>,
+    state: String,
+) -> Result<ScriptReport, String> {
+    let script = EditScript {
+        operations: vec![Operation::MoveToStateRegion { provinces, state }],
+    };
+    edit_script::apply_script(&app_handle, &script).map_err(|e| e.to_string())
+}
+#[tauri::command]
+fn split_state_region(
+    app_handle: AppHandle,
+    state: String,
+    provinces: Vec<String>,
+    new_state: String,
+) -> Result<ScriptReport, String> {
+    let script = EditScript {
+        operations: vec![Operation::SplitStateRegion {
+            state,
+            provinces,
+            new_state,
+        }],
+    };
+    edit_script::apply_script(&app_handle, &script).map_err(|e| e.to_string())
+}
+#[tauri::command]
+fn merge_state_regions(
+    app_handle: AppHandle,
+    from: String,
+    into: String,
+) -> Result<ScriptReport, String> {
+    let script = EditScript {
+        operations: vec![Operation::MergeStateRegions { from, into }],
+    };
+    edit_script::apply_script(&app_handle, &script).map_err(|e| e.to_string())
+}
+#[tauri::command]
+fn get_neighbours(app_handle: AppHandle, region: Region) -> Vec<Neighbour> {
+    game_folder(&app_handle)
+        .adjacency()
+        .neighbours_of(&region, &cached_countries(&app_handle))
+}
+// Straits and other crossings from adjacencies.csv, to draw over the map.
+#[tauri::command]
+fn get_crossings(app_handle: AppHandle) -> Vec<CrossingLine> {
+    let game_folder = game_folder(&app_handle);
+    game_folder
+        .adjacency()
+        .crossing_lines(&game_folder.topology().province_coords())
+}
+// Provinces, such as a country's, split into the parts that aren't connected by land or a
+// crossing, largest first, so connected territory has one.
+#[tauri::command]
+fn get_territories(app_handle: AppHandle, provinces: Vec<String>) -> Vec<Vec<String>> {
+    game_folder(&app_handle).adjacency().territories(&provinces)
+}
+#[tauri::command]
+fn province_at(app_handle: AppHandle, x: f32, y: f32) -> Option<ProvinceLocation> {
+    let province_index = game_folder(&app_handle).province_index();
+    let province = province_index.province_at(x, y)?;
+    locate([province], &cached_countries(&app_handle)).pop()
+}
+#[tauri::command]
+fn provinces_in_rect(
+    app_handle: AppHandle,
+    from: (f32, f32),
+    to: (f32, f32),
+) -> Vec<ProvinceLocation> {
+    let provinces = game_folder(&app_handle)
+        .province_index()
+        .provinces_in_rect(from, to);
+    locate(
+        provinces.iter().map(String::as_str),
+        &cached_countries(&app_handle),
+    )
+}
+#[tauri::command]
+fn provinces_in_polygon(app_handle: AppHandle, polygon: Vec<(f32, f32)>) -> Vec<ProvinceLocation> {
+    let provinces = game_folder(&app_handle)
+        .province_index()
+        .provinces_in_polygon(&polygon);
+    locate(
+        provinces.iter().map(String::as_str),
+        &cached_countries(&app_handle),
+    )
+}
+#[tauri::command]
+fn apply_edit_script(app_handle: AppHandle, path: PathBuf) -> Result<ScriptReport, String> {
+    edit_script::apply_edit_script(&app_handle, &path).map_err(|e| e.to_string())
+}
+#[tauri::command]
+fn apply_external_changes(app_handle: AppHandle) {
+    FileWatcher::apply_pending_changes(&app_handle);
+}
+#[tauri::command]
+fn stream_logs(
+    log_buffer: State<'_, Arc<LogBuffer>>,
+    channel: Channel<LogRecord>,
+    limit: Option<usize>,
+) {
+    log_buffer.stream_to(channel, limit);
 }
 
 fn main() {
@@ -130,15 +385,26 @@ fn main() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(FileWatcherState::default())
+        .manage(TopologyState::default())
+        .manage(AdjacencyState::default())
+        .manage(ProvinceIndexState::default())
+        .manage(ProvinceTypesState::default())
+        .manage(FlagState::default())
         .setup(|app| {
+            logging::init(app);
             let main_window = app.get_webview_window("main").unwrap();
             main_window.maximize().unwrap();
 
             initialize_app_dir(app);
             MainMenu::create_menu(app).unwrap();
+            FileWatcher::watch(app.handle());
             Ok(())
         })
         .on_menu_event(MainMenu::handler)
+        .register_uri_scheme_protocol(tile_protocol::TILE_PROTOCOL, |context, request| {
+            tile_protocol::handle(context.app_handle(), request)
+        })
         .invoke_handler(tauri::generate_handler![
             transfer_state,
             transfer_province,
@@ -147,7 +413,32 @@ fn main() {
             get_uncreated_country_definitions,
             create_country,
             create_country_from_province,
-            get_technologies
+            get_technologies,
+            get_background,
+            convert_gfx,
+            get_flag,
+            get_terrain_layer,
+            get_simplified_outlines,
+            get_labels,
+            export_map,
+            get_map_mode,
+            get_strategic_regions,
+            get_region_totals,
+            get_state_regions,
+            get_state_region,
+            transfer_strategic_region,
+            move_to_state_region,
+            split_state_region,
+            merge_state_regions,
+            get_neighbours,
+            get_crossings,
+            get_territories,
+            province_at,
+            provinces_in_rect,
+            provinces_in_polygon,
+            apply_edit_script,
+            apply_external_changes,
+            stream_logs
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -156,3 +447,49 @@ fn main() {
 fn initialize_app_dir(app: &mut App) {
     std::fs::create_dir_all(app.path().app_cache_dir().unwrap()).unwrap();
 }
+
+fn mod_stack(window: &Window) -> ModStack {
+    CacheConfig::get_config(
+        window
+            .app_handle()
+            .path()
+            .app_cache_dir()
+            .unwrap()
+            .join("config.json"),
+    )
+    .mod_stack()
+}
+
+fn topology(app_handle: &AppHandle) -> Arc<Topology> {
+    game_folder(app_handle).topology()
+}
+
+// The ownership the map is showing, which the frontend keeps written to the cache.
+fn cached_countries(app_handle: &AppHandle) -> Vec<Country> {
+    let cache_dir = app_handle.path().app_cache_dir().unwrap();
+    serde_json::from_str(&std::fs::read_to_string(cache_dir.join("countries.json")).unwrap())
+        .unwrap()
+}
+
+// The state regions with their unsaved edits, or as the mods have them if there are none.
+fn state_regions(window: &Window) -> Vec<StateRegion> {
+    let cache_dir = window.app_handle().path().app_cache_dir().unwrap();
+    match std::fs::read_to_string(cache_dir.join("state_regions.json")) {
+        Ok(edits) => {
+            serde_json::from_str::<StateRegionEdits>(&edits)
+                .unwrap()
+                .regions
+        }
+        Err(_) => StateRegion::parse_from(&mod_stack(window)),
+    }
+}
+
+fn game_folder(app_handle: &AppHandle) -> GameFolder {
+    let cache_dir = app_handle.path().app_cache_dir().unwrap();
+    GameFolder {
+        folder_path: CacheConfig::get_config(cache_dir.join("config.json"))
+            .game_folder
+            .unwrap(),
+        app_handle: app_handle.clone(),
+    }
+}
@@ -2,14 +2,19 @@ use tauri::{
     menu::{MenuBuilder, MenuEvent, SubmenuBuilder},
     App, AppHandle, Manager,
 };
-use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 
 use crate::cache_config::CacheConfig;
+use crate::edit_script::{apply_edit_script, export_ownership, import_ownership};
+use crate::file_watcher::FileWatcher;
 use crate::game_folder::GameFolder;
 use crate::save_as_pdx_script::save_as_pdx_script;
 
 const OPEN_GAME_FOLDER: &str = "open-game-folder";
 const OPEN_WORKING_DIRECTORY: &str = "open-working-directory";
+const APPLY_EDIT_SCRIPT: &str = "apply-edit-script";
+const EXPORT_OWNERSHIP: &str = "export-ownership";
+const IMPORT_OWNERSHIP: &str = "import-ownership";
 const SAVE: &str = "save";
 const EXIT: &str = "exit";
 
@@ -21,6 +26,9 @@ impl MainMenu {
         let submenu = SubmenuBuilder::new(handle, "File")
             .text(OPEN_GAME_FOLDER, "Open Game Folder")
             .text("open-working-directory", "Open Working Directory")
+            .text(APPLY_EDIT_SCRIPT, "Apply Edit Script")
+            .text(EXPORT_OWNERSHIP, "Export Ownership")
+            .text(IMPORT_OWNERSHIP, "Import Ownership")
             .text(SAVE, "Save")
             .text(EXIT, "Exit")
             .build()?;
@@ -35,6 +43,9 @@ impl MainMenu {
                 handle_open_game_folder(app_handle);
             }
             OPEN_WORKING_DIRECTORY => handle_open_working_directory(app_handle),
+            APPLY_EDIT_SCRIPT => handle_apply_edit_script(app_handle),
+            EXPORT_OWNERSHIP => handle_export_ownership(app_handle),
+            IMPORT_OWNERSHIP => handle_import_ownership(app_handle),
             SAVE => {
                 handle_save(app_handle);
             }
@@ -68,6 +79,7 @@ fn handle_open_working_directory(app_handle: &AppHandle) {
         .unwrap()
         .join("config.json");
 
+    let watcher_app_handle = app_handle.clone();
     app_handle.dialog().file().pick_folder(move |file_path| {
         if let Some(file_path) = file_path {
             if let Ok(file_path) = file_path.into_path() {
@@ -78,11 +90,88 @@ fn handle_open_working_directory(app_handle: &AppHandle) {
 
                 config.working_dir = Some(file_path.clone());
                 std::fs::write(config_path, serde_json::to_string(&config).unwrap()).unwrap();
+                FileWatcher::watch(&watcher_app_handle);
             }
         }
     });
 }
 
+fn handle_apply_edit_script(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    app_handle
+        .dialog()
+        .file()
+        .add_filter("Edit script", &["json", "toml"])
+        .pick_file(move |file_path| {
+            if let Some(file_path) = file_path {
+                if let Ok(file_path) = file_path.into_path() {
+                    let (message, kind) = match apply_edit_script(&app_handle, &file_path) {
+                        Ok(report) => (report.to_string(), MessageDialogKind::Info),
+                        Err(e) => (e.to_string(), MessageDialogKind::Error),
+                    };
+                    app_handle
+                        .dialog()
+                        .message(message)
+                        .title("Edit script")
+                        .kind(kind)
+                        .show(|_| {});
+                }
+            }
+        });
+}
+
+fn handle_export_ownership(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    app_handle
+        .dialog()
+        .file()
+        .add_filter("GeoJSON", &["geojson"])
+        .add_filter("TopoJSON", &["topojson"])
+        .save_file(move |file_path| {
+            if let Some(file_path) = file_path {
+                if let Ok(file_path) = file_path.into_path() {
+                    if let Err(e) = export_ownership(&app_handle, &file_path) {
+                        app_handle
+                            .dialog()
+                            .message(e.to_string())
+                            .title("Export ownership")
+                            .kind(MessageDialogKind::Error)
+                            .show(|_| {});
+                    }
+                }
+            }
+        });
+}
+
+fn handle_import_ownership(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    app_handle
+        .dialog()
+        .file()
+        .add_filter("Ownership", &["geojson", "json", "csv"])
+        .pick_file(move |file_path| {
+            if let Some(file_path) = file_path {
+                if let Ok(file_path) = file_path.into_path() {
+                    let (message, kind) = match import_ownership(&app_handle, &file_path) {
+                        Ok(report) if report.rejected.is_empty() => {
+                            (report.to_string(), MessageDialogKind::Info)
+                        }
+                        Ok(report) => (report.to_string(), MessageDialogKind::Warning),
+                        Err(e) => (e.to_string(), MessageDialogKind::Error),
+                    };
+                    app_handle
+                        .dialog()
+                        .message(message)
+                        .title("Import ownership")
+                        .kind(kind)
+                        .show(|_| {});
+                }
+            }
+        });
+}
+
 fn handle_save(app_handle: &AppHandle) {
+    FileWatcher::mark_saving(app_handle);
     save_as_pdx_script(app_handle);
+    FileWatcher::mark_saved(app_handle);
 }
@@ -0,0 +1,59 @@
+use std::borrow::Cow;
+use tauri::{
+    http::{header, Request, Response, StatusCode},
+    AppHandle, Manager,
+};
+use tracing::debug;
+use v3_history_core::{background::TILES_DIR_NAME, tiles::TilePyramid};
+
+pub const TILE_PROTOCOL: &str = "tiles";
+
+// Serves `tiles://localhost/{name}/{z}/{x}/{y}.png` from the cached pyramids, so the map only
+// loads the tiles in view instead of whole textures.
+pub fn handle(app_handle: &AppHandle, request: Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
+    let Some((name, zoom, x, y)) = parse_tile(request.uri().path()) else {
+        return respond(StatusCode::BAD_REQUEST, vec![]);
+    };
+    let tiles_dir = app_handle
+        .path()
+        .app_cache_dir()
+        .unwrap()
+        .join(TILES_DIR_NAME);
+
+    match std::fs::read(TilePyramid::tile_path(&tiles_dir, name, zoom, x, y)) {
+        Ok(tile) => respond(StatusCode::OK, tile),
+        Err(_) => {
+            debug!(name, zoom, x, y, "Tile not in cache");
+            respond(StatusCode::NOT_FOUND, vec![])
+        }
+    }
+}
+
+// Pyramid names are the file stems of the cached images, so anything else isn't a tile.
+fn parse_tile(path: &str) -> Option<(&str, i32, i32, i32)> {
+    let mut parts = path.trim_start_matches('/').split('/');
+    let name = parts.next()?;
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return None;
+    }
+    let zoom = parts.next()?.parse().ok()?;
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.strip_suffix(".png")?.parse().ok()?;
+    match parts.next() {
+        Some(_) => None,
+        None => Some((name, zoom, x, y)),
+    }
+}
+
+fn respond(status: StatusCode, body: Vec<u8>) -> Response<Cow<'static, [u8]>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "image/png")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(Cow::Owned(body))
+        .unwrap()
+}
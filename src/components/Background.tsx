@@ -1,58 +1,93 @@
-import { ImageOverlay } from 'react-leaflet'
+import { ImageOverlay, LayerGroup, LayersControl, TileLayer, useMapEvents } from 'react-leaflet'
 import { LatLngBoundsExpression } from 'leaflet'
 import './Map.css'
-import { useEffect, useState } from 'react'
+import { ReactNode, useEffect, useState } from 'react'
 import { listen } from '@tauri-apps/api/event'
-import { appCacheDir, join } from '@tauri-apps/api/path'
-import { exists } from '@tauri-apps/plugin-fs'
-import { convertFileSrc } from '@tauri-apps/api/core'
+import { convertFileSrc, invoke } from '@tauri-apps/api/core'
 
-const flatmapFileName = 'flatmap_votp.png'
-const landMaskFileName = 'land_mask.png'
-const flatmapOverlayFileName = 'flatmap_overlay_votp.png'
+type TilePyramid = {
+  name: string
+  min_zoom: number
+  max_zoom: number
+  tile_size: number
+}
 
-const getImagePath = async (filename: string, callback: (path: string | null) => void) => {
-  const cacheDir = await appCacheDir()
-  const path = await join(cacheDir, filename)
-  const fileExists = await exists(path)
-  console.log(`File ${filename} at ${path} exists: ${fileExists}`)
+// Paths of the cached images, the layers being the flatmap, land tint and overlay, bottom first. Each image's tiles are named after its file.
+type BackgroundImages = {
+  composite: string
+  layers: string[]
+  tiles: TilePyramid[]
+}
 
-  return fileExists ? callback(convertFileSrc(path)) : null
+// The relief is shaded from the heightmap and coloured by province terrain, the other two are its parts
+type TerrainImages = {
+  relief: string
+  hillshade: string
+  terrain_types: string
+  tiles: TilePyramid | null
 }
 
-export default function Background({ bounds }: { bounds: LatLngBoundsExpression }) {
-  const [flatmap, setFlatmap] = useState<null | string>(null)
-  const [flatmapOverlay, setFlatmapOverlay] = useState<null | string>('')
-  const [landMask, setLandMask] = useState<null | string>('')
+const LAYER_NAMES = ['Flatmap', 'Land tint', 'Overlay']
+const TERRAIN_LAYER_NAME = 'Terrain relief'
 
-  useEffect(() => {
-    const unlistenToFlatmap = listen<String>('load-flatmap', () => {
-      getImagePath(flatmapFileName, setFlatmap)
-    })
-    getImagePath(flatmapFileName, setFlatmap)
+// Served by the backend's tiles protocol, so only the tiles in view are loaded at each zoom
+const tileUrl = (pyramid: TilePyramid) => `${convertFileSrc('', 'tiles')}${pyramid.name}/{z}/{x}/{y}.png`
 
-    const unlistenToLandMask = listen<String>('load-land-mask', () => {
-      getImagePath(landMaskFileName, setLandMask)
-    })
-    getImagePath(landMaskFileName, setLandMask)
+// Images are drawn whole until their tiles have been cut
+function BackgroundImage({ url, tiles, bounds, opacity = 1 }: { url: string, tiles: TilePyramid[], bounds: LatLngBoundsExpression, opacity?: number }) {
+  const pyramid = tiles.find((pyramid) => url.replace(/\\/g, '/').endsWith(`/${pyramid.name}.png`))
+  if (!pyramid) { return <ImageOverlay url={convertFileSrc(url)} bounds={bounds} opacity={opacity} /> }
+
+  return <TileLayer url={tileUrl(pyramid)} bounds={bounds} opacity={opacity} tileSize={pyramid.tile_size} minNativeZoom={pyramid.min_zoom} maxNativeZoom={pyramid.max_zoom} noWrap />
+}
+
+// Other overlays, such as the crossings, are passed as children so they share the layers control
+export default function Background({ bounds, children }: { bounds: LatLngBoundsExpression, children?: ReactNode }) {
+  const [background, setBackground] = useState<BackgroundImages | null>(null)
+  const [terrain, setTerrain] = useState<TerrainImages | null>(null)
+  const [loadingTerrain, setLoadingTerrain] = useState(false)
+
+  // Rendering the relief takes a while, so it's only asked for the first time it's shown
+  useMapEvents({
+    overlayadd: ({ name }) => {
+      if (name !== TERRAIN_LAYER_NAME || terrain || loadingTerrain) { return }
+      setLoadingTerrain(true)
+      invoke<TerrainImages>('get_terrain_layer').then(setTerrain).finally(() => setLoadingTerrain(false))
+    },
+  })
 
-    const unlistenToFlatmapOverlay = listen<String>('load-flatmap-overlay', () => {
-      getImagePath(flatmapOverlayFileName, setFlatmapOverlay)
+  useEffect(() => {
+    // Sent once the composite and its layers are all written, so they can't arrive out of order
+    const unlistenToBackground = listen<BackgroundImages>('background-ready', ({ payload }) => {
+      setBackground(payload)
+    })
+    invoke<BackgroundImages | null>('get_background').then((background) => {
+      if (background) { setBackground(background) }
     })
-    getImagePath(flatmapOverlayFileName, setFlatmapOverlay)
 
     return () => {
-      unlistenToFlatmap.then((unlisten) => unlisten())
-      unlistenToFlatmapOverlay.then((unlisten) => unlisten())
-      unlistenToLandMask.then((unlisten) => unlisten())
+      unlistenToBackground.then((unlisten) => unlisten())
     }
   }, [])
 
+  if (!background) { return null }
+
   return (
-    <>
-      { flatmap && <ImageOverlay url={flatmap} bounds={bounds} /> }
-      { landMask && <ImageOverlay url={landMask} bounds={bounds} /> }
-      { flatmapOverlay && <ImageOverlay url={flatmapOverlay} bounds={bounds} /> }
-    </>
+    <LayersControl position="topleft">
+      <LayersControl.BaseLayer checked name="Composited">
+        <BackgroundImage url={background.composite} tiles={background.tiles} bounds={bounds} />
+      </LayersControl.BaseLayer>
+      <LayersControl.BaseLayer name="Separate layers">
+        <LayerGroup>
+          {background.layers.map((layer, index) => <BackgroundImage key={LAYER_NAMES[index]} url={layer} tiles={background.tiles} bounds={bounds} />)}
+        </LayerGroup>
+      </LayersControl.BaseLayer>
+      <LayersControl.Overlay name={TERRAIN_LAYER_NAME}>
+        <LayerGroup>
+          {terrain && <BackgroundImage url={terrain.relief} tiles={terrain.tiles ? [terrain.tiles] : []} bounds={bounds} opacity={0.6} />}
+        </LayerGroup>
+      </LayersControl.Overlay>
+      {children}
+    </LayersControl>
   )
 }
@@ -3,11 +3,13 @@ import { State } from "./States"
 import { GeoJSON } from 'react-leaflet'
 import { FeatureCollection, Feature, Geometry } from 'geojson'
 import { LeafletEventHandlerFnMap } from "leaflet"
+import { Label } from "./Labels"
 
 export type Country = {
   name: string,
   color: [number, number, number],
   coordinates: Coords,
+  label: Label | null,
   states: State[]
   setup: CountrySetup
 }
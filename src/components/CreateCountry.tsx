@@ -1,6 +1,7 @@
 import { useEffect, useState } from "react";
 import { Country } from "./Countries";
 import { invoke } from "@tauri-apps/api/core";
+import Flag from "./Flag";
 
 export type CountryDefinition = {
   tag: string
@@ -36,7 +37,7 @@ export default function CreateCountry({ createdCountries, onCreateCountry }: Cre
         <input type="text" value={filter} placeholder="Search Tags" className="input input-bordered input-sm" onChange={handleChange} />
         <ul className="menu menu-vertical p-2 max-h-60 overflow-y-scroll block">
           {filteredCountryDefinitions.map(countryDefinition => (
-            <li className="block w-full" onClick={() => onCreateCountry(countryDefinition)}><a>{countryDefinition.tag}</a></li>
+            <li className="block w-full" onClick={() => onCreateCountry(countryDefinition)}><a><Flag tag={countryDefinition.tag} size={24} />{countryDefinition.tag}</a></li>
           ))}
         </ul>
       </div>
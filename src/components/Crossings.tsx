@@ -0,0 +1,57 @@
+import { GeoJSON } from 'react-leaflet'
+import { FeatureCollection, Geometry } from 'geojson'
+import { useEffect, useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+
+type Crossing = {
+  from: string
+  to: string
+  kind: string
+  through: string | null
+  comment: string
+}
+
+type CrossingLine = {
+  crossing: Crossing
+  line: [number, number][]
+}
+
+const KIND_COLORS: { [kind: string]: string } = {
+  sea: '#1d4ed8',
+  river: '#0891b2',
+  lake: '#0891b2',
+  canal: '#7c3aed',
+  impassable: '#b91c1c',
+}
+
+// Straits, canals and other crossings from adjacencies.csv, which join provinces the borders don't show
+export default function Crossings() {
+  const [crossings, setCrossings] = useState<CrossingLine[]>([])
+
+  useEffect(() => {
+    invoke<CrossingLine[]>('get_crossings').then(setCrossings)
+  }, [])
+
+  const data: FeatureCollection<Geometry, Crossing> = {
+    type: "FeatureCollection",
+    features: crossings.map(({ crossing, line }) => ({
+      type: "Feature",
+      properties: crossing,
+      geometry: { type: "LineString", coordinates: line }
+    }))
+  }
+
+  return <GeoJSON
+    data={data}
+    key={crossings.length}
+    style={(feature) => ({
+      color: KIND_COLORS[feature?.properties.kind] || '#000000',
+      weight: 3,
+      dashArray: feature?.properties.kind === 'impassable' ? '2 6' : '10 4',
+    })}
+    onEachFeature={(feature, layer) => {
+      const { from, to, kind, through, comment } = feature.properties
+      layer.bindTooltip(`${comment || kind}: ${from} to ${to}${through ? ` through ${through}` : ''}`, { sticky: true })
+    }}
+  />
+}
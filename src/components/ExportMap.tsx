@@ -0,0 +1,53 @@
+import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { message, save } from "@tauri-apps/plugin-dialog";
+
+type MapExportOptions = {
+  width: number
+  state_borders: boolean
+  province_borders: boolean
+  labels: boolean
+  legend: boolean
+}
+
+const WIDTHS = [2048, 4096, 8192]
+
+export default function ExportMap() {
+  const [options, setOptions] = useState<MapExportOptions>({ width: 4096, state_borders: true, province_borders: false, labels: true, legend: true })
+  const [exporting, setExporting] = useState(false)
+
+  const handleKeyDown = (event: React.KeyboardEvent) => { event.stopPropagation() }
+  const toggle = (option: 'state_borders' | 'province_borders' | 'labels' | 'legend') => setOptions((options) => ({ ...options, [option]: !options[option] }))
+
+  // The format is picked by the file's extension
+  const handleExport = async () => {
+    const path = await save({ filters: [{ name: 'PNG image', extensions: ['png'] }, { name: 'SVG image', extensions: ['svg'] }] })
+    if (!path) { return }
+    setExporting(true)
+    try {
+      await invoke("export_map", { path, options })
+    } catch (error) {
+      await message(String(error), { title: 'Export map', kind: 'error' })
+    } finally {
+      setExporting(false)
+    }
+  }
+
+  return (
+    <div className="dropdown dropdown-top fixed bottom-4 left-4 z-[400]" onKeyDown={handleKeyDown}>
+      <div tabIndex={0} role="button" className="btn">Export Map</div>
+      <div tabIndex={0} className="dropdown-content z-[1] card card-compact bg-base-100 shadow-xl w-56 p-2">
+        <select className="select select-bordered select-sm" value={options.width} onChange={(event) => setOptions({ ...options, width: Number(event.target.value) })}>
+          {WIDTHS.map((width) => <option key={width} value={width}>{width} pixels wide</option>)}
+        </select>
+        {([['state_borders', 'State borders'], ['province_borders', 'Province borders'], ['labels', 'Labels'], ['legend', 'Legend']] as const).map(([option, label]) => (
+          <label key={option} className="label cursor-pointer">
+            <span className="label-text">{label}</span>
+            <input type="checkbox" className="checkbox checkbox-sm" checked={options[option]} onChange={() => toggle(option)} />
+          </label>
+        ))}
+        <button className="btn btn-sm btn-primary" disabled={exporting} onClick={handleExport}>{exporting ? 'Exporting...' : 'Export'}</button>
+      </div>
+    </div>
+  )
+}
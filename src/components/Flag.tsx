@@ -0,0 +1,32 @@
+import { useEffect, useState } from "react";
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+type FlagProps = {
+  tag: string
+  // Width in pixels, the flag being two thirds as tall
+  size: number
+  // Shown instead for countries without a coat of arms
+  fallbackColor?: [number, number, number]
+}
+
+export default function Flag({ tag, size, fallbackColor }: FlagProps) {
+  const [flag, setFlag] = useState<string | null>(null)
+  // Bumped when the coats of arms change, so the redrawn flag isn't taken from the webview's cache
+  const [version, setVersion] = useState(0)
+
+  useEffect(() => {
+    const unlistenToFlags = listen('flags-changed', () => setVersion((version) => version + 1))
+    return () => { unlistenToFlags.then((unlisten) => unlisten()) }
+  }, [])
+
+  useEffect(() => {
+    setFlag(null)
+    invoke<string | null>('get_flag', { tag, size }).then((path) => setFlag(path ? `${convertFileSrc(path)}?v=${version}` : null))
+  }, [tag, size, version])
+
+  const style = { width: size, height: Math.round(size * 2 / 3) }
+  if (!flag) { return <span className="inline-block border border-black align-middle" style={{ ...style, backgroundColor: fallbackColor ? `rgb(${fallbackColor.join(', ')})` : 'transparent' }} /> }
+
+  return <img src={flag} alt={tag} className="inline-block border border-black align-middle" style={style} />
+}
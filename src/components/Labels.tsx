@@ -0,0 +1,83 @@
+import { useEffect, useState } from 'react'
+import { SVGOverlay } from 'react-leaflet'
+import { LatLngBoundsExpression } from 'leaflet'
+import { invoke } from '@tauri-apps/api/core'
+import { Coords } from './Map'
+import { Country } from './Countries'
+import { State } from './States'
+
+const MAP_WIDTH = 8192
+const MAP_HEIGHT = 3616
+
+export type Label = {
+  anchor: [number, number],
+  baseline: [number, number][],
+  font_size: number
+}
+
+type LabelsProps = {
+  bounds: LatLngBoundsExpression
+  countries: Country[]
+  selectedCountry: Country | null
+  selectedState: State | null
+  stateCoords: { [key: string]: Coords }
+  provinceCoords: { [key: string]: Coords }
+  renderBreaker: number
+}
+
+// `s:STATE_NEW_YORK` is labelled `New York`
+const stateLabelText = (state: string) => state
+  .replace(/^s:/, '')
+  .replace(/^STATE_/, '')
+  .split('_')
+  .map((word) => word.charAt(0) + word.slice(1).toLowerCase())
+  .join(' ')
+
+// Labels are drawn in map coordinates, which have y running up, on an SVG with y running down.
+const baselinePath = (baseline: [number, number][]) => baseline
+  .map(([x, y], index) => `${index === 0 ? 'M' : 'L'}${x} ${MAP_HEIGHT - y}`)
+  .join(' ')
+
+export default function Labels({ bounds, countries, selectedCountry, selectedState, stateCoords, provinceCoords, renderBreaker }: LabelsProps) {
+  const [stateLabels, setStateLabels] = useState<{ [key: string]: Label }>({})
+  const [provinceLabels, setProvinceLabels] = useState<{ [key: string]: Label }>({})
+
+  // Countries come with their labels, states and provinces are only labelled once selected.
+  useEffect(() => {
+    if (!selectedCountry) { return setStateLabels({}) }
+    const shapes: { [key: string]: [string, Coords] } = {}
+    selectedCountry.states.forEach((state) => {
+      const coords = stateCoords[`${selectedCountry.name}:${state.name}`]
+      if (coords) { shapes[state.name] = [stateLabelText(state.name), coords] }
+    })
+    invoke<{ [key: string]: Label }>('get_labels', { shapes }).then(setStateLabels)
+  }, [selectedCountry?.name, renderBreaker])
+
+  useEffect(() => {
+    if (!selectedState) { return setProvinceLabels({}) }
+    const shapes: { [key: string]: [string, Coords] } = {}
+    selectedState.provinces.forEach((province) => {
+      if (provinceCoords[province]) { shapes[province] = [province, provinceCoords[province]] }
+    })
+    invoke<{ [key: string]: Label }>('get_labels', { shapes }).then(setProvinceLabels)
+  }, [selectedState?.name, renderBreaker])
+
+  const labels: [string, string, Label][] = [
+    ...countries.flatMap((country): [string, string, Label][] => country.label && country.name !== selectedCountry?.name ? [[`country-${country.name}`, country.name, country.label]] : []),
+    ...Object.entries(stateLabels).filter(([state]) => state !== selectedState?.name).map(([state, label]): [string, string, Label] => [`state-${state}`, stateLabelText(state), label]),
+    ...Object.entries(provinceLabels).map(([province, label]): [string, string, Label] => [`province-${province}`, province, label]),
+  ]
+
+  return (
+    <SVGOverlay bounds={bounds} attributes={{ viewBox: `0 0 ${MAP_WIDTH} ${MAP_HEIGHT}`, style: 'pointer-events: none' }}>
+      <defs>
+        { labels.map(([id, , label]) => <path key={id} id={id} d={baselinePath(label.baseline)} />) }
+      </defs>
+      { labels.map(([id, text, label]) => (
+        <text key={id} fontSize={label.font_size} fill="white" stroke="black" strokeWidth={label.font_size / 12} paintOrder="stroke" dominantBaseline="middle">
+          <textPath href={`#${id}`} startOffset="50%" textAnchor="middle">{text}</textPath>
+        </text>
+      )) }
+    </SVGOverlay>
+  )
+}
@@ -1,17 +1,27 @@
-import { MapContainer } from 'react-leaflet'
+import { LayersControl, MapContainer } from 'react-leaflet'
 import { CRS, LatLngBoundsExpression, LeafletMouseEvent } from 'leaflet'
 import './Map.css'
-import { useEffect, useState } from 'react'
+import { useEffect, useRef, useState } from 'react'
 import { listen } from '@tauri-apps/api/event'
 import { invoke } from '@tauri-apps/api/core'
 import Countries, { Country } from './Countries'
 import States, { State } from './States'
 import Provinces from './Provinces'
 import Background from './Background'
+import Crossings from './Crossings'
 import { exists, readTextFile, writeTextFile } from '@tauri-apps/plugin-fs';
 import { appCacheDir } from '@tauri-apps/api/path'
+import { ask, message } from '@tauri-apps/plugin-dialog'
 import SelectionInfo from './info/SelectionInfo'
 import CreateCountry, { CountryDefinition } from './CreateCountry'
+import { ProvinceKind, Topology, topologyFileName, topologyToCoords } from './Topology'
+import ZoomLevel from './ZoomLevel'
+import Labels from './Labels'
+import ExportMap from './ExportMap'
+import MapModes, { MapModeColors } from './MapModes'
+import MapModeLayer from './MapModeLayer'
+import StrategicRegions, { StrategicRegion } from './StrategicRegions'
+import StrategicRegionLayer from './StrategicRegionLayer'
 
 export type Coords = [number, number][][]
 
@@ -29,6 +39,12 @@ type TransferStateResponse = {
   state_coords: Coords
 }
 
+type ExternalChangeConflict = {
+  changes: string[],
+  paths: string[],
+  in_working_dir: boolean
+}
+
 type TransferProvinceResponse = {
   to_country: Country,
   from_country: Country,
@@ -36,17 +52,35 @@ type TransferProvinceResponse = {
   from_state_coords: Coords
 }
 
+// The backend refuses provinces no country can own, such as sea and lakes
+const invokeProvinceTransfer = async (command: string, args: { [key: string]: unknown }) => {
+  try {
+    return await invoke<TransferProvinceResponse>(command, args)
+  } catch (error) {
+    await message(String(error), { title: 'Transfer refused', kind: 'error' })
+    return null
+  }
+}
+
 const bounds: LatLngBoundsExpression = [[0, 0], [3616, 8192]]
 
-const getProvinceCoords = async () => {
+type Outlines = {
+  [key: string]: Coords
+}
+
+// Falls back to the full topology for caches from before the simplified levels
+const getProvinceCoords = async (level: number) => {
   const cacheDir = await appCacheDir()
-  const path = `${cacheDir}/provinces.json`
+  const levelPath = `${cacheDir}/${topologyFileName(level)}`
+  const path = await exists(levelPath) ? levelPath : `${cacheDir}/${topologyFileName(0)}`
   const fileExists = await exists(path)
   
   if (!fileExists) { return {} }
 
-  const fileContents = await readTextFile(`${cacheDir}/provinces.json`);
-  return JSON.parse(fileContents) as ProvincesCoords
+  const fileContents = await readTextFile(path);
+  // Sea and lakes are never part of a state, so there is nothing to draw them for
+  const isOwnable = ({ properties }: { properties?: { kind: ProvinceKind } }) => properties?.kind !== 'sea' && properties?.kind !== 'lake'
+  return topologyToCoords(JSON.parse(fileContents) as Topology, 'provinces', isOwnable) as ProvincesCoords
 }
 
 const getStateCoords = async () => {
@@ -78,12 +112,22 @@ export default function Map() {
   const [selectedCountry, setSelectedCountry] = useState<Country | null>(null)
   const [selectedState, setSelectedState] = useState<State | null>(null)
   const [selectedProvince, setSelectedProvince] = useState<string | null>(null)
+  const [level, setLevel] = useState(0)
+  // Only the full topology until the backend says how many simplified levels it has written
+  const [levelCount, setLevelCount] = useState(1)
+  const [mapModeColors, setMapModeColors] = useState<MapModeColors | null>(null)
+  const [cachedAt, setCachedAt] = useState(0)
+  const [strategicRegions, setStrategicRegions] = useState<[StrategicRegion, Coords][]>([])
+  const [showStrategicRegions, setShowStrategicRegions] = useState(false)
+  const [selectedRegion, setSelectedRegion] = useState<string | null>(null)
+  const levelRef = useRef(level)
   const [renderBreaker, setRenderBreaker] = useState(Date.now())
   const forceRerender = () => setRenderBreaker(Date.now())
 
   useEffect(() => {
-    const unlistenToProvinceCoords = listen<ProvincesCoords>('load-province-coords', () => {
-      getProvinceCoords().then((provinceCoords) => {
+    const unlistenToProvinceCoords = listen<number>('load-province-coords', ({ payload }) => {
+      setLevelCount(payload)
+      getProvinceCoords(levelRef.current).then((provinceCoords) => {
         console.log(provinceCoords)
         setProvinceCoords(provinceCoords)
       })
@@ -107,7 +151,18 @@ export default function Map() {
       })
     })
 
-    getProvinceCoords().then((provinceCoords) => setProvinceCoords(provinceCoords))
+    const unlistenToExternalChangeConflict = listen<ExternalChangeConflict>('external-change-conflict', async ({ payload }) => {
+      const files = payload.paths.join('\n')
+      if (payload.in_working_dir) {
+        await ask(`These files in the working directory changed and will be overwritten by your next save:\n${files}`, { title: 'External changes', kind: 'warning' })
+        return
+      }
+
+      const reload = await ask(`These game files changed while you have unsaved edits:\n${files}\n\nReload them and discard your edits?`, { title: 'External changes', kind: 'warning' })
+      if (reload) { invoke('apply_external_changes') }
+    })
+
+    getProvinceCoords(levelRef.current).then((provinceCoords) => setProvinceCoords(provinceCoords))
     getStateCoords().then((stateCoords) => setStateCoords(stateCoords))
     getCountries().then((countries) => {
       setCountries(countries)
@@ -118,22 +173,56 @@ export default function Map() {
       unlistenToProvinceCoords.then((unlisten) => unlisten())
       unlistenToStateData.then((unlisten) => unlisten())
       unlistenToCountryData.then((unlisten) => unlisten())
+      unlistenToExternalChangeConflict.then((unlisten) => unlisten())
     }
   }, [setStateCoords])
 
+  const hasCountries = countries.length > 0
+
+  // Redraws every layer at the level of detail for the zoom. Countries and states are dissolved by the backend, which knows their provinces' shared borders.
+  useEffect(() => {
+    levelRef.current = level
+    getProvinceCoords(level).then((provinceCoords) => setProvinceCoords(provinceCoords))
+
+    const groups: { [key: string]: string[] } = {}
+    countries.forEach((country) => {
+      groups[country.name] = country.states.flatMap((state) => state.provinces)
+      country.states.forEach((state) => { groups[`${country.name}:${state.name}`] = state.provinces })
+    })
+    if (Object.keys(groups).length === 0) { return }
+
+    invoke<Outlines>('get_simplified_outlines', { level, groups }).then((outlines) => {
+      setCountries((countries) => countries.map((country) => ({ ...country, coordinates: outlines[country.name] || country.coordinates })))
+      setStateCoords((stateCoords) => {
+        const stateCoordsCopy = { ...stateCoords }
+        Object.keys(stateCoordsCopy).forEach((key) => { if (outlines[key]) { stateCoordsCopy[key] = outlines[key] } })
+        return stateCoordsCopy
+      })
+      forceRerender()
+    })
+  }, [level, hasCountries])
+
+  // Regions are outlined from the states' provinces, so they follow the level like everything else
+  useEffect(() => {
+    if (!hasCountries) { return }
+    invoke<[StrategicRegion, Coords][]>('get_strategic_regions', { level }).then((regions) => {
+      setStrategicRegions(regions)
+      forceRerender()
+    })
+  }, [level, hasCountries])
+
   const handleControlClickCountry = async (event: LeafletMouseEvent) => {
     if (selectedCountry && selectedState) {
       if (selectedProvince) {
         const toCountry = event.sourceTarget.feature.properties as Country
-        const { to_country: responseToCountry, from_country: responseFromCountry, to_state_coords: responseToStateCoords, from_state_coords: responseFromStateCoords } = await invoke<TransferProvinceResponse>("transfer_province", { 
+        const response = await invokeProvinceTransfer("transfer_province", { 
           state: selectedState.name,
           province: selectedProvince,
           fromCountry: selectedCountry,
-          toCountry,
-          fromCoords: stateCoords[`${selectedCountry.name}:${selectedState.name}`],
-          toCoords: stateCoords[`${toCountry.name}:${selectedState.name}`] || [],
-          provinceCoords: provinceCoords[selectedProvince]
+          toCountry
         })
+        if (!response) { return }
+        const { to_country: responseToCountry, from_country: responseFromCountry, to_state_coords: responseToStateCoords, from_state_coords: responseFromStateCoords } = response
 
         handleTransferResponse({ toCountry: responseToCountry, fromCountry: responseFromCountry, toStateCoords: responseToStateCoords, fromStateCoords: responseFromStateCoords, selectedState })
         setSelectedState((state) => state?.name === selectedState.name ? responseFromCountry.states.find((state) => state.name === selectedState.name) || null : state)
@@ -143,9 +232,7 @@ export default function Map() {
         const { to_country: responseToCountry, from_country: responseFromCountry, state_coords: responseStateCoords } = await invoke<TransferStateResponse>("transfer_state", { 
           state: selectedState.name,
           fromCountry: selectedCountry,
-          toCountry,
-          fromCoords: stateCoords[`${selectedCountry.name}:${selectedState.name}`],
-          toCoords: stateCoords[`${toCountry.name}:${selectedState.name}`] || [] 
+          toCountry
         })
 
         handleTransferResponse({ toCountry: responseToCountry, fromCountry: responseFromCountry, toStateCoords: responseStateCoords, fromStateCoords: [], selectedState })
@@ -262,6 +349,7 @@ export default function Map() {
     if (stateFileExists && countryFileExists) {
       await writeTextFile(statePath, JSON.stringify(states))
       await writeTextFile(countryPath, JSON.stringify(countries))
+      setCachedAt(Date.now())
     }
   }
 
@@ -274,14 +362,14 @@ export default function Map() {
   const handleCreateCountry = async (countryDefinition: CountryDefinition) => {
     if (selectedCountry && selectedState) {
       if (selectedProvince) {
-        const { to_country, from_country, to_state_coords, from_state_coords } = await invoke<TransferProvinceResponse>("create_country_from_province", {
+        const response = await invokeProvinceTransfer("create_country_from_province", {
           countryDefinition,
           fromCountry: selectedCountry,
           state: selectedState.name,
-          province: selectedProvince,
-          stateCoords: stateCoords[`${selectedCountry.name}:${selectedState.name}`],
-          provinceCoords: provinceCoords[selectedProvince]
+          province: selectedProvince
         })
+        if (!response) { return }
+        const { to_country, from_country, to_state_coords, from_state_coords } = response
         handleTransferResponse({ toCountry: to_country, fromCountry: from_country, toStateCoords: to_state_coords, fromStateCoords: from_state_coords, selectedState })
         setSelectedState((state) => state?.name === selectedState.name ? from_country.states.find((state) => state.name === selectedState.name) || null : state)
         setSelectedProvince((province) => province === selectedProvince ? null : province)
@@ -289,8 +377,7 @@ export default function Map() {
         const { to_country: responseToCountry, from_country: responseFromCountry, state_coords: responseStateCoords } = await invoke<TransferStateResponse>("create_country", { 
           countryDefinition,
           fromCountry: selectedCountry,
-          state: selectedState.name,
-          coords: stateCoords[`${selectedCountry.name}:${selectedState.name}`]
+          state: selectedState.name
         })
         handleTransferResponse({ toCountry: responseToCountry, fromCountry: responseFromCountry, toStateCoords: responseStateCoords, fromStateCoords: [], selectedState })
         setSelectedState((state) => state?.name === selectedState.name ? null : state)
@@ -301,13 +388,24 @@ export default function Map() {
   return (
     <div>
       <MapContainer center={[0, 0]} minZoom={-2} maxZoom={2} doubleClickZoom={false} crs={CRS.Simple} bounds={bounds}>
-        <Background bounds={bounds} />
+        <Background bounds={bounds}>
+          <LayersControl.Overlay name="Straits and crossings">
+            <Crossings />
+          </LayersControl.Overlay>
+        </Background>
+        <ZoomLevel levelCount={levelCount} onLevelChange={setLevel} />
         <Countries countries={countries} renderBreaker={renderBreaker} eventHandlers={{ click: handleClickCountry }} />
+        { mapModeColors && <MapModeLayer colors={mapModeColors} stateCoords={stateCoords} provinceCoords={provinceCoords} renderBreaker={renderBreaker} /> }
         { selectedCountry && <States country={selectedCountry} stateCoords={stateCoords} renderBreaker={renderBreaker} eventHandlers={{ click: handleClickState }} selectedState={selectedState} /> }
         { selectedState && <Provinces state={selectedState} provinceCoords={provinceCoords} renderBreaker={renderBreaker} eventHandlers={{ click: handleClickProvince }} selectedProvince={selectedProvince} /> }
+        { showStrategicRegions && <StrategicRegionLayer regions={strategicRegions} selectedRegion={selectedRegion} onSelectRegion={setSelectedRegion} renderBreaker={renderBreaker} /> }
+        <Labels bounds={bounds} countries={countries} selectedCountry={selectedCountry} selectedState={selectedState} stateCoords={stateCoords} provinceCoords={provinceCoords} renderBreaker={renderBreaker} />
       </MapContainer>
       { selectedCountry && <SelectionInfo selectedCountry={selectedCountry} selectedState={selectedState} selectedProvince={selectedProvince} onCountryChange={handleCountryChange} /> }
       { selectedState && <CreateCountry createdCountries={countries} onCreateCountry={handleCreateCountry} /> }
+      { hasCountries && <ExportMap /> }
+      { hasCountries && <StrategicRegions regions={strategicRegions.map(([region]) => region)} selectedRegion={selectedRegion} onSelectRegion={setSelectedRegion} showOverlay={showStrategicRegions} onShowOverlayChange={setShowStrategicRegions} countries={countries} cachedAt={cachedAt} /> }
+      { hasCountries && <MapModes cachedAt={cachedAt} onColorsChange={(colors) => { setMapModeColors(colors); forceRerender() }} /> }
     </div>
   ) 
 }
@@ -0,0 +1,30 @@
+import { GeoJSON } from 'react-leaflet'
+import { FeatureCollection, Geometry } from 'geojson'
+import { Coords } from './Map'
+import { MapModeColors, rgb } from './MapModes'
+
+type MapModeLayerProps = {
+  colors: MapModeColors
+  stateCoords: { [key: string]: Coords }
+  provinceCoords: { [key: string]: Coords }
+  renderBreaker: number
+}
+
+// Drawn over the countries without taking their clicks, so selecting and transferring still work
+export default function MapModeLayer({ colors, stateCoords, provinceCoords, renderBreaker }: MapModeLayerProps) {
+  const shapes = [
+    ...Object.entries(colors.states).filter(([key]) => stateCoords[key]).map(([key, color]) => ({ coordinates: stateCoords[key], color })),
+    ...Object.entries(colors.provinces).filter(([key]) => provinceCoords[key]).map(([key, color]) => ({ coordinates: provinceCoords[key], color })),
+  ]
+
+  const data: FeatureCollection<Geometry, { color: string }> = {
+    type: "FeatureCollection",
+    features: shapes.map(({ coordinates, color }) => ({
+      type: "Feature",
+      properties: { color: rgb(color) },
+      geometry: { type: "Polygon", coordinates }
+    }))
+  }
+
+  return <GeoJSON data={data} key={renderBreaker} interactive={false} style={(feature) => ({ fillColor: feature?.properties.color, fillOpacity: 0.85, stroke: false })} />
+}
@@ -0,0 +1,72 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+
+type Color = [number, number, number]
+
+export type MapModeColors = {
+  states: { [key: string]: Color }
+  provinces: { [key: string]: Color }
+  legend: { label: string, color: Color }[]
+}
+
+export type MapMode = 'political' | 'culture' | 'religion' | 'pop_density' | 'building_levels' | 'tech_tier' | 'changed_from_vanilla'
+
+const MAP_MODES: [MapMode, string][] = [
+  ['political', 'Political'],
+  ['culture', 'Culture'],
+  ['religion', 'Religion'],
+  ['pop_density', 'Population density'],
+  ['building_levels', 'Building levels'],
+  ['tech_tier', 'Tech tier'],
+  ['changed_from_vanilla', 'Changed from vanilla'],
+]
+
+export const rgb = ([r, g, b]: Color) => `rgb(${r}, ${g}, ${b})`
+
+type MapModesProps = {
+  // Bumped whenever the countries the backend colours by have been written to the cache
+  cachedAt: number
+  onColorsChange: (colors: MapModeColors | null) => void
+}
+
+export default function MapModes({ cachedAt, onColorsChange }: MapModesProps) {
+  const [mapMode, setMapMode] = useState<MapMode>('political')
+  const [buildingGroup, setBuildingGroup] = useState('bg_manufacturing')
+  const [colors, setColors] = useState<MapModeColors | null>(null)
+
+  useEffect(() => {
+    if (mapMode === 'political') {
+      setColors(null)
+      onColorsChange(null)
+      return
+    }
+    const mode = mapMode === 'building_levels' ? { mode: mapMode, building_group: buildingGroup } : { mode: mapMode }
+    invoke<MapModeColors>('get_map_mode', { mode }).then((colors) => {
+      setColors(colors)
+      onColorsChange(colors)
+    })
+  }, [mapMode, buildingGroup, cachedAt])
+
+  const handleKeyDown = (event: React.KeyboardEvent) => { event.stopPropagation() }
+
+  return (
+    <div className="fixed bottom-4 right-4 z-[400] card card-compact bg-base-100 shadow-xl w-64" onKeyDown={handleKeyDown}>
+      <div className="card-body">
+        <select className="select select-bordered select-sm" value={mapMode} onChange={(event) => setMapMode(event.target.value as MapMode)}>
+          {MAP_MODES.map(([mode, label]) => <option key={mode} value={mode}>{label}</option>)}
+        </select>
+        { mapMode === 'building_levels' && <input type="text" className="input input-bordered input-sm" value={buildingGroup} placeholder="Building group" onChange={(event) => setBuildingGroup(event.target.value)} /> }
+        { colors && (
+          <ul className="max-h-48 overflow-y-auto">
+            {colors.legend.map(({ label, color }) => (
+              <li key={label} className="flex items-center gap-2">
+                <span className="inline-block w-3 h-3 border border-black" style={{ backgroundColor: rgb(color) }} />
+                <span className="text-sm">{label}</span>
+              </li>
+            ))}
+          </ul>
+        ) }
+      </div>
+    </div>
+  )
+}
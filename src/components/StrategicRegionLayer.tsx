@@ -0,0 +1,37 @@
+import { GeoJSON } from 'react-leaflet'
+import { FeatureCollection, Geometry } from 'geojson'
+import { LeafletMouseEvent } from 'leaflet'
+import { Coords } from './Map'
+import { rgb } from './MapModes'
+import { StrategicRegion } from './StrategicRegions'
+
+type StrategicRegionLayerProps = {
+  regions: [StrategicRegion, Coords][]
+  selectedRegion: string | null
+  onSelectRegion: (region: string) => void
+  renderBreaker: number
+}
+
+// Only the outlines take clicks, so states and provinces inside a region can still be selected
+export default function StrategicRegionLayer({ regions, selectedRegion, onSelectRegion, renderBreaker }: StrategicRegionLayerProps) {
+  const data: FeatureCollection<Geometry, { name: string, color: string }> = {
+    type: "FeatureCollection",
+    features: regions.filter(([, coordinates]) => coordinates.length > 0).map(([region, coordinates]) => ({
+      type: "Feature",
+      properties: { name: region.name, color: region.color ? rgb(region.color) : '#000000' },
+      geometry: { type: "Polygon", coordinates }
+    }))
+  }
+
+  return <GeoJSON
+    data={data}
+    key={`${renderBreaker}-${selectedRegion}`}
+    eventHandlers={{ click: (event: LeafletMouseEvent) => onSelectRegion(event.sourceTarget.feature.properties.name) }}
+    style={(feature) => ({
+      color: feature?.properties.color,
+      weight: feature?.properties.name === selectedRegion ? 5 : 3,
+      dashArray: '8 6',
+      fill: false,
+    })}
+  />
+}
@@ -0,0 +1,96 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { message } from "@tauri-apps/plugin-dialog";
+import { Country } from "./Countries";
+
+export type StrategicRegion = {
+  name: string
+  file: string
+  color: [number, number, number] | null
+  capital_province: string | null
+  states: string[]
+}
+
+type RegionTotals = {
+  pops: number
+  buildings: { [key: string]: number }
+  owners: { [key: string]: number }
+}
+
+type StrategicRegionsProps = {
+  regions: StrategicRegion[]
+  selectedRegion: string | null
+  onSelectRegion: (region: string | null) => void
+  showOverlay: boolean
+  onShowOverlayChange: (show: boolean) => void
+  countries: Country[]
+  // Bumped whenever the countries the totals are counted from have been written to the cache
+  cachedAt: number
+}
+
+export default function StrategicRegions({ regions, selectedRegion, onSelectRegion, showOverlay, onShowOverlayChange, countries, cachedAt }: StrategicRegionsProps) {
+  const [totals, setTotals] = useState<RegionTotals | null>(null)
+  const [toCountry, setToCountry] = useState('')
+  const [transferring, setTransferring] = useState(false)
+
+  useEffect(() => {
+    if (!selectedRegion) {
+      setTotals(null)
+      return
+    }
+    invoke<RegionTotals | null>('get_region_totals', { region: selectedRegion }).then(setTotals)
+  }, [selectedRegion, cachedAt])
+
+  const handleKeyDown = (event: React.KeyboardEvent) => { event.stopPropagation() }
+
+  // The backend applies it as an edit script and has the map reload the countries it touched
+  const handleTransfer = async () => {
+    if (!selectedRegion || !toCountry) { return }
+    setTransferring(true)
+    try {
+      await invoke('transfer_strategic_region', { region: selectedRegion, to: toCountry })
+    } catch (error) {
+      await message(String(error), { title: 'Transfer refused', kind: 'error' })
+    } finally {
+      setTransferring(false)
+    }
+  }
+
+  return (
+    <div className="dropdown dropdown-top fixed bottom-4 left-40 z-[400]" onKeyDown={handleKeyDown}>
+      <div tabIndex={0} role="button" className="btn">Strategic Regions</div>
+      <div tabIndex={0} className="dropdown-content z-[1] card card-compact bg-base-100 shadow-xl w-72 p-2">
+        <label className="label cursor-pointer">
+          <span className="label-text">Show outlines</span>
+          <input type="checkbox" className="toggle toggle-sm" checked={showOverlay} onChange={() => onShowOverlayChange(!showOverlay)} />
+        </label>
+        <select className="select select-bordered select-sm" value={selectedRegion || ''} onChange={(event) => onSelectRegion(event.target.value || null)}>
+          <option value="">No region selected</option>
+          {regions.map((region) => <option key={region.name} value={region.name}>{region.name.replace('sr:', '')}</option>)}
+        </select>
+        { totals && (
+          <div className="max-h-64 overflow-y-auto text-sm">
+            <p>Population: {totals.pops.toLocaleString()}</p>
+            <p className="font-bold mt-2">Owners</p>
+            <ul>
+              {Object.entries(totals.owners).map(([tag, provinces]) => <li key={tag}>{tag}: {provinces} provinces</li>)}
+            </ul>
+            <p className="font-bold mt-2">Buildings</p>
+            <ul>
+              {Object.entries(totals.buildings).map(([building, level]) => <li key={building}>{building}: {level}</li>)}
+            </ul>
+          </div>
+        ) }
+        { selectedRegion && (
+          <div className="join">
+            <select className="select select-bordered select-sm join-item grow" value={toCountry} onChange={(event) => setToCountry(event.target.value)}>
+              <option value="">Transfer to...</option>
+              {countries.map((country) => <option key={country.name} value={country.name}>{country.name}</option>)}
+            </select>
+            <button className="btn btn-sm btn-primary join-item" disabled={!toCountry || transferring} onClick={handleTransfer}>Transfer</button>
+          </div>
+        ) }
+      </div>
+    </div>
+  )
+}
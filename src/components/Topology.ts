@@ -0,0 +1,48 @@
+import { Coords } from "./Map"
+
+export type ProvinceKind = "land" | "sea" | "lake" | "impassable"
+
+type Geometry = {
+  type: "MultiPolygon",
+  id: string,
+  arcs: number[][][],
+  properties?: { kind: ProvinceKind, terrain: string | null }
+}
+
+export type Topology = {
+  type: "Topology",
+  transform: { scale: [number, number], translate: [number, number] },
+  arcs: [number, number][][],
+  objects: { [name: string]: { type: "GeometryCollection", geometries: Geometry[] } }
+}
+
+const decodeArcs = ({ arcs, transform: { scale, translate } }: Topology) => arcs.map((arc) => {
+  let x = 0
+  let y = 0
+  return arc.map(([dx, dy]) => {
+    x += dx
+    y += dy
+    return [x * scale[0] + translate[0], y * scale[1] + translate[1]] as [number, number]
+  })
+})
+
+// Rings are flattened the same way the backend writes coords: each outer ring followed by its holes
+export const topologyToCoords = (topology: Topology, object: string, include: (geometry: Geometry) => boolean = () => true) => {
+  const arcs = decodeArcs(topology)
+  const ringCoords = (ring: number[]) => ring.flatMap((arcRef, index) => {
+    const arc = arcRef >= 0 ? arcs[arcRef] : [...arcs[~arcRef]].reverse()
+    return index === 0 ? arc : arc.slice(1)
+  })
+
+  const coords: { [id: string]: Coords } = {}
+  topology.objects[object].geometries.filter(include).forEach((geometry) => {
+    coords[geometry.id] = geometry.arcs.flat().map(ringCoords)
+  })
+  return coords
+}
+
+// At zoom 1 and in, a pixel of the province map is at least two on screen, so nothing is simplified.
+// Level 0 is the full topology and each of the `levelCount` levels after it is simplified further, the backend sends the count with the topology
+export const levelForZoom = (zoom: number, levelCount: number) => Math.min(Math.max(Math.round(1 - zoom), 0), levelCount - 1)
+
+export const topologyFileName = (level: number) => level === 0 ? 'topology.json' : `topology-${level}.json`
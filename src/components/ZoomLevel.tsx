@@ -0,0 +1,13 @@
+import { useEffect } from 'react'
+import { useMapEvents } from 'react-leaflet'
+import { levelForZoom } from './Topology'
+
+export default function ZoomLevel({ levelCount, onLevelChange }: { levelCount: number, onLevelChange: (level: number) => void }) {
+  const map = useMapEvents({
+    zoomend: () => onLevelChange(levelForZoom(map.getZoom(), levelCount))
+  })
+
+  useEffect(() => onLevelChange(levelForZoom(map.getZoom(), levelCount)), [map, levelCount])
+
+  return null
+}
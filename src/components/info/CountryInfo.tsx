@@ -35,10 +35,19 @@ export default function CountryInfo({ country, onChangeCountry }: CountryInfoPro
   const handleGetTechnologies = async () => { setTechnologies((await invoke<Technology[]>("get_technologies", {}))) }
   const [technologies, setTechnologies] = useState<Technology[]>([])
   useEffect(() => { handleGetTechnologies() }, [])
+  // Parts of the country that neither borders nor straits connect, largest first
+  const [territories, setTerritories] = useState<string[][]>([])
+  useEffect(() => {
+    const provinces = country.states.flatMap((state) => state.provinces)
+    invoke<string[][]>("get_territories", { provinces }).then(setTerritories)
+  }, [country])
   const filteredTechnologies = technologies.filter(tech => !country.setup.technologies_researched.includes(tech.name))
 
   return (
     <div>
+      { territories.length > 1 && (
+        <h2 className="text-warning">Territory: {territories.length} unconnected parts, {territories.slice(1).map((part) => part.length).join(', ')} provinces cut off</h2>
+      )}
       <h2>Base Tech: 
         <select className="select select-bordered select-xs w-full max-w-xs" value={country.setup.base_tech || ''} onChange={handleChangeBaseTech}>
           <option value="tier_1">tier_1</option>
@@ -4,6 +4,7 @@ import { useEffect, useRef } from "react"
 import { DomEvent } from "leaflet"
 import StateInfo from "./StateInfo"
 import CountryInfo from "./CountryInfo"
+import Flag from "../Flag"
 
 type SelectionInfoProps = {
   selectedCountry: Country
@@ -27,7 +28,7 @@ export default function SelectionInfo({ selectedCountry, selectedState, selected
   return (
     <div ref={infoRef} className='fixed top-4 right-4 card card-compact bg-base-100 z-[400]'>
       <div className="card-body">
-        <h1 className="card-title justify-end">Country: {selectedCountry.name}</h1>
+        <h1 className="card-title justify-end"><Flag tag={selectedCountry.name} size={36} fallbackColor={selectedCountry.color} /> Country: {selectedCountry.name}</h1>
         { 
           selectedProvince ? 
             <h3 className="card-title text-sm">Province: {selectedProvince}</h3> :
@@ -2,8 +2,11 @@ import { useEffect, useRef, useState } from "react"
 import { State } from "../States"
 import PopsInfo from "./PopsInfo"
 import StateBuildingsInfo from "./StateBuildingsInfo"
+import StateRegionInfo from "./StateRegionInfo"
 
-type TabSelection = 'population' | 'buildings'
+type TabSelection = 'population' | 'buildings' | 'region'
+
+const TABS: TabSelection[] = ['population', 'buildings', 'region']
 
 type StateInfoProps = {
   selectedState: State,
@@ -20,7 +23,7 @@ export default function StateInfo({ selectedState, onStateChange }: StateInfoPro
     const handleTabPress = (event: KeyboardEvent) => {
       if (event.key === 'Tab') { 
         event.preventDefault()
-        setTabSelection((tabSelection) => tabSelection === 'population' ? 'buildings' : 'population') 
+        setTabSelection((tabSelection) => TABS[(TABS.indexOf(tabSelection) + 1) % TABS.length])
       }
     }
     window.addEventListener('keydown', handleTabPress)
@@ -55,11 +58,13 @@ export default function StateInfo({ selectedState, onStateChange }: StateInfoPro
         <div role="tablist" className="tabs tabs-boxed tooltip tooltip-left justify-self-end" data-tip='Tab'>
           <div role="tab" className={`tab ${isSelected('population')}`} onClick={() => handleTabSelection('population')}>Population</div>
           <div role="tab" className={`tab ${isSelected('buildings')}`} onClick={() => handleTabSelection('buildings')}>Buildings</div>
+          <div role="tab" className={`tab ${isSelected('region')}`} onClick={() => handleTabSelection('region')}>Region</div>
         </div>
       </div>
       <div className="card block" ref={divRef} tabIndex={0} onKeyDown={handleOnKeyDown}>
         { tabSelection === 'population' && <PopsInfo key={selectedState.name} pops={selectedState.pops} onPopsChange={(pops) => onStateChange({...selectedState, pops})} /> }
         { tabSelection === 'buildings' && <StateBuildingsInfo buildings={selectedState.state_buildings} onBuildingsChange={(state_buildings) => onStateChange({...selectedState, state_buildings})} /> }
+        { tabSelection === 'region' && <StateRegionInfo state={selectedState.name} /> }
       </div>
     </div>
   )
@@ -0,0 +1,147 @@
+import { useEffect, useState } from "react"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { message } from "@tauri-apps/plugin-dialog"
+
+type Hubs = {
+  city: string | null
+  port: string | null
+  farm: string | null
+  mine: string | null
+  wood: string | null
+}
+
+type Resource = {
+  kind: string
+  depleted_type: string | null
+  discovered_amount: number | null
+  undiscovered_amount: number | null
+}
+
+export type StateRegion = {
+  name: string
+  file: string
+  id: number | null
+  subsistence_building: string | null
+  provinces: string[]
+  impassable: string[]
+  prime_land: string[]
+  traits: string[]
+  hubs: Hubs
+  arable_land: number
+  arable_resources: string[]
+  capped_resources: { [key: string]: number }
+  resources: Resource[]
+  naval_exit_id: number | null
+}
+
+type ScriptReport = {
+  operations: { operation: string, countries: string[], notes: string[] }[]
+}
+
+// Building groups read better without their prefix, bg_iron_mining as iron mining
+const groupName = (group: string) => group.replace(/^(bg|state_trait)_/, '').replace(/_/g, ' ')
+
+// What the map itself gives the state in map_data/state_regions, whoever owns it
+export default function StateRegionInfo({ state }: { state: string }) {
+  const [region, setRegion] = useState<StateRegion | null>(null)
+  const [loaded, setLoaded] = useState(false)
+  const [regionNames, setRegionNames] = useState<string[]>([])
+  const [selectedProvinces, setSelectedProvinces] = useState<string[]>([])
+  const [target, setTarget] = useState('')
+  const [newState, setNewState] = useState('')
+  const [editing, setEditing] = useState(false)
+
+  useEffect(() => {
+    const loadRegion = () => {
+      invoke<StateRegion | null>('get_state_region', { state }).then((region) => {
+        setRegion(region)
+        setLoaded(true)
+      })
+      invoke<StateRegion[]>('get_state_regions').then((regions) => setRegionNames(regions.map((region) => region.name)))
+    }
+    setLoaded(false)
+    setSelectedProvinces([])
+    loadRegion()
+    const unlistenToStateRegions = listen('state-regions-changed', loadRegion)
+    return () => { unlistenToStateRegions.then((unlisten) => unlisten()) }
+  }, [state])
+
+  const toggleProvince = (province: string) => setSelectedProvinces((selected) =>
+    selected.includes(province) ? selected.filter((other) => other !== province) : [...selected, province])
+
+  // Applied as an edit script, which has the map reload the countries whose states changed
+  const editRegion = async (command: string, args: object) => {
+    setEditing(true)
+    try {
+      const report = await invoke<ScriptReport>(command, args)
+      const notes = report.operations.flatMap((operation) => operation.notes)
+      if (notes.length > 0) { await message(notes.join('\n'), { title: 'State regions edited', kind: 'info' }) }
+      setSelectedProvinces([])
+    } catch (error) {
+      await message(String(error), { title: 'Edit refused', kind: 'error' })
+    } finally {
+      setEditing(false)
+    }
+  }
+
+  if (!loaded) { return <p className="text-sm">Loading...</p> }
+  if (!region) { return <p className="text-sm">Not in map_data/state_regions</p> }
+
+  const hubs = Object.entries(region.hubs).filter(([, province]) => province)
+
+  return (
+    <div className="max-h-96 overflow-y-auto text-sm">
+      <p>Arable land: {region.arable_land}</p>
+      <p>Provinces: {region.provinces.length}{region.impassable.length > 0 && `, ${region.impassable.length} impassable`}{region.prime_land.length > 0 && `, ${region.prime_land.length} prime land`}</p>
+      { region.naval_exit_id !== null && <p>Naval exit: {region.naval_exit_id}</p> }
+      { region.traits.length > 0 && <p>Traits: {region.traits.map(groupName).join(', ')}</p> }
+      <p className="font-bold mt-2">Arable resources</p>
+      <p>{region.arable_resources.map(groupName).join(', ') || 'None'}</p>
+      <p className="font-bold mt-2">Capped resources</p>
+      <ul>
+        {Object.entries(region.capped_resources).map(([group, levels]) => <li key={group}>{groupName(group)}: {levels}</li>)}
+      </ul>
+      { region.resources.length > 0 && (
+        <>
+          <p className="font-bold mt-2">Deposits</p>
+          <ul>
+            {region.resources.map((resource, index) => (
+              <li key={index}>
+                {groupName(resource.kind)}
+                {resource.discovered_amount !== null && `: ${resource.discovered_amount}`}
+                {resource.undiscovered_amount !== null && ` (${resource.undiscovered_amount} undiscovered)`}
+                {resource.depleted_type && `, depletes to ${groupName(resource.depleted_type)}`}
+              </li>
+            ))}
+          </ul>
+        </>
+      )}
+      <p className="font-bold mt-2">Hubs</p>
+      <ul>
+        {hubs.map(([kind, province]) => <li key={kind}>{kind}: {province}</li>)}
+      </ul>
+      <p className="font-bold mt-2">Borders</p>
+      <div className="max-h-32 overflow-y-auto">
+        {region.provinces.map((province) => (
+          <label key={province} className="label cursor-pointer justify-start gap-2 py-0">
+            <input type="checkbox" className="checkbox checkbox-xs" checked={selectedProvinces.includes(province)} onChange={() => toggleProvince(province)} />
+            <span className="label-text">{province}</span>
+          </label>
+        ))}
+      </div>
+      <select className="select select-bordered select-sm w-full mt-1" value={target} onChange={(event) => setTarget(event.target.value)}>
+        <option value="">Other state region...</option>
+        {regionNames.filter((name) => name !== region.name).map((name) => <option key={name} value={name}>{name.replace('s:', '')}</option>)}
+      </select>
+      <div className="join mt-1">
+        <button className="btn btn-sm join-item" disabled={!target || selectedProvinces.length === 0 || editing} onClick={() => editRegion('move_to_state_region', { provinces: selectedProvinces, state: target })}>Move provinces</button>
+        <button className="btn btn-sm join-item" disabled={!target || editing} onClick={() => editRegion('merge_state_regions', { from: region.name, into: target })}>Merge into</button>
+      </div>
+      <div className="join mt-1">
+        <input type="text" className="input input-bordered input-sm join-item grow" placeholder="STATE_NEW_KEY" value={newState} onChange={(event) => setNewState(event.target.value)} />
+        <button className="btn btn-sm join-item" disabled={!newState || selectedProvinces.length === 0 || editing} onClick={() => editRegion('split_state_region', { state: region.name, provinces: selectedProvinces, newState })}>Split off</button>
+      </div>
+    </div>
+  )
+}